@@ -1,4 +1,4 @@
-CREATE TABLE auths (
+CREATE TABLE IF NOT EXISTS auths (
     id VARCHAR(255) PRIMARY KEY NOT NULL,
     email VARCHAR(255) NOT NULL UNIQUE,
     hash VARCHAR(255) NOT NULL,
@@ -6,4 +6,4 @@ CREATE TABLE auths (
     community_id VARCHAR(100) NOT NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     last_update TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
-);
\ No newline at end of file
+);
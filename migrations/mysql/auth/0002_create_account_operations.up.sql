@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS account_operations (
+    id VARCHAR(100) PRIMARY KEY NOT NULL,
+    community_id VARCHAR(100) NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    username VARCHAR(255),
+    status VARCHAR(20) NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL,
+    last_modified TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX account_operations_status (status, created_at)
+);
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS likes;
+DROP TABLE IF EXISTS replies;
+DROP TABLE IF EXISTS expression_posts;
+DROP TABLE IF EXISTS user_profiles;
@@ -1,11 +1,11 @@
-CREATE TABLE user_profiles(
+CREATE TABLE IF NOT EXISTS user_profiles (
     id VARCHAR(100) PRIMARY KEY,
     avatar TEXT,
     username VARCHAR(255) NOT NULL UNIQUE,
     last_modified TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
 );
 
-CREATE TABLE expression_posts(
+CREATE TABLE IF NOT EXISTS expression_posts (
     id VARCHAR(100) PRIMARY KEY,
     title TEXT NOT NULL,
     subtitle TEXT,
@@ -18,7 +18,7 @@ CREATE TABLE expression_posts(
     FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
 );
 
-CREATE TABLE replies (
+CREATE TABLE IF NOT EXISTS replies (
     id VARCHAR(100) PRIMARY KEY,
     author VARCHAR(100) NOT NULL,
     parent VARCHAR(100) NOT NULL,
@@ -28,7 +28,7 @@ CREATE TABLE replies (
     FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
 );
 
-CREATE TABLE likes (
+CREATE TABLE IF NOT EXISTS likes (
     parent_id VARCHAR(255) NOT NULL,
     author VARCHAR(100) NOT NULL
 );
@@ -0,0 +1,12 @@
+ALTER TABLE replies
+    DROP INDEX replies_deleted_at,
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
+
+ALTER TABLE expression_posts
+    DROP INDEX expression_posts_deleted_at,
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
+
+ALTER TABLE user_profiles
+    DROP COLUMN is_moderator;
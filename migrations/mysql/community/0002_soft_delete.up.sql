@@ -0,0 +1,12 @@
+ALTER TABLE user_profiles
+    ADD COLUMN is_moderator BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE expression_posts
+    ADD COLUMN deleted_at TIMESTAMP NULL DEFAULT NULL,
+    ADD COLUMN deleted_by VARCHAR(100) NULL DEFAULT NULL,
+    ADD INDEX expression_posts_deleted_at (deleted_at);
+
+ALTER TABLE replies
+    ADD COLUMN deleted_at TIMESTAMP NULL DEFAULT NULL,
+    ADD COLUMN deleted_by VARCHAR(100) NULL DEFAULT NULL,
+    ADD INDEX replies_deleted_at (deleted_at);
@@ -0,0 +1,5 @@
+ALTER TABLE likes DROP INDEX likes_parent_author;
+
+ALTER TABLE expression_posts
+    DROP COLUMN reply_count,
+    DROP COLUMN like_count;
@@ -0,0 +1,20 @@
+ALTER TABLE expression_posts
+    ADD COLUMN like_count INT NOT NULL DEFAULT 0,
+    ADD COLUMN reply_count INT NOT NULL DEFAULT 0;
+
+-- A user likes a post at most once
+CREATE TABLE likes_distinct AS SELECT DISTINCT parent_id, author FROM likes;
+DELETE FROM likes;
+INSERT INTO likes (parent_id, author) SELECT parent_id, author FROM likes_distinct;
+DROP TABLE likes_distinct;
+ALTER TABLE likes ADD UNIQUE KEY likes_parent_author (parent_id, author);
+
+-- Counter updates must not count as edits, so last_modified is kept as is
+UPDATE expression_posts AS post
+SET
+    like_count = (SELECT COUNT(*) FROM likes WHERE likes.parent_id = post.id),
+    reply_count = (
+        SELECT COUNT(*) FROM replies
+        WHERE replies.parent = post.id AND replies.deleted_at IS NULL
+    ),
+    last_modified = last_modified;
@@ -0,0 +1 @@
+ALTER TABLE replies DROP INDEX replies_parent;
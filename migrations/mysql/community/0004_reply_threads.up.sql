@@ -0,0 +1 @@
+ALTER TABLE replies ADD INDEX replies_parent (parent, created_at);
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS reply_edits;
+
+ALTER TABLE replies
+    DROP COLUMN edited_at;
@@ -0,0 +1,12 @@
+ALTER TABLE replies
+    ADD COLUMN edited_at TIMESTAMP NULL DEFAULT NULL;
+
+CREATE TABLE IF NOT EXISTS reply_edits (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    reply_id VARCHAR(100) NOT NULL,
+    content TEXT NOT NULL,
+    edited_by VARCHAR(100) NOT NULL,
+    replaced_at TIMESTAMP NOT NULL,
+    INDEX reply_edits_reply_id (reply_id),
+    FOREIGN KEY (reply_id) REFERENCES replies(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+ALTER TABLE expression_posts
+    DROP INDEX expression_posts_hot_score,
+    DROP COLUMN hot_score;
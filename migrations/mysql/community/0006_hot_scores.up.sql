@@ -0,0 +1,5 @@
+-- Trending feeds rank posts by this score. It starts at zero and is filled in
+-- by the periodic trending job
+ALTER TABLE expression_posts
+    ADD COLUMN hot_score DOUBLE NOT NULL DEFAULT 0,
+    ADD INDEX expression_posts_hot_score (hot_score, id);
@@ -0,0 +1,2 @@
+ALTER TABLE replies DROP INDEX replies_search;
+ALTER TABLE expression_posts DROP INDEX expression_posts_search;
@@ -0,0 +1,5 @@
+-- Full-text indexes. InnoDB updates them with every insert, edit and delete
+ALTER TABLE expression_posts
+    ADD FULLTEXT INDEX expression_posts_search (title, subtitle, content_value);
+ALTER TABLE replies
+    ADD FULLTEXT INDEX replies_search (content);
@@ -0,0 +1,28 @@
+-- Topics posts can be tagged with. The list starts with a curated set and
+-- moderators extend it
+CREATE TABLE IF NOT EXISTS topics (
+    name VARCHAR(32) PRIMARY KEY,
+    created_by VARCHAR(100),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT IGNORE INTO topics (name) VALUES
+    ('anxiety'), ('depression'), ('grief'), ('recovery'), ('stress'), ('loneliness'), ('relationships'), ('family'), ('work'), ('burnout'), ('self-care'), ('mindfulness'), ('sleep'), ('trauma'), ('addiction');
+
+CREATE TABLE IF NOT EXISTS post_tags (
+    post_id VARCHAR(100) NOT NULL,
+    topic VARCHAR(32) NOT NULL,
+    PRIMARY KEY (post_id, topic),
+    INDEX post_tags_topic (topic, post_id),
+    FOREIGN KEY (post_id) REFERENCES expression_posts(id) ON DELETE CASCADE,
+    FOREIGN KEY (topic) REFERENCES topics(name) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS topic_follows (
+    user_id VARCHAR(100) NOT NULL,
+    topic VARCHAR(32) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, topic),
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (topic) REFERENCES topics(name) ON DELETE CASCADE
+);
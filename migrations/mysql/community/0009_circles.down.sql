@@ -0,0 +1,7 @@
+ALTER TABLE expression_posts
+    DROP FOREIGN KEY expression_posts_circle_fk,
+    DROP INDEX expression_posts_circle,
+    DROP COLUMN circle_id;
+
+DROP TABLE IF EXISTS circle_members;
+DROP TABLE IF EXISTS circles;
@@ -0,0 +1,31 @@
+-- Circles are sub-communities with their own members and feeds. Joining a
+-- `request` circle needs the approval of its owners or moderators. Names
+-- are unique under the case insensitive default collation
+CREATE TABLE IF NOT EXISTS circles (
+    id VARCHAR(100) PRIMARY KEY,
+    name VARCHAR(60) NOT NULL UNIQUE,
+    description TEXT,
+    rules TEXT NOT NULL,
+    visibility VARCHAR(16) NOT NULL CHECK (visibility IN ('public', 'request')),
+    created_by VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS circle_members (
+    circle_id VARCHAR(100) NOT NULL,
+    user_id VARCHAR(100) NOT NULL,
+    role VARCHAR(16) NOT NULL CHECK (role IN ('owner', 'moderator', 'member')),
+    status VARCHAR(16) NOT NULL CHECK (status IN ('active', 'pending')),
+    joined_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (circle_id, user_id),
+    INDEX circle_members_user (user_id),
+    FOREIGN KEY (circle_id) REFERENCES circles(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+
+-- Posts outside any circle keep a NULL circle and stay in the global feeds
+ALTER TABLE expression_posts
+    ADD COLUMN circle_id VARCHAR(100) NULL DEFAULT NULL,
+    ADD INDEX expression_posts_circle (circle_id, created_at),
+    ADD CONSTRAINT expression_posts_circle_fk
+        FOREIGN KEY (circle_id) REFERENCES circles(id) ON DELETE CASCADE;
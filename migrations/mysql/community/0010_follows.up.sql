@@ -0,0 +1,12 @@
+-- Users following other users. Their posts make up the follower's home feed
+-- along with posts in the topics they follow
+CREATE TABLE IF NOT EXISTS user_follows (
+    follower_id VARCHAR(100) NOT NULL,
+    followee_id VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (follower_id, followee_id),
+    INDEX user_follows_followee (followee_id, created_at),
+    INDEX user_follows_follower (follower_id, created_at),
+    FOREIGN KEY (follower_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
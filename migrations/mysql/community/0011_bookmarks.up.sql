@@ -0,0 +1,35 @@
+-- Posts and replies users saved for later, each optionally filed under one of
+-- the user's named collections. Bookmarks of soft deleted content stay
+-- hidden until a restore and go away with the content when it is purged.
+-- MySQL refuses CHECK constraints on columns with cascading foreign keys, so
+-- triggers make sure every bookmark saves exactly one post or reply
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id VARCHAR(100) PRIMARY KEY,
+    user_id VARCHAR(100) NOT NULL,
+    post_id VARCHAR(100),
+    reply_id VARCHAR(100),
+    collection VARCHAR(60),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, post_id),
+    UNIQUE (user_id, reply_id),
+    INDEX bookmarks_user (user_id, created_at),
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES expression_posts(id) ON DELETE CASCADE,
+    FOREIGN KEY (reply_id) REFERENCES replies(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER bookmarks_one_target_insert BEFORE INSERT ON bookmarks
+FOR EACH ROW
+BEGIN
+    IF (NEW.post_id IS NULL) = (NEW.reply_id IS NULL) THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'A bookmark saves exactly one post or reply';
+    END IF;
+END;
+
+CREATE TRIGGER bookmarks_one_target_update BEFORE UPDATE ON bookmarks
+FOR EACH ROW
+BEGIN
+    IF (NEW.post_id IS NULL) = (NEW.reply_id IS NULL) THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'A bookmark saves exactly one post or reply';
+    END IF;
+END;
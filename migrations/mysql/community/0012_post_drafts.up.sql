@@ -0,0 +1,20 @@
+-- Unpublished posts. They live apart from expression_posts so feeds, search
+-- and counters never see them, and every field may stay empty until the
+-- draft is published. The scheduler publishes drafts once `publish_at` passes
+CREATE TABLE IF NOT EXISTS post_drafts (
+    id VARCHAR(100) PRIMARY KEY,
+    author VARCHAR(100) NOT NULL,
+    title TEXT,
+    subtitle TEXT,
+    cover_image TEXT,
+    content_type ENUM('text', 'image'),
+    content_value TEXT,
+    tags TEXT,
+    circle_id VARCHAR(100),
+    publish_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX post_drafts_author (author, last_modified),
+    INDEX post_drafts_publish_at (publish_at),
+    FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS auths;
+DROP FUNCTION IF EXISTS touch_last_update();
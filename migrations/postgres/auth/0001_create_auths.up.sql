@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS auths (
+    id VARCHAR(255) PRIMARY KEY NOT NULL,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    hash VARCHAR(255) NOT NULL,
+    refresh_token VARCHAR(300),
+    community_id VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_update TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Postgres has no ON UPDATE clause for column defaults
+CREATE OR REPLACE FUNCTION touch_last_update() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.last_update = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auths_last_update
+BEFORE UPDATE ON auths
+FOR EACH ROW EXECUTE FUNCTION touch_last_update();
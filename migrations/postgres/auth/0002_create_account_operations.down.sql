@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS account_operations;
+DROP FUNCTION IF EXISTS touch_last_modified();
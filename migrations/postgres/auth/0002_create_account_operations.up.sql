@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS account_operations (
+    id VARCHAR(100) PRIMARY KEY NOT NULL,
+    community_id VARCHAR(100) NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    username VARCHAR(255),
+    status VARCHAR(20) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS account_operations_status ON account_operations (status, created_at);
+
+CREATE OR REPLACE FUNCTION touch_last_modified() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.last_modified = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER account_operations_last_modified
+BEFORE UPDATE ON account_operations
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
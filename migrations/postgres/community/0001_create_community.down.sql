@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS likes;
+DROP TABLE IF EXISTS replies;
+DROP TABLE IF EXISTS expression_posts;
+DROP TABLE IF EXISTS user_profiles;
+DROP FUNCTION IF EXISTS touch_last_modified();
+DROP TYPE IF EXISTS expression_content_type;
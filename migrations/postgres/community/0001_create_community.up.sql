@@ -0,0 +1,54 @@
+CREATE TYPE expression_content_type AS ENUM ('text', 'image');
+
+-- Postgres has no ON UPDATE clause for column defaults
+CREATE OR REPLACE FUNCTION touch_last_modified() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.last_modified = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE IF NOT EXISTS user_profiles (
+    id VARCHAR(100) PRIMARY KEY,
+    avatar TEXT,
+    username VARCHAR(255) NOT NULL UNIQUE,
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS expression_posts (
+    id VARCHAR(100) PRIMARY KEY,
+    title TEXT NOT NULL,
+    subtitle TEXT,
+    cover_image TEXT,
+    author VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    content_type expression_content_type NOT NULL,
+    content_value TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS replies (
+    id VARCHAR(100) PRIMARY KEY,
+    author VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    parent VARCHAR(100) NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS likes (
+    parent_id VARCHAR(255) NOT NULL,
+    author VARCHAR(100) NOT NULL
+);
+
+CREATE TRIGGER user_profiles_last_modified
+BEFORE UPDATE ON user_profiles
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
+
+CREATE TRIGGER expression_posts_last_modified
+BEFORE UPDATE ON expression_posts
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
+
+CREATE TRIGGER replies_last_modified
+BEFORE UPDATE ON replies
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
@@ -0,0 +1,12 @@
+DROP INDEX IF EXISTS replies_deleted_at;
+ALTER TABLE replies
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
+
+DROP INDEX IF EXISTS expression_posts_deleted_at;
+ALTER TABLE expression_posts
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
+
+ALTER TABLE user_profiles
+    DROP COLUMN is_moderator;
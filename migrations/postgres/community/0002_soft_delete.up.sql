@@ -0,0 +1,12 @@
+ALTER TABLE user_profiles
+    ADD COLUMN is_moderator BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE expression_posts
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by VARCHAR(100);
+CREATE INDEX IF NOT EXISTS expression_posts_deleted_at ON expression_posts (deleted_at);
+
+ALTER TABLE replies
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by VARCHAR(100);
+CREATE INDEX IF NOT EXISTS replies_deleted_at ON replies (deleted_at);
@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS likes_parent_author;
+
+DROP TRIGGER IF EXISTS expression_posts_last_modified ON expression_posts;
+CREATE TRIGGER expression_posts_last_modified
+BEFORE UPDATE ON expression_posts
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
+
+ALTER TABLE expression_posts
+    DROP COLUMN reply_count,
+    DROP COLUMN like_count;
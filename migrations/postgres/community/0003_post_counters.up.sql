@@ -0,0 +1,27 @@
+ALTER TABLE expression_posts
+    ADD COLUMN like_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN reply_count INTEGER NOT NULL DEFAULT 0;
+
+-- Counter updates must not count as edits, so last_modified is only touched
+-- when the post itself changes
+DROP TRIGGER IF EXISTS expression_posts_last_modified ON expression_posts;
+CREATE TRIGGER expression_posts_last_modified
+BEFORE UPDATE OF title, subtitle, cover_image, content_type, content_value, deleted_at, deleted_by
+ON expression_posts
+FOR EACH ROW EXECUTE FUNCTION touch_last_modified();
+
+-- A user likes a post at most once
+DELETE FROM likes AS duplicate
+USING likes AS kept
+WHERE duplicate.ctid > kept.ctid
+    AND duplicate.parent_id = kept.parent_id
+    AND duplicate.author = kept.author;
+CREATE UNIQUE INDEX IF NOT EXISTS likes_parent_author ON likes (parent_id, author);
+
+UPDATE expression_posts AS post
+SET
+    like_count = (SELECT COUNT(*) FROM likes WHERE likes.parent_id = post.id),
+    reply_count = (
+        SELECT COUNT(*) FROM replies
+        WHERE replies.parent = post.id AND replies.deleted_at IS NULL
+    );
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS reply_edits;
+
+ALTER TABLE replies DROP COLUMN edited_at;
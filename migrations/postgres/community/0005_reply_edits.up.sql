@@ -0,0 +1,10 @@
+ALTER TABLE replies ADD COLUMN edited_at TIMESTAMPTZ;
+
+CREATE TABLE IF NOT EXISTS reply_edits (
+    id BIGSERIAL PRIMARY KEY,
+    reply_id VARCHAR(100) NOT NULL REFERENCES replies(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    edited_by VARCHAR(100) NOT NULL,
+    replaced_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS reply_edits_reply_id ON reply_edits (reply_id);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS replies_search;
+ALTER TABLE replies DROP COLUMN search_vector;
+DROP INDEX IF EXISTS expression_posts_search;
+ALTER TABLE expression_posts DROP COLUMN search_vector;
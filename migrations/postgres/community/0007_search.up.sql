@@ -0,0 +1,12 @@
+-- Full-text search documents. Generated columns keep them in step with every
+-- insert and edit, and deleted rows drop out of the GIN indexes with them
+ALTER TABLE expression_posts ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(subtitle, '')), 'B') ||
+    setweight(to_tsvector('english', coalesce(content_value, '')), 'C')
+) STORED;
+CREATE INDEX IF NOT EXISTS expression_posts_search ON expression_posts USING GIN (search_vector);
+
+ALTER TABLE replies ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+CREATE INDEX IF NOT EXISTS replies_search ON replies USING GIN (search_vector);
@@ -0,0 +1,25 @@
+-- Topics posts can be tagged with. The list starts with a curated set and
+-- moderators extend it
+CREATE TABLE IF NOT EXISTS topics (
+    name VARCHAR(32) PRIMARY KEY,
+    created_by VARCHAR(100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO topics (name) VALUES
+    ('anxiety'), ('depression'), ('grief'), ('recovery'), ('stress'), ('loneliness'), ('relationships'), ('family'), ('work'), ('burnout'), ('self-care'), ('mindfulness'), ('sleep'), ('trauma'), ('addiction')
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS post_tags (
+    post_id VARCHAR(100) NOT NULL REFERENCES expression_posts(id) ON DELETE CASCADE,
+    topic VARCHAR(32) NOT NULL REFERENCES topics(name) ON DELETE CASCADE,
+    PRIMARY KEY (post_id, topic)
+);
+CREATE INDEX IF NOT EXISTS post_tags_topic ON post_tags (topic, post_id);
+
+CREATE TABLE IF NOT EXISTS topic_follows (
+    user_id VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    topic VARCHAR(32) NOT NULL REFERENCES topics(name) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, topic)
+);
@@ -0,0 +1,27 @@
+-- Circles are sub-communities with their own members and feeds. Joining a
+-- `request` circle needs the approval of its owners or moderators
+CREATE TABLE IF NOT EXISTS circles (
+    id VARCHAR(100) PRIMARY KEY,
+    name VARCHAR(60) NOT NULL,
+    description TEXT,
+    rules TEXT NOT NULL DEFAULT '',
+    visibility VARCHAR(16) NOT NULL CHECK (visibility IN ('public', 'request')),
+    created_by VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX IF NOT EXISTS circles_name ON circles (LOWER(name));
+
+CREATE TABLE IF NOT EXISTS circle_members (
+    circle_id VARCHAR(100) NOT NULL REFERENCES circles(id) ON DELETE CASCADE,
+    user_id VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL CHECK (role IN ('owner', 'moderator', 'member')),
+    status VARCHAR(16) NOT NULL CHECK (status IN ('active', 'pending')),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (circle_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS circle_members_user ON circle_members (user_id);
+
+-- Posts outside any circle keep a NULL circle and stay in the global feeds
+ALTER TABLE expression_posts
+    ADD COLUMN circle_id VARCHAR(100) REFERENCES circles(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS expression_posts_circle ON expression_posts (circle_id, created_at);
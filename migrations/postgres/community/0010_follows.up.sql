@@ -0,0 +1,10 @@
+-- Users following other users. Their posts make up the follower's home feed
+-- along with posts in the topics they follow
+CREATE TABLE IF NOT EXISTS user_follows (
+    follower_id VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    followee_id VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (follower_id, followee_id)
+);
+CREATE INDEX IF NOT EXISTS user_follows_followee ON user_follows (followee_id, created_at);
+CREATE INDEX IF NOT EXISTS user_follows_follower ON user_follows (follower_id, created_at);
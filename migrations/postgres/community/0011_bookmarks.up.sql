@@ -0,0 +1,15 @@
+-- Posts and replies users saved for later, each optionally filed under one of
+-- the user's named collections. Bookmarks of soft deleted content stay
+-- hidden until a restore and go away with the content when it is purged
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id VARCHAR(100) PRIMARY KEY,
+    user_id VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    post_id VARCHAR(100) REFERENCES expression_posts(id) ON DELETE CASCADE,
+    reply_id VARCHAR(100) REFERENCES replies(id) ON DELETE CASCADE,
+    collection VARCHAR(60),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (user_id, post_id),
+    UNIQUE (user_id, reply_id),
+    CHECK ((post_id IS NULL) <> (reply_id IS NULL))
+);
+CREATE INDEX IF NOT EXISTS bookmarks_user ON bookmarks (user_id, created_at);
@@ -0,0 +1,19 @@
+-- Unpublished posts. They live apart from expression_posts so feeds, search
+-- and counters never see them, and every field may stay empty until the
+-- draft is published. The scheduler publishes drafts once `publish_at` passes
+CREATE TABLE IF NOT EXISTS post_drafts (
+    id VARCHAR(100) PRIMARY KEY,
+    author VARCHAR(100) NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+    title TEXT,
+    subtitle TEXT,
+    cover_image TEXT,
+    content_type expression_content_type,
+    content_value TEXT,
+    tags TEXT,
+    circle_id VARCHAR(100),
+    publish_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX IF NOT EXISTS post_drafts_author ON post_drafts (author, last_modified);
+CREATE INDEX IF NOT EXISTS post_drafts_publish_at ON post_drafts (publish_at);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS auths (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    hash TEXT NOT NULL,
+    refresh_token TEXT,
+    community_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    last_update DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- SQLite has no ON UPDATE clause for column defaults
+CREATE TRIGGER IF NOT EXISTS auths_last_update
+AFTER UPDATE ON auths
+FOR EACH ROW
+BEGIN
+    UPDATE auths SET last_update = CURRENT_TIMESTAMP WHERE id = OLD.id;
+END;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS account_operations (
+    id TEXT PRIMARY KEY NOT NULL,
+    community_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    username TEXT,
+    status TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at DATETIME NOT NULL,
+    last_modified DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS account_operations_status ON account_operations (status, created_at);
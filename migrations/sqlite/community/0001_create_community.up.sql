@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS user_profiles (
+    id TEXT PRIMARY KEY,
+    avatar TEXT,
+    username TEXT NOT NULL UNIQUE,
+    last_modified DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- SQLite has no ENUM type, so the allowed values are enforced with a CHECK
+CREATE TABLE IF NOT EXISTS expression_posts (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    subtitle TEXT,
+    cover_image TEXT,
+    author TEXT NOT NULL,
+    content_type TEXT NOT NULL CHECK (content_type IN ('text', 'image')),
+    content_value TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    last_modified DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS replies (
+    id TEXT PRIMARY KEY,
+    author TEXT NOT NULL,
+    parent TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    last_modified DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS likes (
+    parent_id TEXT NOT NULL,
+    author TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+ALTER TABLE user_profiles ADD COLUMN is_moderator BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE expression_posts ADD COLUMN deleted_at DATETIME;
+ALTER TABLE expression_posts ADD COLUMN deleted_by TEXT;
+CREATE INDEX IF NOT EXISTS expression_posts_deleted_at ON expression_posts (deleted_at);
+
+ALTER TABLE replies ADD COLUMN deleted_at DATETIME;
+ALTER TABLE replies ADD COLUMN deleted_by TEXT;
+CREATE INDEX IF NOT EXISTS replies_deleted_at ON replies (deleted_at);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS likes_parent_author;
+
+ALTER TABLE expression_posts DROP COLUMN reply_count;
+ALTER TABLE expression_posts DROP COLUMN like_count;
@@ -0,0 +1,15 @@
+ALTER TABLE expression_posts ADD COLUMN like_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE expression_posts ADD COLUMN reply_count INTEGER NOT NULL DEFAULT 0;
+
+-- A user likes a post at most once
+DELETE FROM likes
+WHERE rowid NOT IN (SELECT MIN(rowid) FROM likes GROUP BY parent_id, author);
+CREATE UNIQUE INDEX IF NOT EXISTS likes_parent_author ON likes (parent_id, author);
+
+UPDATE expression_posts
+SET
+    like_count = (SELECT COUNT(*) FROM likes WHERE likes.parent_id = expression_posts.id),
+    reply_count = (
+        SELECT COUNT(*) FROM replies
+        WHERE replies.parent = expression_posts.id AND replies.deleted_at IS NULL
+    );
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS replies_parent ON replies (parent, created_at);
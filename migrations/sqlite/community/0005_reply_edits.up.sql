@@ -0,0 +1,11 @@
+ALTER TABLE replies ADD COLUMN edited_at DATETIME;
+
+CREATE TABLE IF NOT EXISTS reply_edits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    reply_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    edited_by TEXT NOT NULL,
+    replaced_at DATETIME NOT NULL,
+    FOREIGN KEY (reply_id) REFERENCES replies(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS reply_edits_reply_id ON reply_edits (reply_id);
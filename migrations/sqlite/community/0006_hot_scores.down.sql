@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS expression_posts_hot_score;
+ALTER TABLE expression_posts DROP COLUMN hot_score;
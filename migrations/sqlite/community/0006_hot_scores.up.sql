@@ -0,0 +1,4 @@
+-- Trending feeds rank posts by this score. It starts at zero and is filled in
+-- by the periodic trending job
+ALTER TABLE expression_posts ADD COLUMN hot_score REAL NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS expression_posts_hot_score ON expression_posts (hot_score, id);
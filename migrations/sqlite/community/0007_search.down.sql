@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS reply_search_update;
+DROP TRIGGER IF EXISTS reply_search_delete;
+DROP TRIGGER IF EXISTS reply_search_insert;
+DROP TABLE IF EXISTS reply_search;
+DROP TRIGGER IF EXISTS post_search_update;
+DROP TRIGGER IF EXISTS post_search_delete;
+DROP TRIGGER IF EXISTS post_search_insert;
+DROP TABLE IF EXISTS post_search;
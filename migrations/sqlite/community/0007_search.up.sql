@@ -0,0 +1,56 @@
+-- Full-text indexes over posts and replies. They read their text from the
+-- tables themselves, matched by rowid, and triggers keep them in step with
+-- every insert, edit and delete. VACUUM may renumber rowids, so run
+-- `INSERT INTO post_search(post_search) VALUES ('rebuild')` and the same for
+-- reply_search after one.
+CREATE VIRTUAL TABLE IF NOT EXISTS post_search USING fts5(
+    title,
+    subtitle,
+    content_value,
+    content = 'expression_posts',
+    content_rowid = 'rowid',
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS post_search_insert AFTER INSERT ON expression_posts BEGIN
+    INSERT INTO post_search (rowid, title, subtitle, content_value)
+    VALUES (new.rowid, new.title, new.subtitle, new.content_value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS post_search_delete AFTER DELETE ON expression_posts BEGIN
+    INSERT INTO post_search (post_search, rowid, title, subtitle, content_value)
+    VALUES ('delete', old.rowid, old.title, old.subtitle, old.content_value);
+END;
+
+CREATE TRIGGER IF NOT EXISTS post_search_update
+AFTER UPDATE OF title, subtitle, content_value ON expression_posts BEGIN
+    INSERT INTO post_search (post_search, rowid, title, subtitle, content_value)
+    VALUES ('delete', old.rowid, old.title, old.subtitle, old.content_value);
+    INSERT INTO post_search (rowid, title, subtitle, content_value)
+    VALUES (new.rowid, new.title, new.subtitle, new.content_value);
+END;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS reply_search USING fts5(
+    content,
+    content = 'replies',
+    content_rowid = 'rowid',
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS reply_search_insert AFTER INSERT ON replies BEGIN
+    INSERT INTO reply_search (rowid, content) VALUES (new.rowid, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS reply_search_delete AFTER DELETE ON replies BEGIN
+    INSERT INTO reply_search (reply_search, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS reply_search_update AFTER UPDATE OF content ON replies BEGIN
+    INSERT INTO reply_search (reply_search, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+    INSERT INTO reply_search (rowid, content) VALUES (new.rowid, new.content);
+END;
+
+INSERT INTO post_search (post_search) VALUES ('rebuild');
+INSERT INTO reply_search (reply_search) VALUES ('rebuild');
@@ -0,0 +1,28 @@
+-- Topics posts can be tagged with. The list starts with a curated set and
+-- moderators extend it
+CREATE TABLE IF NOT EXISTS topics (
+    name TEXT PRIMARY KEY,
+    created_by TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO topics (name) VALUES
+    ('anxiety'), ('depression'), ('grief'), ('recovery'), ('stress'), ('loneliness'), ('relationships'), ('family'), ('work'), ('burnout'), ('self-care'), ('mindfulness'), ('sleep'), ('trauma'), ('addiction');
+
+CREATE TABLE IF NOT EXISTS post_tags (
+    post_id TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    PRIMARY KEY (post_id, topic),
+    FOREIGN KEY (post_id) REFERENCES expression_posts(id) ON DELETE CASCADE,
+    FOREIGN KEY (topic) REFERENCES topics(name) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS post_tags_topic ON post_tags (topic, post_id);
+
+CREATE TABLE IF NOT EXISTS topic_follows (
+    user_id TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, topic),
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (topic) REFERENCES topics(name) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS expression_posts_circle;
+ALTER TABLE expression_posts DROP COLUMN circle_id;
+
+DROP TABLE IF EXISTS circle_members;
+DROP TABLE IF EXISTS circles;
@@ -0,0 +1,28 @@
+-- Circles are sub-communities with their own members and feeds. Joining a
+-- `request` circle needs the approval of its owners or moderators
+CREATE TABLE IF NOT EXISTS circles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT,
+    rules TEXT NOT NULL DEFAULT '',
+    visibility TEXT NOT NULL CHECK (visibility IN ('public', 'request')),
+    created_by TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS circle_members (
+    circle_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'moderator', 'member')),
+    status TEXT NOT NULL CHECK (status IN ('active', 'pending')),
+    joined_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (circle_id, user_id),
+    FOREIGN KEY (circle_id) REFERENCES circles(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS circle_members_user ON circle_members (user_id);
+
+-- Posts outside any circle keep a NULL circle and stay in the global feeds.
+-- SQLite cannot drop a column with a foreign key, so none is declared here
+ALTER TABLE expression_posts ADD COLUMN circle_id TEXT;
+CREATE INDEX IF NOT EXISTS expression_posts_circle ON expression_posts (circle_id, created_at);
@@ -0,0 +1,12 @@
+-- Users following other users. Their posts make up the follower's home feed
+-- along with posts in the topics they follow
+CREATE TABLE IF NOT EXISTS user_follows (
+    follower_id TEXT NOT NULL,
+    followee_id TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (follower_id, followee_id),
+    FOREIGN KEY (follower_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS user_follows_followee ON user_follows (followee_id, created_at);
+CREATE INDEX IF NOT EXISTS user_follows_follower ON user_follows (follower_id, created_at);
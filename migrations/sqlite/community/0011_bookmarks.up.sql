@@ -0,0 +1,18 @@
+-- Posts and replies users saved for later, each optionally filed under one of
+-- the user's named collections. Bookmarks of soft deleted content stay
+-- hidden until a restore and go away with the content when it is purged
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    post_id TEXT,
+    reply_id TEXT,
+    collection TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, post_id),
+    UNIQUE (user_id, reply_id),
+    CHECK ((post_id IS NULL) <> (reply_id IS NULL)),
+    FOREIGN KEY (user_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES expression_posts(id) ON DELETE CASCADE,
+    FOREIGN KEY (reply_id) REFERENCES replies(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS bookmarks_user ON bookmarks (user_id, created_at);
@@ -0,0 +1,20 @@
+-- Unpublished posts. They live apart from expression_posts so feeds, search
+-- and counters never see them, and every field may stay empty until the
+-- draft is published. The scheduler publishes drafts once `publish_at` passes
+CREATE TABLE IF NOT EXISTS post_drafts (
+    id TEXT PRIMARY KEY,
+    author TEXT NOT NULL,
+    title TEXT,
+    subtitle TEXT,
+    cover_image TEXT,
+    content_type TEXT CHECK (content_type IN ('text', 'image')),
+    content_value TEXT,
+    tags TEXT,
+    circle_id TEXT,
+    publish_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (author) REFERENCES user_profiles(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS post_drafts_author ON post_drafts (author, last_modified);
+CREATE INDEX IF NOT EXISTS post_drafts_publish_at ON post_drafts (publish_at);
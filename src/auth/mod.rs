@@ -1,4 +1,4 @@
-pub use models::{AccessToken, Auth, RefreshToken};
+pub use models::{signing_keys_loaded, AccessToken, AccountSaga, Auth, OrphanPolicy, RefreshToken};
 pub use mutations::Mutation;
 pub use queries::Query;
 
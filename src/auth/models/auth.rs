@@ -1,15 +1,15 @@
 use async_graphql::InputObject;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, Row};
+use sqlx::FromRow;
 use ulid::Ulid;
 use uuid::Uuid;
 
-use crate::{community::UserProfile, db::DbController};
+use crate::db::{AccountOperation, AccountOperationKind, AuthRecord, DbController};
 
 use super::{
     email::Email,
     jwt::{AccessToken, RefreshToken, Tokens},
-    Password,
+    AccountSaga, Password,
 };
 
 #[derive(Debug, FromRow)]
@@ -45,11 +45,7 @@ impl Auth {
     }
 
     async fn does_email_exist(db: &DbController, email: &str) -> bool {
-        sqlx::query("SELECT id FROM auths WHERE email = ?")
-            .bind(email)
-            .fetch_one(&db.auth_pool)
-            .await
-            .is_ok()
+        db.auths.email_exists(email).await.unwrap_or(false)
     }
 
     pub async fn register(
@@ -63,38 +59,36 @@ impl Auth {
             return Err("User already exists".to_string());
         }
 
-        let Ok(mut tx) = db.auth_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction.");
-            return Err("Server error. Please try again".to_string());
-        };
-
         // Create User representation for database
         let (auth, access_token) = Auth::new(email, password.hash()?)?;
 
-        if let Err(err) =
-            sqlx::query("INSERT INTO auths (id, email, hash, community_id, refresh_token) VALUES (?, ?, ?, ?, ?)")
-                .bind(auth.id.to_string())
-                .bind(auth.email.as_str())
-                .bind(auth.hash)
-                .bind(auth.community_id.to_string())
-                .bind(auth.refresh_token.as_str())
-                .execute(&mut *tx)
-                .await
+        // The auth and the pending profile creation are stored together, so
+        // an interrupted registration is always finished or undone later
+        let operation = AccountOperation::new(
+            auth.community_id.to_string(),
+            AccountOperationKind::Register,
+            Some(username),
+        );
+        if let Err(err) = db
+            .auths
+            .insert(
+                &AuthRecord {
+                    id: auth.id.to_string(),
+                    email: auth.email.as_str().to_string(),
+                    hash: auth.hash,
+                    community_id: auth.community_id.to_string(),
+                    refresh_token: Some(auth.refresh_token.as_str().to_string()),
+                },
+                &operation,
+            )
+            .await
         {
             eprintln!("{:#?}", err);
-            return Err("Server error. Please try again".to_string())
+            return Err("Server error. Please try again".to_string());
         };
 
-        if UserProfile::register(db, auth.community_id.to_string(), username)
-            .await
-            .is_err()
-        {
-            return Err(
-                "There was an issue creating the user profile. Please try again.".to_string(),
-            );
-        }
+        AccountSaga::create_profile(db, &operation).await?;
 
-        let _ = tx.commit().await;
         Ok((access_token, auth.refresh_token))
     }
 
@@ -103,32 +97,24 @@ impl Auth {
         email: Email,
         password: Password,
     ) -> Result<Tokens, String> {
-        if let Ok(auth) = sqlx::query("SELECT * FROM auths WHERE email = ?")
-            .bind(email.as_str())
-            .fetch_one(&db.auth_pool)
-            .await
-        {
+        if let Ok(Some(auth)) = db.auths.find_by_email(email.as_str()).await {
             // Verify password sent by user
-            let hash: &str = auth.get("hash");
-            password.verify(hash)?;
+            password.verify(&auth.hash)?;
 
             // Generate JWT tokens
-            let auth_id: &str = auth.get("id");
-            let community_id: &str = auth.get("community_id");
-            let Ok(access_token) = AccessToken::new(community_id) else {
+            let Ok(access_token) = AccessToken::new(&auth.community_id) else {
                 eprintln!("JWT ERROR: Error creating access token in Login");
                 return Err("Server error. Please try again".to_string());
             };
-            let Ok(refresh_token) = RefreshToken::new(auth_id) else {
+            let Ok(refresh_token) = RefreshToken::new(&auth.id) else {
                 eprintln!("JWT ERROR: Error creating refresh token in Login");
                 return Err("Server error. Please try again".to_string());
             };
 
             // Update refresh token in database
-            if sqlx::query("UPDATE auths SET refresh_token = ? WHERE email = ?")
-                .bind(refresh_token.as_str())
-                .bind(email.as_str())
-                .execute(&db.auth_pool)
+            if db
+                .auths
+                .set_refresh_token(&auth.community_id, refresh_token.as_str())
                 .await
                 .is_err()
             {
@@ -142,13 +128,7 @@ impl Auth {
     }
 
     pub async fn logout(db: &DbController, id: &str) -> Result<(), String> {
-        if sqlx::query("UPDATE auths SET refresh_token = ? WHERE community_id = ?")
-            .bind("")
-            .bind(id)
-            .execute(&db.auth_pool)
-            .await
-            .is_err()
-        {
+        if db.auths.set_refresh_token(id, "").await.is_err() {
             return Err("Error logging out. Please try again".to_string());
         }
 
@@ -156,16 +136,11 @@ impl Auth {
     }
 
     pub async fn refresh(db: &DbController, id: &str) -> Result<AccessToken, String> {
-        let Ok(auth) = sqlx::query("SELECT id FROM auths WHERE id = ?")
-            .bind(id)
-            .fetch_one(&db.auth_pool)
-            .await
-        else {
+        let Ok(Some(auth)) = db.auths.find_by_id(id).await else {
             return Err("User does not exist.".to_string());
         };
 
-        let auth_id: &str = auth.get("id");
-        let Ok(token) = AccessToken::new(auth_id) else {
+        let Ok(token) = AccessToken::new(&auth.id) else {
             eprintln!("JWT ERROR: Error creating access token in Refresh");
             return Err("Server error. Please try again".to_string());
         };
@@ -178,19 +153,11 @@ impl Auth {
         email: Email,
         community_id: String,
     ) -> Result<bool, String> {
-        if sqlx::query(
-            r#"
-            UPDATE 
-                auths 
-            SET email = ? 
-            WHERE community_id = ?
-        "#,
-        )
-        .bind(email.as_str())
-        .bind(community_id)
-        .execute(&db.auth_pool)
-        .await
-        .is_err()
+        if db
+            .auths
+            .update_email(&community_id, email.as_str())
+            .await
+            .is_err()
         {
             return Err("There was a problem updating your email. Please try again.".to_string());
         };
@@ -203,19 +170,11 @@ impl Auth {
         password: Password,
         community_id: String,
     ) -> Result<bool, String> {
-        if sqlx::query(
-            r#"
-            UPDATE 
-                auths 
-            SET hash = ? 
-            WHERE community_id = ?
-        "#,
-        )
-        .bind(password.hash()?)
-        .bind(community_id)
-        .execute(&db.auth_pool)
-        .await
-        .is_err()
+        if db
+            .auths
+            .update_hash(&community_id, &password.hash()?)
+            .await
+            .is_err()
         {
             return Err(
                 "There was a problem updating your password. Please try again.".to_string(),
@@ -226,28 +185,20 @@ impl Auth {
     }
 
     pub async fn delete(db: &DbController, community_id: String) -> Result<bool, String> {
-        let Ok(mut tx) = db.auth_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction.");
-            return Err("Server error. Please try again".to_string());
-        };
-
-        if sqlx::query("DELETE FROM auths WHERE community_id = ?")
-            .bind(&community_id)
-            .execute(&mut *tx)
-            .await
-            .is_err()
-        {
+        let operation = AccountOperation::new(community_id, AccountOperationKind::Delete, None);
+        if db.auths.insert_operation(&operation).await.is_err() {
             return Err("There seems to be an issue on our end. Please try again.".to_string());
         }
 
-        if UserProfile::delete(db, community_id).await.is_err() {
-            return Err(
-                "DATABASE ERROR: Error deleting user in database. Please try again.".to_string(),
+        // Once recorded the deletion is guaranteed; the reconciler finishes it
+        // if either database fails now
+        if let Err(err) = AccountSaga::remove_account(db, &operation).await {
+            eprintln!(
+                "DATABASE ERROR: Error deleting user {}, left for reconciliation: {err}",
+                operation.community_id
             );
         }
 
-        let _ = tx.commit().await;
-
         Ok(true)
     }
 }
@@ -266,3 +217,60 @@ pub struct AuthRegistrationRequest {
     pub password: String,
     pub username: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::UserProfile;
+
+    fn set_token_secrets() {
+        std::env::set_var("ACCESS_TOKEN_SECRET", "test-access-secret");
+        std::env::set_var("REFRESH_TOKEN_SECRET", "test-refresh-secret");
+    }
+
+    fn credentials(email: &str) -> (Email, Password) {
+        (
+            Email::parse(email.to_string()).unwrap(),
+            Password::parse("Passw0rd!23".to_string()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn register_creates_profile_and_login_checks_password() {
+        set_token_secrets();
+        let db = DbController::in_memory();
+
+        let (email, password) = credentials("ada@example.com");
+        let (access_token, _) = Auth::register(&db, email, password, "ada".to_string())
+            .await
+            .unwrap();
+        let community_id = AccessToken::decode(access_token.as_str()).unwrap().sub;
+        let profile = UserProfile::get_by_id(&db, community_id).await;
+        assert!(profile.is_ok());
+
+        let (email, password) = credentials("ada@example.com");
+        assert!(Auth::login(&db, email, password).await.is_ok());
+
+        let email = Email::parse("ada@example.com".to_string()).unwrap();
+        let wrong = Password::parse("Wr0ngPass!".to_string()).unwrap();
+        assert!(Auth::login(&db, email, wrong).await.is_err());
+
+        let (email, password) = credentials("nobody@example.com");
+        assert!(Auth::login(&db, email, password).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn register_rejects_taken_email() {
+        set_token_secrets();
+        let db = DbController::in_memory();
+
+        let (email, password) = credentials("bo@example.com");
+        Auth::register(&db, email, password, "bo".to_string())
+            .await
+            .unwrap();
+
+        let (email, password) = credentials("bo@example.com");
+        let result = Auth::register(&db, email, password, "bo2".to_string()).await;
+        assert_eq!(result.unwrap_err(), "User already exists");
+    }
+}
@@ -6,6 +6,13 @@ use serde::{Deserialize, Serialize};
 
 pub type Tokens = (AccessToken, RefreshToken);
 
+/// Returns true when both token signing secrets are present in the environment.
+pub fn signing_keys_loaded() -> bool {
+    ["ACCESS_TOKEN_SECRET", "REFRESH_TOKEN_SECRET"]
+        .iter()
+        .all(|key| dotenv::var(key).is_ok_and(|secret| !secret.is_empty()))
+}
+
 #[derive(Debug)]
 pub struct AccessToken(String);
 
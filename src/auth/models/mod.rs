@@ -2,8 +2,10 @@ mod auth;
 mod email;
 mod jwt;
 mod password;
+mod saga;
 
 pub use auth::{Auth, AuthAccessRequest, AuthRegistrationRequest};
 pub use email::Email;
-pub use jwt::{AccessToken, RefreshToken};
+pub use jwt::{signing_keys_loaded, AccessToken, RefreshToken};
 pub use password::Password;
+pub use saga::{AccountSaga, OrphanPolicy};
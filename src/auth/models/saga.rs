@@ -0,0 +1,303 @@
+use std::{collections::HashSet, fmt};
+
+use chrono::Duration;
+
+use crate::{
+    community::UserProfile,
+    db::{self, AccountOperation, AccountOperationKind, AccountOperationStatus, DbController},
+};
+
+/// Time an operation is left alone before the reconciler assumes whoever
+/// started it is no longer driving it.
+const RECONCILE_GRACE_SECS: i64 = 60;
+/// Failed attempts after which a registration is rolled back instead.
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Drives account changes that span the auth and community databases.
+///
+/// Every change is first recorded in the auth database's outbox, in the same
+/// transaction as the auth write when there is one. The community side is
+/// then applied and the outbox entry closed. Anything that fails part way
+/// stays open and is finished or compensated by [`AccountSaga::reconcile`].
+pub struct AccountSaga;
+
+/// What the reconciler does about accounts that exist in only one database.
+/// Removing a profile takes its posts and replies with it, so an auth
+/// database that is empty or stale must not be able to wipe the community.
+#[derive(Debug, Clone, Copy)]
+pub enum OrphanPolicy {
+    /// Count orphans without touching them.
+    Report,
+    /// Remove at most this many orphans per run.
+    Remove(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    /// Open operations that were finished or compensated.
+    pub resolved: usize,
+    /// Open operations that are still open after this run.
+    pub unresolved: usize,
+    /// Auths that had no profile and no operation explaining why.
+    pub orphaned_auths: usize,
+    /// Profiles that had no auth and no operation explaining why.
+    pub orphaned_profiles: usize,
+    /// Orphans removed by this run.
+    pub removed: usize,
+}
+
+impl fmt::Display for ReconcileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} operations resolved, {} unresolved, {} orphaned auths and {} orphaned profiles found, {} removed",
+            self.resolved,
+            self.unresolved,
+            self.orphaned_auths,
+            self.orphaned_profiles,
+            self.removed
+        )
+    }
+}
+
+impl AccountSaga {
+    /// Creates the profile for a registration whose auth is already stored.
+    /// Any failure rolls the whole registration back.
+    pub async fn create_profile(
+        db: &DbController,
+        operation: &AccountOperation,
+    ) -> Result<(), String> {
+        let username = operation.username.clone().unwrap_or_default();
+
+        if let Err(err) = UserProfile::register(db, operation.community_id.clone(), username).await
+        {
+            Self::record_failure(db, operation, AccountOperationStatus::Compensating, &err).await;
+            if let Err(err) = Self::compensate(db, operation).await {
+                eprintln!(
+                    "SAGA_ERROR: Error compensating registration {}: {err}",
+                    operation.id
+                );
+            }
+            return Err(
+                "There was an issue creating the user profile. Please try again.".to_string(),
+            );
+        }
+
+        if db
+            .auths
+            .set_operation_status(&operation.id, AccountOperationStatus::Completed)
+            .await
+            .is_err()
+        {
+            // The reconciler sees the profile and closes the operation
+            eprintln!(
+                "SAGA_ERROR: Error completing registration {}.",
+                operation.id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Removes the profile, then the auth. The operation stays open until
+    /// both are gone.
+    pub async fn remove_account(
+        db: &DbController,
+        operation: &AccountOperation,
+    ) -> Result<(), String> {
+        if let Err(err) = UserProfile::delete(db, operation.community_id.clone()).await {
+            Self::record_failure(db, operation, AccountOperationStatus::Pending, &err).await;
+            return Err(err);
+        }
+
+        if let Err(err) = db
+            .auths
+            .delete(
+                &operation.community_id,
+                &operation.id,
+                AccountOperationStatus::Completed,
+            )
+            .await
+        {
+            Self::record_failure(
+                db,
+                operation,
+                AccountOperationStatus::Pending,
+                &err.to_string(),
+            )
+            .await;
+            return Err(err.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Undoes a registration: the profile, if it was created, then the auth.
+    async fn compensate(db: &DbController, operation: &AccountOperation) -> Result<(), String> {
+        UserProfile::delete(db, operation.community_id.clone()).await?;
+
+        if let Err(err) = db
+            .auths
+            .delete(
+                &operation.community_id,
+                &operation.id,
+                AccountOperationStatus::Compensated,
+            )
+            .await
+        {
+            return Err(err.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Rolls an open registration forward when possible. Registrations whose
+    /// username was taken in the meantime, or that keep failing, are undone.
+    async fn resume_registration(
+        db: &DbController,
+        operation: &AccountOperation,
+    ) -> Result<(), String> {
+        if operation.status == AccountOperationStatus::Compensating
+            || operation.attempts >= MAX_ATTEMPTS
+        {
+            return Self::compensate(db, operation).await;
+        }
+
+        match db.profiles.get_by_id(&operation.community_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                let username = operation.username.clone().unwrap_or_default();
+                if UserProfile::does_profile_exist(db, &operation.community_id, &username).await {
+                    return Self::compensate(db, operation).await;
+                }
+                if let Err(err) =
+                    UserProfile::register(db, operation.community_id.clone(), username).await
+                {
+                    Self::record_failure(db, operation, AccountOperationStatus::Pending, &err)
+                        .await;
+                    return Err(err);
+                }
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+
+        if let Err(err) = db
+            .auths
+            .set_operation_status(&operation.id, AccountOperationStatus::Completed)
+            .await
+        {
+            return Err(err.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Finishes open operations and looks for accounts that exist in only
+    /// one database, removing them as far as `orphans` allows. Accounts with
+    /// an operation still in its grace period are left alone, since a
+    /// request may be working on them.
+    pub async fn reconcile(
+        db: &DbController,
+        orphans: OrphanPolicy,
+    ) -> Result<ReconcileReport, String> {
+        let mut report = ReconcileReport::default();
+        let cutoff = db::now() - Duration::seconds(RECONCILE_GRACE_SECS);
+        // Timestamps are stored to the second, so rows written this very
+        // second only show up when looking past the current time
+        let horizon = db::now() + Duration::seconds(1);
+
+        let open = match db.auths.open_operations(cutoff).await {
+            Ok(open) => open,
+            Err(err) => return Err(err.to_string()),
+        };
+        for operation in open {
+            let result = match operation.kind {
+                AccountOperationKind::Register => Self::resume_registration(db, &operation).await,
+                AccountOperationKind::Delete => Self::remove_account(db, &operation).await,
+            };
+            match result {
+                Ok(()) => report.resolved += 1,
+                Err(err) => {
+                    eprintln!(
+                        "SAGA_ERROR: Error resolving operation {}: {err}",
+                        operation.id
+                    );
+                    report.unresolved += 1;
+                }
+            }
+        }
+
+        // Everything still open, including operations inside the grace period
+        let busy: HashSet<String> = match db.auths.open_operations(horizon).await {
+            Ok(open) => open.into_iter().map(|op| op.community_id).collect(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let (auths, profiles) =
+            match tokio::try_join!(db.auths.community_ids(cutoff), db.profiles.ids()) {
+                Ok((auths, profiles)) => (
+                    auths.into_iter().collect::<HashSet<_>>(),
+                    profiles.into_iter().collect::<HashSet<_>>(),
+                ),
+                Err(err) => return Err(err.to_string()),
+            };
+
+        let orphaned_auths: Vec<&String> = auths
+            .difference(&profiles)
+            .filter(|community_id| !busy.contains(*community_id))
+            .collect();
+        // Auths newer than the cutoff are excluded above, so only profiles
+        // whose auth is truly missing are treated as orphans.
+        let all_auths: HashSet<String> = match db.auths.community_ids(horizon).await {
+            Ok(ids) => ids.into_iter().collect(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let orphaned_profiles: Vec<&String> = profiles
+            .difference(&all_auths)
+            .filter(|community_id| !busy.contains(*community_id))
+            .collect();
+        report.orphaned_auths = orphaned_auths.len();
+        report.orphaned_profiles = orphaned_profiles.len();
+
+        let OrphanPolicy::Remove(limit) = orphans else {
+            return Ok(report);
+        };
+        for community_id in orphaned_auths.into_iter().chain(orphaned_profiles) {
+            if report.removed >= limit {
+                break;
+            }
+            if Self::remove_orphan(db, community_id).await {
+                report.removed += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Records a deletion for an orphaned account and carries it out, so the
+    /// repair leaves the same trail as a user initiated deletion.
+    async fn remove_orphan(db: &DbController, community_id: &str) -> bool {
+        let operation =
+            AccountOperation::new(community_id.to_string(), AccountOperationKind::Delete, None);
+        if db.auths.insert_operation(&operation).await.is_err() {
+            eprintln!("SAGA_ERROR: Error recording orphan removal for {community_id}.");
+            return false;
+        }
+        Self::remove_account(db, &operation).await.is_ok()
+    }
+
+    async fn record_failure(
+        db: &DbController,
+        operation: &AccountOperation,
+        status: AccountOperationStatus,
+        error: &str,
+    ) {
+        if db
+            .auths
+            .fail_operation(&operation.id, status, error)
+            .await
+            .is_err()
+        {
+            eprintln!("SAGA_ERROR: Error updating operation {}.", operation.id);
+        }
+    }
+}
@@ -15,9 +15,10 @@ use crate::{
 
 use super::{models::AuthRegistrationRequest, AccessToken, Auth};
 
+#[derive(Default)]
 pub struct Mutation;
 
-#[Object]
+#[Object(name = "AuthMutation")]
 impl Mutation {
     async fn register(
         &self,
@@ -195,9 +196,9 @@ impl Mutation {
         };
 
         if let Err(err) = Auth::update_email(db, email, community_id).await {
-            return Ok(GatewayResponse::new(false, Some(err), None, 500));
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
         } else {
-            return Ok(GatewayResponse::new(true, None, None, 200));
+            Ok(GatewayResponse::new(true, None, None, 200))
         }
     }
 
@@ -256,9 +257,9 @@ impl Mutation {
         };
 
         if let Err(err) = Auth::update_password(db, new_password, community_id).await {
-            return Ok(GatewayResponse::new(false, Some(err), None, 500));
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
         } else {
-            return Ok(GatewayResponse::new(true, None, None, 200));
+            Ok(GatewayResponse::new(true, None, None, 200))
         }
     }
 
@@ -309,9 +310,9 @@ impl Mutation {
         };
 
         if let Err(err) = Auth::delete(db, community_id).await {
-            return Ok(GatewayResponse::new(false, Some(err), None, 500));
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
         } else {
-            return Ok(GatewayResponse::new(true, None, None, 204));
+            Ok(GatewayResponse::new(true, None, None, 204))
         }
     }
 }
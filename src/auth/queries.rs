@@ -10,9 +10,10 @@ use crate::{community::UserProfile, db::DbController, GatewayResponse};
 
 use super::{AccessToken, Auth, RefreshToken};
 
+#[derive(Default)]
 pub struct Query;
 
-#[Object]
+#[Object(name = "AuthQuery")]
 impl Query {
     async fn logout(&self, ctx: &Context<'_>) -> Result<GatewayResponse<UserProfile>> {
         // Get access cookie from headers
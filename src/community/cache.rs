@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{config::env_or, db::routing};
+
+use super::{ExpressionPost, TrendingWindow, UserProfile};
+
+/// Default number of entries kept before the ones closest to expiring are
+/// evicted.
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+/// Default lifetime of cached feeds.
+const DEFAULT_FEED_TTL_SECS: u64 = 30;
+/// Default lifetime of cached profiles.
+const DEFAULT_PROFILE_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    RecentPosts(u16),
+    TrendingPosts(TrendingWindow, u16),
+    Profile(String),
+}
+
+#[derive(Debug, Clone)]
+enum CachedValue {
+    Posts(Vec<ExpressionPost>),
+    Profile(UserProfile),
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: CachedValue,
+    expires: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    /// Bumped on every invalidation, so loads that raced with a write are
+    /// not stored.
+    generation: u64,
+    last_invalidated: Option<Instant>,
+}
+
+/// Change to community data that cached reads may depend on.
+#[derive(Debug, Clone)]
+pub enum CommunityEvent {
+    /// A post was created, edited, deleted or restored, or its counters were
+    /// repaired.
+    PostChanged,
+    /// A reply was added, deleted or restored.
+    ReplyChanged,
+    /// `user` liked or unliked a post.
+    LikeChanged { user: String },
+    /// A profile was created or removed, along with its posts and likes.
+    ProfileChanged { id: String },
+    /// Deleted posts and replies were purged, taking their likes with them.
+    Purged,
+    /// Trending scores of posts were recomputed and some of them moved.
+    ScoresChanged,
+}
+
+/// Hit and miss counts since the process started.
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    pub evictions: u64,
+}
+
+/// Bounded, in-process cache for the feed and profile reads that change
+/// slowly. Every process keeps its own, so changes made by another instance
+/// only show up once entries expire.
+#[derive(Debug)]
+pub struct CommunityCache {
+    state: Mutex<CacheState>,
+    capacity: usize,
+    feed_ttl: Duration,
+    profile_ttl: Duration,
+    /// How far replicas may lag, when there are any.
+    replica_lag: Option<Duration>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl CommunityCache {
+    pub fn new(
+        capacity: usize,
+        feed_ttl: Duration,
+        profile_ttl: Duration,
+        replica_lag: Option<Duration>,
+    ) -> Self {
+        Self {
+            state: Mutex::new(CacheState::default()),
+            capacity,
+            feed_ttl,
+            profile_ttl,
+            replica_lag,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads `COMMUNITY_CACHE_CAPACITY`, `FEED_CACHE_TTL_SECS` and
+    /// `PROFILE_CACHE_TTL_SECS`. A capacity of zero turns caching off.
+    pub fn from_env(replica_lag: Option<Duration>) -> Self {
+        Self::new(
+            env_or("COMMUNITY_CACHE_CAPACITY", DEFAULT_CACHE_CAPACITY),
+            Duration::from_secs(env_or("FEED_CACHE_TTL_SECS", DEFAULT_FEED_TTL_SECS)),
+            Duration::from_secs(env_or("PROFILE_CACHE_TTL_SECS", DEFAULT_PROFILE_TTL_SECS)),
+            replica_lag,
+        )
+    }
+
+    /// Cache that stores nothing, for controllers bound to a unit of work.
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO, Duration::ZERO, None)
+    }
+
+    pub async fn recent_posts<F, Fut>(
+        &self,
+        limit: u16,
+        load: F,
+    ) -> Result<Vec<ExpressionPost>, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ExpressionPost>, String>>,
+    {
+        self.posts(CacheKey::RecentPosts(limit), load).await
+    }
+
+    pub async fn trending_posts<F, Fut>(
+        &self,
+        window: TrendingWindow,
+        limit: u16,
+        load: F,
+    ) -> Result<Vec<ExpressionPost>, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ExpressionPost>, String>>,
+    {
+        self.posts(CacheKey::TrendingPosts(window, limit), load)
+            .await
+    }
+
+    pub async fn profile<F, Fut>(&self, id: &str, load: F) -> Result<UserProfile, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<UserProfile, String>>,
+    {
+        let key = CacheKey::Profile(id.to_string());
+        if let Some(CachedValue::Profile(profile)) = self.lookup(&key) {
+            return Ok(profile);
+        }
+
+        let generation = self.generation();
+        let profile = load().await?;
+        self.store(
+            key,
+            CachedValue::Profile(profile.clone()),
+            self.profile_ttl,
+            generation,
+        );
+        Ok(profile)
+    }
+
+    async fn posts<F, Fut>(&self, key: CacheKey, load: F) -> Result<Vec<ExpressionPost>, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ExpressionPost>, String>>,
+    {
+        if let Some(CachedValue::Posts(posts)) = self.lookup(&key) {
+            return Ok(posts);
+        }
+
+        let generation = self.generation();
+        let posts = load().await?;
+        self.store(
+            key,
+            CachedValue::Posts(posts.clone()),
+            self.feed_ttl,
+            generation,
+        );
+        Ok(posts)
+    }
+
+    /// Drops every entry the change may have made stale.
+    pub fn invalidate(&self, event: CommunityEvent) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.generation += 1;
+        state.last_invalidated = Some(Instant::now());
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+
+        // Feeds embed authors, like counts and reply counts, so every change
+        // reaches them. Profiles carry the ids of the posts they liked.
+        match event {
+            CommunityEvent::PostChanged
+            | CommunityEvent::ReplyChanged
+            | CommunityEvent::ScoresChanged => {
+                state
+                    .entries
+                    .retain(|key, _| matches!(key, CacheKey::Profile(_)));
+            }
+            CommunityEvent::LikeChanged { user: id } | CommunityEvent::ProfileChanged { id } => {
+                state.entries.retain(|key, _| match key {
+                    CacheKey::Profile(profile) => *profile != id,
+                    _ => false,
+                });
+            }
+            CommunityEvent::Purged => state.entries.clear(),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self
+                .state
+                .lock()
+                .map(|state| state.entries.len())
+                .unwrap_or_default(),
+            capacity: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.state
+            .lock()
+            .map(|state| state.generation)
+            .unwrap_or_default()
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<CachedValue> {
+        let value = self.state.lock().ok().and_then(|state| {
+            state
+                .entries
+                .get(key)
+                .filter(|entry| entry.expires > Instant::now())
+                .map(|entry| entry.value.clone())
+        });
+
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Stores a freshly loaded value unless something changed while it was
+    /// being loaded. Values read from a replica are also skipped until the
+    /// replica has had time to catch up with the last change.
+    fn store(&self, key: CacheKey, value: CachedValue, ttl: Duration, generation: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.generation != generation {
+            return;
+        }
+        if let (Some(lag), Some(last_invalidated)) = (self.replica_lag, state.last_invalidated) {
+            if routing::replica_allowed() && last_invalidated.elapsed() < lag {
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            state.entries.retain(|_, entry| entry.expires > now);
+            if state.entries.len() >= self.capacity {
+                if let Some(oldest) = soonest_to_expire(&state.entries) {
+                    state.entries.remove(&oldest);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        state.entries.insert(
+            key,
+            Entry {
+                value,
+                expires: now + ttl,
+            },
+        );
+    }
+}
+
+fn soonest_to_expire(entries: &HashMap<CacheKey, Entry>) -> Option<CacheKey> {
+    entries
+        .iter()
+        .min_by_key(|(_, entry)| entry.expires)
+        .map(|(key, _)| key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn cache(replica_lag: Option<Duration>) -> CommunityCache {
+        let ttl = Duration::from_secs(60);
+        CommunityCache::new(16, ttl, ttl, replica_lag)
+    }
+
+    fn keys(cache: &CommunityCache) -> HashSet<CacheKey> {
+        cache
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn profile_key(id: &str) -> CacheKey {
+        CacheKey::Profile(id.to_string())
+    }
+
+    async fn load_profile(cache: &CommunityCache, id: &str) {
+        cache
+            .profile(id, || async { Ok(UserProfile::default()) })
+            .await
+            .unwrap();
+    }
+
+    /// Caches both feeds and the profiles of ada and bo.
+    async fn fill(cache: &CommunityCache) {
+        cache
+            .recent_posts(10, || async { Ok(vec![]) })
+            .await
+            .unwrap();
+        cache
+            .trending_posts(TrendingWindow::Week, 10, || async { Ok(vec![]) })
+            .await
+            .unwrap();
+        load_profile(cache, "ada").await;
+        load_profile(cache, "bo").await;
+    }
+
+    #[tokio::test]
+    async fn events_drop_the_entries_they_make_stale() {
+        let profiles = HashSet::from([profile_key("ada"), profile_key("bo")]);
+        let cases = [
+            (CommunityEvent::PostChanged, profiles.clone()),
+            (CommunityEvent::ReplyChanged, profiles.clone()),
+            (CommunityEvent::ScoresChanged, profiles),
+            (
+                CommunityEvent::LikeChanged {
+                    user: "ada".to_string(),
+                },
+                HashSet::from([profile_key("bo")]),
+            ),
+            (
+                CommunityEvent::ProfileChanged {
+                    id: "ada".to_string(),
+                },
+                HashSet::from([profile_key("bo")]),
+            ),
+            (CommunityEvent::Purged, HashSet::new()),
+        ];
+
+        for (event, kept) in cases {
+            let cache = cache(None);
+            fill(&cache).await;
+            assert_eq!(keys(&cache).len(), 4);
+            cache.invalidate(event.clone());
+            assert_eq!(keys(&cache), kept, "after {event:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn loads_that_raced_an_invalidation_are_not_stored() {
+        let cache = cache(None);
+        cache
+            .profile("ada", || async {
+                cache.invalidate(CommunityEvent::PostChanged);
+                Ok(UserProfile::default())
+            })
+            .await
+            .unwrap();
+        assert!(keys(&cache).is_empty());
+    }
+
+    #[tokio::test]
+    async fn replica_reads_are_not_stored_until_the_replica_caught_up() {
+        let cache = cache(Some(Duration::from_millis(50)));
+        cache.invalidate(CommunityEvent::PostChanged);
+
+        routing::scope(false, load_profile(&cache, "ada")).await;
+        assert!(keys(&cache).is_empty());
+
+        // Reads from the primary are current whatever the lag
+        load_profile(&cache, "bo").await;
+        assert_eq!(keys(&cache), HashSet::from([profile_key("bo")]));
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        routing::scope(false, load_profile(&cache, "ada")).await;
+        assert!(keys(&cache).contains(&profile_key("ada")));
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::dataloader::{DataLoader, Loader};
+
+use crate::db::DbController;
+
+use super::{Bookmark, UserProfile};
+
+/// Follower and following counts of the profile with this id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FollowCounts(pub String);
+
+/// Whether the viewer bookmarked the post.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ViewerBookmark {
+    pub viewer: String,
+    pub post_id: String,
+}
+
+/// Batches the per-item reads of a response, like the follow counts of every
+/// profile in a list, into one query each. Nothing is cached between loads,
+/// so a loader can serve every request.
+pub struct CommunityLoader {
+    db: Arc<DbController>,
+}
+
+impl CommunityLoader {
+    /// Loader to hand to a schema as data. Batches run on their own tasks,
+    /// outside of any request, so they always read from the primaries.
+    pub fn new(db: Arc<DbController>) -> DataLoader<Self> {
+        DataLoader::new(Self { db }, tokio::spawn)
+    }
+}
+
+impl Loader<FollowCounts> for CommunityLoader {
+    type Value = (i64, i64);
+    type Error = String;
+
+    async fn load(
+        &self,
+        keys: &[FollowCounts],
+    ) -> Result<HashMap<FollowCounts, Self::Value>, Self::Error> {
+        let ids: Vec<String> = keys.iter().map(|FollowCounts(id)| id.clone()).collect();
+        let counts = UserProfile::follow_counts(&self.db, &ids).await?;
+        Ok(counts
+            .into_iter()
+            .map(|(id, counts)| (FollowCounts(id), counts))
+            .collect())
+    }
+}
+
+impl Loader<ViewerBookmark> for CommunityLoader {
+    type Value = bool;
+    type Error = String;
+
+    async fn load(
+        &self,
+        keys: &[ViewerBookmark],
+    ) -> Result<HashMap<ViewerBookmark, Self::Value>, Self::Error> {
+        let mut posts_by_viewer: HashMap<&str, Vec<String>> = HashMap::new();
+        for key in keys {
+            posts_by_viewer
+                .entry(&key.viewer)
+                .or_default()
+                .push(key.post_id.clone());
+        }
+
+        let mut bookmarked = HashMap::new();
+        for (viewer, post_ids) in posts_by_viewer {
+            for post_id in Bookmark::bookmarked_posts(&self.db, viewer, &post_ids).await? {
+                let key = ViewerBookmark {
+                    viewer: viewer.to_string(),
+                    post_id,
+                };
+                bookmarked.insert(key, true);
+            }
+        }
+        Ok(bookmarked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::{
+        models::fixtures::{backend_tests, post, profile},
+        BookmarkKind, ExpressionPost,
+    };
+
+    async fn follow_counts_load_for_many_profiles_at_once(db: DbController) {
+        for id in ["ada", "bo", "cy", "dee"] {
+            profile(&db, id).await;
+        }
+        for (follower, followee) in [("bo", "ada"), ("cy", "ada"), ("ada", "bo")] {
+            UserProfile::follow(&db, followee.to_string(), follower.to_string())
+                .await
+                .unwrap();
+        }
+
+        let loader = CommunityLoader::new(Arc::new(db));
+        let keys = ["ada", "bo", "cy", "dee"].map(|id| FollowCounts(id.to_string()));
+        let counts = loader.load_many(keys.clone()).await.unwrap();
+        assert_eq!(counts.get(&keys[0]), Some(&(2, 1)));
+        assert_eq!(counts.get(&keys[1]), Some(&(1, 1)));
+        assert_eq!(counts.get(&keys[2]), Some(&(0, 1)));
+        // Profiles nobody follows and that follow nobody count as zero
+        assert_eq!(counts.get(&keys[3]), None);
+    }
+
+    async fn viewer_bookmarks_load_for_many_posts_at_once(db: DbController) {
+        profile(&db, "ada").await;
+        profile(&db, "bo").await;
+        let first = post(&db, "ada", None).await;
+        let second = post(&db, "ada", None).await;
+        for (user, post) in [("ada", &first), ("bo", &second)] {
+            Bookmark::add(
+                &db,
+                post.id.clone(),
+                BookmarkKind::Post,
+                None,
+                user.to_string(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let loader = CommunityLoader::new(Arc::new(db));
+        let key = |viewer: &str, post: &ExpressionPost| ViewerBookmark {
+            viewer: viewer.to_string(),
+            post_id: post.id.clone(),
+        };
+        let keys = [
+            key("ada", &first),
+            key("ada", &second),
+            key("bo", &first),
+            key("bo", &second),
+        ];
+        let bookmarked = loader.load_many(keys.clone()).await.unwrap();
+        let bookmarked: Vec<bool> = keys
+            .iter()
+            .map(|key| bookmarked.get(key).copied().unwrap_or(false))
+            .collect();
+        assert_eq!(bookmarked, [true, false, false, true]);
+    }
+
+    backend_tests!(
+        follow_counts_load_for_many_profiles_at_once,
+        viewer_bookmarks_load_for_many_posts_at_once,
+    );
+}
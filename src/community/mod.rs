@@ -1,9 +1,41 @@
+use async_graphql::Context;
+use tower_cookies::Cookies;
+
+use crate::auth::AccessToken;
+
+mod cache;
+mod loaders;
 mod models;
 mod mutations;
+mod pagination;
 mod queries;
 
-pub use models::expression_post::{ExpressionPost, ExpressionPostAggregate};
-pub use models::reply::Reply;
-pub use models::user_profile::UserProfile;
+pub use cache::{CacheStats, CommunityCache, CommunityEvent};
+pub use loaders::CommunityLoader;
+pub use models::bookmark::{Bookmark, BookmarkCollection, BookmarkCollectionList, BookmarkKind};
+pub use models::circle::{
+    Circle, CircleList, CircleMember, CircleMemberList, CircleMemberStatus, CircleRole,
+    CircleVisibility,
+};
+pub use models::draft::PostDraft;
+pub use models::expression_post::{
+    ExpressionPost, ExpressionPostAggregate, ExpressionPostContent, TrendingWindow,
+};
+pub use models::reply::{Reply, ReplyEdit, ReplyEditHistory};
+pub use models::search::{PostSearchResult, PostSearchResults};
+pub use models::topic::{Topic, TopicList};
+pub use models::user_profile::{Follow, UserProfile};
 pub use mutations::Mutation;
+pub use pagination::{
+    BookmarkConnection, ExpressionPostConnection, FollowConnection, PostDraftConnection,
+    ReplyConnection,
+};
 pub use queries::Query;
+
+/// Community id of the logged in viewer, or `None` for visitors.
+fn viewer(ctx: &Context<'_>) -> Option<String> {
+    let cookie = ctx.data::<Cookies>().ok()?.get("sat")?;
+    AccessToken::decode(cookie.value())
+        .ok()
+        .map(|claims| claims.sub)
+}
@@ -0,0 +1,455 @@
+use async_graphql::{Enum, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::{
+    community::pagination::{self, BookmarkConnection, PageArgs},
+    db::{self, BookmarkRecord, Cursor, DbController, Page, SortKey},
+};
+
+use super::{expression_post::ExpressionPost, reply::Reply};
+
+/// Longest collection name, in characters.
+const MAX_COLLECTION_LENGTH: usize = 60;
+
+/// Kind of content a bookmark saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum BookmarkKind {
+    Post,
+    Reply,
+}
+
+/// A post or reply a user saved. Bookmarks are only ever shown to the user
+/// who made them.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: String,
+    pub kind: BookmarkKind,
+    /// Collection the bookmark is filed under, if any.
+    pub collection: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// The saved post, for bookmarks of posts. Its replies are not loaded.
+    pub post: Option<ExpressionPost>,
+    /// The saved reply, for bookmarks of replies.
+    pub reply: Option<Reply>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct BookmarkCollection {
+    pub name: String,
+    /// Number of bookmarks filed under the collection.
+    pub bookmark_count: i64,
+}
+
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct BookmarkCollectionList {
+    pub collections: Vec<BookmarkCollection>,
+}
+
+impl Bookmark {
+    /// Where the bookmark sits in its list, latest first.
+    pub fn page_cursor(&self) -> Cursor {
+        Cursor {
+            key: SortKey::Time(self.created_at),
+            id: self.id.clone(),
+        }
+    }
+
+    /// Saves a post or reply, filed under `collection` when one is given.
+    /// Collections are named by their user and exist for as long as they
+    /// hold a bookmark.
+    pub async fn add(
+        db: &DbController,
+        target_id: String,
+        kind: BookmarkKind,
+        collection: Option<String>,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let collection = valid_collection(collection)?;
+
+        let (target_id, collection) = (&target_id, &collection);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            if Self::find(&db, logged_in_user, target_id).await?.is_some() {
+                return Err("You already bookmarked this.".to_string());
+            }
+
+            let mut record = BookmarkRecord {
+                id: Ulid::new().to_string(),
+                user_id: logged_in_user.clone(),
+                post_id: None,
+                reply_id: None,
+                collection: collection.clone(),
+                created_at: db::now(),
+            };
+            let (post, reply) = match kind {
+                BookmarkKind::Post => {
+                    let Ok(Some(post)) = db.posts.get_by_id(target_id).await else {
+                        return Err("Expression post does not exist.".to_string());
+                    };
+                    if !post.can_be_read_by(&db, Some(logged_in_user)).await? {
+                        return Err("Expression post does not exist.".to_string());
+                    }
+                    record.post_id = Some(target_id.clone());
+                    (Some(post), None)
+                }
+                BookmarkKind::Reply => {
+                    let Ok(Some(_)) = db.replies.author_of(target_id).await else {
+                        return Err("Reply does not exist.".to_string());
+                    };
+                    ExpressionPost::of_thread(&db, target_id, Some(logged_in_user)).await?;
+                    let Ok(reply) = db.replies.get_by_id(target_id).await else {
+                        eprintln!("DATABASE ERROR: Error retrieving reply in Bookmark Add");
+                        return Err("Server error. Please try again.".to_string());
+                    };
+                    record.reply_id = Some(target_id.clone());
+                    (None, reply)
+                }
+            };
+
+            if db.bookmarks.insert(&record).await.is_err() {
+                eprintln!("DATABASE ERROR: Error saving bookmark in Bookmark Add");
+                return Err("Server error. Please try again.".to_string());
+            }
+            Ok(Bookmark {
+                id: record.id,
+                kind,
+                collection: record.collection,
+                created_at: record.created_at,
+                post,
+                reply,
+            })
+        })
+        .await
+    }
+
+    pub async fn remove(
+        db: &DbController,
+        target_id: String,
+        logged_in_user: String,
+    ) -> Result<bool, String> {
+        let Some(bookmark) = Self::find(db, &logged_in_user, &target_id).await? else {
+            return Err("Bookmark does not exist.".to_string());
+        };
+
+        if db.bookmarks.delete(&bookmark.id).await.is_err() {
+            eprintln!("DATABASE ERROR: Error removing bookmark in Bookmark Remove");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(true)
+    }
+
+    /// Files a bookmark under another collection, or under none when no
+    /// `collection` is given.
+    pub async fn move_to(
+        db: &DbController,
+        target_id: String,
+        collection: Option<String>,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let collection = valid_collection(collection)?;
+
+        let (target_id, collection) = (&target_id, &collection);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Some(mut bookmark) = Self::find(&db, logged_in_user, target_id).await? else {
+                return Err("Bookmark does not exist.".to_string());
+            };
+
+            if db
+                .bookmarks
+                .set_collection(&bookmark.id, collection.as_deref())
+                .await
+                .is_err()
+            {
+                eprintln!("DATABASE ERROR: Error moving bookmark in Bookmark MoveTo");
+                return Err("Server error. Please try again.".to_string());
+            }
+            bookmark.collection = collection.clone();
+
+            Self::load(&db, bookmark)
+                .await?
+                .ok_or_else(|| "Bookmark does not exist.".to_string())
+        })
+        .await
+    }
+
+    /// Bookmarks of the user, latest first, only those filed under
+    /// `collection` when one is given. Bookmarks of content the user can no
+    /// longer read are skipped, and reading carries on past them until the
+    /// page is full.
+    pub async fn get_bookmarks(
+        db: &DbController,
+        user_id: String,
+        collection: Option<String>,
+        args: PageArgs,
+    ) -> Result<BookmarkConnection, String> {
+        let (user_id, collection) = (&user_id, &collection);
+        pagination::connection(args, Self::page_cursor, |mut page: Page| async move {
+            let limit = usize::from(page.limit);
+            let mut bookmarks = vec![];
+            loop {
+                let Ok(records) = db
+                    .bookmarks
+                    .list(user_id, collection.as_deref(), &page)
+                    .await
+                else {
+                    eprintln!(
+                        "DATABASE ERROR: Error retrieving bookmarks in Bookmark GetBookmarks"
+                    );
+                    return Err("Server error. Please try again.".to_string());
+                };
+                let exhausted = records.len() < limit;
+                // The next read starts past the last record of this one
+                let boundary = if page.from_end {
+                    records.first()
+                } else {
+                    records.last()
+                }
+                .map(record_cursor);
+
+                let mut loaded = vec![];
+                for record in records {
+                    if let Some(bookmark) = Self::load(db, record).await? {
+                        loaded.push(bookmark);
+                    }
+                }
+                if page.from_end {
+                    loaded.append(&mut bookmarks);
+                    bookmarks = loaded;
+                    page.before = boundary;
+                } else {
+                    bookmarks.append(&mut loaded);
+                    page.after = boundary;
+                }
+                if exhausted || bookmarks.len() >= limit {
+                    break;
+                }
+            }
+
+            if page.from_end {
+                bookmarks.drain(..bookmarks.len().saturating_sub(limit));
+            } else {
+                bookmarks.truncate(limit);
+            }
+            Ok(bookmarks)
+        })
+        .await
+    }
+
+    pub async fn get_collections(
+        db: &DbController,
+        user_id: String,
+    ) -> Result<Vec<BookmarkCollection>, String> {
+        let Ok(collections) = db.bookmarks.collections(&user_id).await else {
+            eprintln!("DATABASE ERROR: Error retrieving collections in Bookmark GetCollections");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(collections)
+    }
+
+    /// Ids among `post_ids` of the posts the user bookmarked.
+    pub async fn bookmarked_posts(
+        db: &DbController,
+        user_id: &str,
+        post_ids: &[String],
+    ) -> Result<Vec<String>, String> {
+        let Ok(bookmarked) = db.bookmarks.bookmarked_posts(user_id, post_ids).await else {
+            eprintln!("DATABASE ERROR: Error checking bookmarks in Bookmark BookmarkedPosts");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(bookmarked)
+    }
+
+    async fn find(
+        db: &DbController,
+        user_id: &str,
+        target_id: &str,
+    ) -> Result<Option<BookmarkRecord>, String> {
+        let Ok(bookmark) = db.bookmarks.find(user_id, target_id).await else {
+            eprintln!("DATABASE ERROR: Error retrieving bookmark in Bookmark Find");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(bookmark)
+    }
+
+    /// The bookmark with the content it saves, or `None` when the content
+    /// is deleted or its user may no longer read it.
+    async fn load(db: &DbController, record: BookmarkRecord) -> Result<Option<Self>, String> {
+        let user = Some(record.user_id.as_str());
+        let (kind, post, reply) = match (&record.post_id, &record.reply_id) {
+            (Some(post_id), _) => {
+                let Ok(post) = db.posts.get_by_id(post_id).await else {
+                    eprintln!("DATABASE ERROR: Error retrieving post in Bookmark Load");
+                    return Err("Server error. Please try again.".to_string());
+                };
+                let Some(post) = post else {
+                    return Ok(None);
+                };
+                if !post.can_be_read_by(db, user).await? {
+                    return Ok(None);
+                }
+                (BookmarkKind::Post, Some(post), None)
+            }
+            (None, Some(reply_id)) => {
+                let Ok(reply) = db.replies.get_by_id(reply_id).await else {
+                    eprintln!("DATABASE ERROR: Error retrieving reply in Bookmark Load");
+                    return Err("Server error. Please try again.".to_string());
+                };
+                let Some(reply) = reply.filter(|reply| !reply.is_deleted()) else {
+                    return Ok(None);
+                };
+                // Replies go out of reach with the thread they are in
+                if ExpressionPost::of_thread(db, reply_id, user).await.is_err() {
+                    return Ok(None);
+                }
+                (BookmarkKind::Reply, None, Some(reply))
+            }
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some(Bookmark {
+            id: record.id,
+            kind,
+            collection: record.collection,
+            created_at: record.created_at,
+            post,
+            reply,
+        }))
+    }
+}
+
+/// Where the bookmark record sits in its list, like [`Bookmark::page_cursor`].
+fn record_cursor(record: &BookmarkRecord) -> Cursor {
+    Cursor {
+        key: SortKey::Time(record.created_at),
+        id: record.id.clone(),
+    }
+}
+
+/// Trimmed collection name, which must not be empty or too long.
+fn valid_collection(collection: Option<String>) -> Result<Option<String>, String> {
+    let Some(collection) = collection else {
+        return Ok(None);
+    };
+    let collection = collection.trim().to_string();
+    if collection.is_empty() || collection.chars().count() > MAX_COLLECTION_LENGTH {
+        return Err(format!(
+            "Collection names must be between 1 and {MAX_COLLECTION_LENGTH} characters."
+        ));
+    }
+    Ok(Some(collection))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::connection::CursorType;
+
+    use super::*;
+    use crate::community::{
+        models::fixtures::{self, backend_tests, post, profile, reply},
+        Circle,
+    };
+
+    async fn bookmark(db: &DbController, user: &str, target_id: &str, kind: BookmarkKind) {
+        Bookmark::add(db, target_id.to_string(), kind, None, user.to_string())
+            .await
+            .unwrap();
+    }
+
+    async fn bookmark_count(db: &DbController, user: &str) -> usize {
+        Bookmark::get_bookmarks(db, user.to_string(), None, PageArgs::default())
+            .await
+            .unwrap()
+            .edges
+            .len()
+    }
+
+    /// Circle of "owner" that "member" was let into.
+    async fn circle_with_member(db: &DbController) -> String {
+        profile(db, "owner").await;
+        profile(db, "member").await;
+        fixtures::circle_with_member(db, "owner", "member").await
+    }
+
+    async fn remove_member(db: &DbController, circle_id: &str) {
+        Circle::remove_member(
+            db,
+            circle_id.to_string(),
+            "member".to_string(),
+            "owner".to_string(),
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn bookmarks_of_posts_in_circles_left_are_hidden(db: DbController) {
+        let circle_id = circle_with_member(&db).await;
+        let post = post(&db, "owner", Some(&circle_id)).await;
+        bookmark(&db, "member", &post.id, BookmarkKind::Post).await;
+        assert_eq!(bookmark_count(&db, "member").await, 1);
+
+        remove_member(&db, &circle_id).await;
+        assert_eq!(bookmark_count(&db, "member").await, 0);
+    }
+
+    async fn pages_fill_up_past_hidden_bookmarks(db: DbController) {
+        let circle_id = circle_with_member(&db).await;
+        for circle in [None, Some(&circle_id), Some(&circle_id), None] {
+            let post = post(&db, "owner", circle.map(String::as_str)).await;
+            bookmark(&db, "member", &post.id, BookmarkKind::Post).await;
+        }
+        remove_member(&db, &circle_id).await;
+
+        let args = |after: Option<String>| PageArgs {
+            after,
+            first: Some(1),
+            ..PageArgs::default()
+        };
+        let first = Bookmark::get_bookmarks(&db, "member".to_string(), None, args(None))
+            .await
+            .unwrap();
+        assert_eq!(first.edges.len(), 1);
+        assert!(first.has_next_page);
+
+        let after = first.edges[0].cursor.encode_cursor();
+        let second = Bookmark::get_bookmarks(&db, "member".to_string(), None, args(Some(after)))
+            .await
+            .unwrap();
+        assert_eq!(second.edges.len(), 1);
+        assert!(!second.has_next_page);
+        assert_ne!(first.edges[0].node.id, second.edges[0].node.id);
+
+        let last = PageArgs {
+            last: Some(2),
+            ..PageArgs::default()
+        };
+        let last = Bookmark::get_bookmarks(&db, "member".to_string(), None, last)
+            .await
+            .unwrap();
+        let ids: Vec<_> = last.edges.iter().map(|edge| &edge.node.id).collect();
+        assert_eq!(ids, [&first.edges[0].node.id, &second.edges[0].node.id]);
+        assert!(!last.has_previous_page);
+    }
+
+    async fn bookmarks_of_replies_go_with_their_post(db: DbController) {
+        profile(&db, "author").await;
+        let post = post(&db, "author", None).await;
+        let reply_to_post = reply(&db, "author", &post.id, "Lovely").await;
+        let nested = reply(&db, "author", &reply_to_post.id, "Agreed").await;
+        bookmark(&db, "author", &nested.id, BookmarkKind::Reply).await;
+        assert_eq!(bookmark_count(&db, "author").await, 1);
+
+        ExpressionPost::delete(&db, post.id.clone(), "author".to_string())
+            .await
+            .unwrap();
+        assert_eq!(bookmark_count(&db, "author").await, 0);
+    }
+
+    backend_tests!(
+        bookmarks_of_posts_in_circles_left_are_hidden,
+        pages_fill_up_past_hidden_bookmarks,
+        bookmarks_of_replies_go_with_their_post
+    );
+}
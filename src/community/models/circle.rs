@@ -0,0 +1,643 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::db::{self, DbController, NewCircleRecord};
+
+use super::user_profile::UserProfile;
+
+/// Longest circle name, in characters.
+const MAX_NAME_LENGTH: usize = 60;
+/// Shortest circle name, in characters.
+const MIN_NAME_LENGTH: usize = 3;
+/// Longest circle description, in characters.
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
+/// Most rules a circle can have.
+const MAX_RULES: usize = 20;
+/// Longest rule, in characters.
+const MAX_RULE_LENGTH: usize = 500;
+/// Circles returned by circle lists when no limit is given.
+const DEFAULT_CIRCLE_LIMIT: u16 = 20;
+/// Largest circle list a client may ask for.
+const MAX_CIRCLE_LIMIT: u16 = 100;
+
+/// Who may join a circle and read its feeds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum CircleVisibility {
+    /// Anyone can read the circle and join it right away.
+    #[default]
+    Public,
+    /// Only members read the circle, and joining needs the approval of its
+    /// owners or moderators.
+    Request,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum CircleRole {
+    Owner,
+    Moderator,
+    Member,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum CircleMemberStatus {
+    Active,
+    /// Asked to join a `REQUEST` circle and waits for approval.
+    Pending,
+}
+
+impl CircleVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircleVisibility::Public => "public",
+            CircleVisibility::Request => "request",
+        }
+    }
+
+    /// Unknown values, which the schema rules out, read as the most
+    /// restrictive visibility.
+    pub fn parse(visibility: &str) -> Self {
+        match visibility {
+            "public" => CircleVisibility::Public,
+            _ => CircleVisibility::Request,
+        }
+    }
+}
+
+impl CircleRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircleRole::Owner => "owner",
+            CircleRole::Moderator => "moderator",
+            CircleRole::Member => "member",
+        }
+    }
+
+    /// Unknown values, which the schema rules out, read as the least
+    /// privileged role.
+    pub fn parse(role: &str) -> Self {
+        match role {
+            "owner" => CircleRole::Owner,
+            "moderator" => CircleRole::Moderator,
+            _ => CircleRole::Member,
+        }
+    }
+
+    /// Whether the role may approve and remove members.
+    pub fn moderates(&self) -> bool {
+        matches!(self, CircleRole::Owner | CircleRole::Moderator)
+    }
+}
+
+impl CircleMemberStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircleMemberStatus::Active => "active",
+            CircleMemberStatus::Pending => "pending",
+        }
+    }
+
+    /// Unknown values, which the schema rules out, read as pending.
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "active" => CircleMemberStatus::Active,
+            _ => CircleMemberStatus::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Circle {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub rules: Vec<String>,
+    pub visibility: CircleVisibility,
+    /// Number of active members, owners and moderators included.
+    pub member_count: i64,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct CircleMember {
+    pub circle_id: String,
+    pub user: UserProfile,
+    pub role: CircleRole,
+    pub status: CircleMemberStatus,
+    /// When the user joined or, while pending, asked to join.
+    pub joined_at: DateTime<Utc>,
+}
+
+impl CircleMember {
+    fn is_active(&self) -> bool {
+        self.status == CircleMemberStatus::Active
+    }
+}
+
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct CircleList {
+    pub circles: Vec<Circle>,
+}
+
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct CircleMemberList {
+    pub members: Vec<CircleMember>,
+}
+
+impl Circle {
+    pub async fn create(
+        db: &DbController,
+        request: NewCircleRequest,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let name = request.name.trim().to_string();
+        let length = name.chars().count();
+        if !(MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&length) {
+            return Err(format!(
+                "Circle names must be between {MIN_NAME_LENGTH} and {MAX_NAME_LENGTH} characters."
+            ));
+        }
+        let description = valid_description(request.description)?;
+        let rules = valid_rules(request.rules.unwrap_or_default())?;
+
+        let record = NewCircleRecord {
+            id: Ulid::new().to_string(),
+            name,
+            description,
+            rules,
+            visibility: request.visibility.unwrap_or_default(),
+            created_by: logged_in_user,
+            created_at: db::now(),
+        };
+        let record = &record;
+        db.unit_of_work(|db| async move {
+            let Ok(taken) = db.circles.name_taken(&record.name).await else {
+                eprintln!("DATABASE ERROR: Error checking circle name in Circle Create");
+                return Err("Server error. Please try again.".to_string());
+            };
+            if taken {
+                return Err("A circle with this name already exists.".to_string());
+            }
+
+            if db.circles.insert(record).await.is_err() {
+                eprintln!("DATABASE ERROR: Error saving circle in Circle Create");
+                return Err("Server error. Please try again.".to_string());
+            }
+
+            Ok(Circle {
+                id: record.id.clone(),
+                name: record.name.clone(),
+                description: record.description.clone(),
+                rules: record.rules.clone(),
+                visibility: record.visibility,
+                member_count: 1,
+                created_by: record.created_by.clone(),
+                created_at: record.created_at,
+            })
+        })
+        .await
+    }
+
+    pub async fn get_by_id(db: &DbController, id: &str) -> Result<Self, String> {
+        match db.circles.get_by_id(id).await {
+            Ok(Some(circle)) => Ok(circle),
+            Ok(None) => Err("Circle does not exist.".to_string()),
+            Err(_) => {
+                eprintln!("DATABASE ERROR: Error retrieving circle in Circle GetById");
+                Err("Server error. Please try again.".to_string())
+            }
+        }
+    }
+
+    /// Circles with the most members first.
+    pub async fn get_circles(
+        db: &DbController,
+        limit: Option<u16>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Self>, String> {
+        let limit = match limit {
+            Some(0) | None => DEFAULT_CIRCLE_LIMIT,
+            Some(limit) => limit.min(MAX_CIRCLE_LIMIT),
+        };
+
+        let Ok(circles) = db.circles.list(limit, offset.unwrap_or_default()).await else {
+            eprintln!("DATABASE ERROR: Error retrieving circles in Circle GetCircles");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(circles)
+    }
+
+    /// Changes the description, rules or visibility of a circle. Owners and
+    /// moderators may edit it; fields left out keep their value.
+    pub async fn update(
+        db: &DbController,
+        request: UpdateCircleRequest,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let description = match &request.description {
+            Some(description) => Some(valid_description(Some(description.clone()))?),
+            None => None,
+        };
+        let rules = request.rules.clone().map(valid_rules).transpose()?;
+
+        let request = &request;
+        let (description, rules) = (&description, &rules);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let mut circle = Self::get_by_id(&db, &request.circle_id).await?;
+            if !Self::role_of(&db, &circle.id, logged_in_user)
+                .await?
+                .is_some_and(|role| role.moderates())
+            {
+                return Err("Only owners and moderators can edit this circle.".to_string());
+            }
+
+            if let Some(description) = description {
+                circle.description = description.clone();
+            }
+            if let Some(rules) = rules {
+                circle.rules = rules.clone();
+            }
+            if let Some(visibility) = request.visibility {
+                circle.visibility = visibility;
+            }
+            if db.circles.update(&circle).await.is_err() {
+                eprintln!("DATABASE ERROR: Error updating circle in Circle Update");
+                return Err("Server error. Please try again.".to_string());
+            }
+            Ok(circle)
+        })
+        .await
+    }
+
+    /// Joins a public circle, or asks to join one that takes requests.
+    pub async fn join(
+        db: &DbController,
+        circle_id: String,
+        logged_in_user: String,
+    ) -> Result<CircleMember, String> {
+        let circle_id = &circle_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let circle = Self::get_by_id(&db, circle_id).await?;
+            match Self::membership(&db, circle_id, logged_in_user).await? {
+                Some(member) if member.is_active() => {
+                    return Err("You are already a member of this circle.".to_string())
+                }
+                Some(_) => return Err("Your request to join is waiting for review.".to_string()),
+                None => {}
+            }
+
+            let status = match circle.visibility {
+                CircleVisibility::Public => CircleMemberStatus::Active,
+                CircleVisibility::Request => CircleMemberStatus::Pending,
+            };
+            if db
+                .circles
+                .add_member(
+                    circle_id,
+                    logged_in_user,
+                    CircleRole::Member,
+                    status,
+                    db::now(),
+                )
+                .await
+                .is_err()
+            {
+                eprintln!("DATABASE ERROR: Error adding circle member in Circle Join");
+                return Err("Server error. Please try again.".to_string());
+            }
+
+            Self::membership(&db, circle_id, logged_in_user)
+                .await?
+                .ok_or_else(|| "Server error. Please try again.".to_string())
+        })
+        .await
+    }
+
+    /// Leaves a circle or withdraws a request to join it. A circle always
+    /// keeps at least one owner.
+    pub async fn leave(
+        db: &DbController,
+        circle_id: String,
+        logged_in_user: String,
+    ) -> Result<bool, String> {
+        let circle_id = &circle_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Some(member) = Self::membership(&db, circle_id, logged_in_user).await? else {
+                return Err("You are not a member of this circle.".to_string());
+            };
+            if member.role == CircleRole::Owner && Self::owner_count(&db, circle_id).await? == 1 {
+                return Err("Make another member an owner before leaving this circle.".to_string());
+            }
+
+            Self::remove(&db, circle_id, logged_in_user).await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Approves a request to join. Owners and moderators review requests.
+    pub async fn approve_member(
+        db: &DbController,
+        circle_id: String,
+        user_id: String,
+        logged_in_user: String,
+    ) -> Result<CircleMember, String> {
+        let (circle_id, user_id) = (&circle_id, &user_id);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            if !Self::role_of(&db, circle_id, logged_in_user)
+                .await?
+                .is_some_and(|role| role.moderates())
+            {
+                return Err("Only owners and moderators can approve members.".to_string());
+            }
+            let Some(member) = Self::membership(&db, circle_id, user_id).await? else {
+                return Err("This user has not asked to join the circle.".to_string());
+            };
+            if member.is_active() {
+                return Err("This user is already a member of the circle.".to_string());
+            }
+
+            Self::set(
+                &db,
+                circle_id,
+                user_id,
+                member.role,
+                CircleMemberStatus::Active,
+            )
+            .await?;
+            Ok(CircleMember {
+                status: CircleMemberStatus::Active,
+                ..member
+            })
+        })
+        .await
+    }
+
+    /// Declines a request to join or removes a member. Moderators may remove
+    /// members; only owners may remove moderators and other owners.
+    pub async fn remove_member(
+        db: &DbController,
+        circle_id: String,
+        user_id: String,
+        logged_in_user: String,
+    ) -> Result<bool, String> {
+        let (circle_id, user_id) = (&circle_id, &user_id);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            if user_id == logged_in_user {
+                return Err("Leave the circle to remove yourself from it.".to_string());
+            }
+            let Some(role) = Self::role_of(&db, circle_id, logged_in_user).await? else {
+                return Err("Only owners and moderators can remove members.".to_string());
+            };
+            let Some(member) = Self::membership(&db, circle_id, user_id).await? else {
+                return Err("This user is not a member of the circle.".to_string());
+            };
+            let allowed = match role {
+                CircleRole::Owner => true,
+                CircleRole::Moderator => member.role == CircleRole::Member,
+                CircleRole::Member => false,
+            };
+            if !allowed {
+                return Err("You are not allowed to remove this member.".to_string());
+            }
+
+            Self::remove(&db, circle_id, user_id).await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Gives an active member another role. Only owners hand out roles, and
+    /// they cannot change their own.
+    pub async fn set_role(
+        db: &DbController,
+        circle_id: String,
+        user_id: String,
+        role: CircleRole,
+        logged_in_user: String,
+    ) -> Result<CircleMember, String> {
+        let (circle_id, user_id) = (&circle_id, &user_id);
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            if Self::role_of(&db, circle_id, logged_in_user).await? != Some(CircleRole::Owner) {
+                return Err("Only owners can change member roles.".to_string());
+            }
+            if user_id == logged_in_user {
+                return Err("Owners cannot change their own role.".to_string());
+            }
+            let Some(member) = Self::membership(&db, circle_id, user_id)
+                .await?
+                .filter(CircleMember::is_active)
+            else {
+                return Err("This user is not a member of the circle.".to_string());
+            };
+
+            Self::set(&db, circle_id, user_id, role, member.status).await?;
+            Ok(CircleMember { role, ..member })
+        })
+        .await
+    }
+
+    /// Members of a circle, owners first. Anyone who can read the circle sees
+    /// its members; only owners and moderators see pending requests.
+    pub async fn get_members(
+        db: &DbController,
+        circle_id: String,
+        status: CircleMemberStatus,
+        viewer: Option<String>,
+    ) -> Result<Vec<CircleMember>, String> {
+        let circle = Self::get_by_id(db, &circle_id).await?;
+        let role = match &viewer {
+            Some(viewer) => Self::role_of(db, &circle_id, viewer).await?,
+            None => None,
+        };
+        let allowed = match status {
+            CircleMemberStatus::Active => {
+                circle.visibility == CircleVisibility::Public || role.is_some()
+            }
+            CircleMemberStatus::Pending => role.is_some_and(|role| role.moderates()),
+        };
+        if !allowed {
+            return Err("You are not allowed to see the members of this circle.".to_string());
+        }
+
+        let Ok(members) = db.circles.members(&circle_id, status).await else {
+            eprintln!("DATABASE ERROR: Error retrieving circle members in Circle GetMembers");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(members)
+    }
+
+    /// Whether the viewer may read the posts of a circle: anyone for public
+    /// circles, active members for the others.
+    pub async fn can_read(
+        db: &DbController,
+        circle_id: &str,
+        viewer: Option<&str>,
+    ) -> Result<bool, String> {
+        let circle = Self::get_by_id(db, circle_id).await?;
+        if circle.visibility == CircleVisibility::Public {
+            return Ok(true);
+        }
+        match viewer {
+            Some(viewer) => Ok(Self::role_of(db, circle_id, viewer).await?.is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the user is an active member of the circle. Circles that do
+    /// not exist have no members.
+    pub async fn is_member(
+        db: &DbController,
+        circle_id: &str,
+        user_id: &str,
+    ) -> Result<bool, String> {
+        Ok(Self::role_of(db, circle_id, user_id).await?.is_some())
+    }
+
+    /// Fails unless the user is an active member, who may post in the circle.
+    pub async fn check_can_post(
+        db: &DbController,
+        circle_id: &str,
+        user_id: &str,
+    ) -> Result<(), String> {
+        Self::get_by_id(db, circle_id).await?;
+        if Self::role_of(db, circle_id, user_id).await?.is_none() {
+            return Err("Join this circle to post in it.".to_string());
+        }
+        Ok(())
+    }
+
+    async fn membership(
+        db: &DbController,
+        circle_id: &str,
+        user_id: &str,
+    ) -> Result<Option<CircleMember>, String> {
+        let Ok(member) = db.circles.membership(circle_id, user_id).await else {
+            eprintln!("DATABASE ERROR: Error retrieving circle membership in Circle Membership");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(member)
+    }
+
+    /// Role of an active member, or `None` for everyone else.
+    async fn role_of(
+        db: &DbController,
+        circle_id: &str,
+        user_id: &str,
+    ) -> Result<Option<CircleRole>, String> {
+        Ok(Self::membership(db, circle_id, user_id)
+            .await?
+            .filter(CircleMember::is_active)
+            .map(|member| member.role))
+    }
+
+    async fn owner_count(db: &DbController, circle_id: &str) -> Result<usize, String> {
+        let Ok(members) = db
+            .circles
+            .members(circle_id, CircleMemberStatus::Active)
+            .await
+        else {
+            eprintln!("DATABASE ERROR: Error retrieving circle members in Circle OwnerCount");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(members
+            .iter()
+            .filter(|member| member.role == CircleRole::Owner)
+            .count())
+    }
+
+    async fn set(
+        db: &DbController,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+    ) -> Result<(), String> {
+        if db
+            .circles
+            .set_member(circle_id, user_id, role, status)
+            .await
+            .is_err()
+        {
+            eprintln!("DATABASE ERROR: Error updating circle member in Circle Set");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(())
+    }
+
+    async fn remove(db: &DbController, circle_id: &str, user_id: &str) -> Result<(), String> {
+        if db.circles.remove_member(circle_id, user_id).await.is_err() {
+            eprintln!("DATABASE ERROR: Error removing circle member in Circle Remove");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Trimmed description, unless it is too long. Blank descriptions are
+/// cleared.
+fn valid_description(description: Option<String>) -> Result<Option<String>, String> {
+    let description = description
+        .map(|description| description.trim().to_string())
+        .filter(|description| !description.is_empty());
+    if description
+        .as_ref()
+        .is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_LENGTH)
+    {
+        return Err(format!(
+            "Circle descriptions can be at most {MAX_DESCRIPTION_LENGTH} characters."
+        ));
+    }
+    Ok(description)
+}
+
+/// Rules trimmed onto a single line each, without blank ones. Circles store
+/// their rules one per line.
+fn valid_rules(rules: Vec<String>) -> Result<Vec<String>, String> {
+    let rules: Vec<String> = rules
+        .iter()
+        .map(|rule| rule.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|rule| !rule.is_empty())
+        .collect();
+    if rules.len() > MAX_RULES {
+        return Err(format!("Circles can have at most {MAX_RULES} rules."));
+    }
+    if rules
+        .iter()
+        .any(|rule| rule.chars().count() > MAX_RULE_LENGTH)
+    {
+        return Err(format!(
+            "Circle rules can be at most {MAX_RULE_LENGTH} characters."
+        ));
+    }
+    Ok(rules)
+}
+
+/********** REQUEST OBJECTS **********/
+
+#[derive(InputObject, Debug)]
+pub struct NewCircleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub rules: Option<Vec<String>>,
+    /// `PUBLIC` when left out.
+    pub visibility: Option<CircleVisibility>,
+}
+
+/// Fields left out keep their current value. An empty description clears
+/// it.
+#[derive(InputObject, Debug)]
+pub struct UpdateCircleRequest {
+    pub circle_id: String,
+    pub description: Option<String>,
+    pub rules: Option<Vec<String>>,
+    pub visibility: Option<CircleVisibility>,
+}
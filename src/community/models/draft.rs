@@ -0,0 +1,417 @@
+use async_graphql::{InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::{
+    community::pagination::{self, PageArgs, PostDraftConnection},
+    db::{self, Cursor, DbController, Page, SortKey},
+};
+
+use super::{
+    circle::Circle,
+    expression_post::{ExpressionPost, ExpressionPostContent, NewExpressionPost},
+    topic::Topic,
+};
+
+/// Most drafts the scheduler publishes in one run.
+const PUBLISH_BATCH_SIZE: u16 = 100;
+
+/// A post that is not published yet. Drafts are only ever shown to their
+/// author, and any field may be missing until the draft is published.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct PostDraft {
+    pub id: String,
+    #[graphql(skip)]
+    pub author: String,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub cover_image: Option<String>,
+    pub content: Option<ExpressionPostContent>,
+    /// Topics the post will be tagged with, by name.
+    pub tags: Vec<String>,
+    /// Circle the post will be published into, if any.
+    pub circle_id: Option<String>,
+    /// When the scheduler publishes the draft. Drafts without one wait for
+    /// `publishDraft`.
+    pub publish_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// When the draft was last saved.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl PostDraft {
+    /// Where the draft sits among its author's drafts, last saved first.
+    pub fn page_cursor(&self) -> Cursor {
+        Cursor {
+            key: SortKey::Time(self.last_modified),
+            id: self.id.clone(),
+        }
+    }
+
+    pub async fn create(
+        db: &DbController,
+        draft: NewDraftRequest,
+        author: String,
+    ) -> Result<Self, String> {
+        if let Some(content) = &draft.content {
+            check_content_kind(content)?;
+        }
+
+        let now = db::now();
+        let draft = PostDraft {
+            id: Ulid::new().to_string(),
+            author,
+            title: non_empty(draft.title),
+            subtitle: non_empty(draft.subtitle),
+            cover_image: non_empty(draft.cover_image),
+            content: draft.content,
+            tags: draft.tags.unwrap_or_default(),
+            circle_id: non_empty(draft.circle_id),
+            publish_at: None,
+            created_at: now,
+            last_modified: now,
+        };
+        if db.drafts.insert(&draft).await.is_err() {
+            eprintln!("DATABASE ERROR: Error saving draft in PostDraft Create");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(draft)
+    }
+
+    /// Saves the fields the request carries and leaves the others alone, so
+    /// clients can autosave just what changed.
+    pub async fn update(
+        db: &DbController,
+        request: UpdateDraftRequest,
+        author: String,
+    ) -> Result<Self, String> {
+        if let Some(content) = &request.content {
+            check_content_kind(content)?;
+        }
+
+        let (request, author) = (&request, &author);
+        db.unit_of_work(|db| async move {
+            let mut draft = Self::get_own(&db, &request.draft_id, author).await?;
+            if let Some(title) = &request.title {
+                draft.title = non_empty(Some(title.clone()));
+            }
+            if let Some(subtitle) = &request.subtitle {
+                draft.subtitle = non_empty(Some(subtitle.clone()));
+            }
+            if let Some(cover_image) = &request.cover_image {
+                draft.cover_image = non_empty(Some(cover_image.clone()));
+            }
+            if let Some(content) = &request.content {
+                draft.content = Some(content.clone());
+            }
+            if let Some(tags) = &request.tags {
+                draft.tags = tags.clone();
+            }
+            if let Some(circle_id) = &request.circle_id {
+                draft.circle_id = non_empty(Some(circle_id.clone()));
+            }
+            draft.last_modified = db::now();
+
+            if db.drafts.update(&draft).await.is_err() {
+                eprintln!("DATABASE ERROR: Error saving draft in PostDraft Update");
+                return Err("Server error. Please try again.".to_string());
+            }
+            Ok(draft)
+        })
+        .await
+    }
+
+    pub async fn get(db: &DbController, id: String, author: String) -> Result<Self, String> {
+        Self::get_own(db, &id, &author).await
+    }
+
+    /// Drafts of the author, last saved first.
+    pub async fn get_drafts(
+        db: &DbController,
+        author: String,
+        args: PageArgs,
+    ) -> Result<PostDraftConnection, String> {
+        let author = &author;
+        pagination::connection(args, Self::page_cursor, |page: Page| async move {
+            let Ok(drafts) = db.drafts.list(author, &page).await else {
+                eprintln!("DATABASE ERROR: Error retrieving drafts in PostDraft GetDrafts");
+                return Err("Server error. Please try again.".to_string());
+            };
+            Ok(drafts)
+        })
+        .await
+    }
+
+    pub async fn delete(db: &DbController, id: String, author: String) -> Result<bool, String> {
+        let (id, author) = (&id, &author);
+        db.unit_of_work(|db| async move {
+            Self::get_own(&db, id, author).await?;
+            if db.drafts.delete(id).await.is_err() {
+                eprintln!("DATABASE ERROR: Error deleting draft in PostDraft Delete");
+                return Err("Server error. Please try again.".to_string());
+            }
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Schedules the draft to be published at `publish_at`, or takes it off
+    /// the schedule without one. The draft is checked as if it were
+    /// published now, so problems show up while its author is around.
+    pub async fn schedule(
+        db: &DbController,
+        id: String,
+        publish_at: Option<DateTime<Utc>>,
+        author: String,
+    ) -> Result<Self, String> {
+        if publish_at.is_some_and(|at| at <= Utc::now()) {
+            return Err("Drafts can only be scheduled for the future.".to_string());
+        }
+
+        let (id, author) = (&id, &author);
+        db.unit_of_work(|db| async move {
+            let mut draft = Self::get_own(&db, id, author).await?;
+            if publish_at.is_some() {
+                let post = draft.to_post()?;
+                Topic::validate_tags(&db, post.tags.as_deref().unwrap_or_default()).await?;
+                if let Some(circle_id) = &post.circle_id {
+                    Circle::check_can_post(&db, circle_id, author).await?;
+                }
+            }
+
+            if db.drafts.schedule(id, publish_at).await.is_err() {
+                eprintln!("DATABASE ERROR: Error scheduling draft in PostDraft Schedule");
+                return Err("Server error. Please try again.".to_string());
+            }
+            draft.publish_at = publish_at;
+            Ok(draft)
+        })
+        .await
+    }
+
+    /// Publishes the draft as a new post and deletes it, in one transaction.
+    pub async fn publish(
+        db: &DbController,
+        id: String,
+        author: String,
+    ) -> Result<ExpressionPost, String> {
+        let (id, author) = (&id, &author);
+        db.unit_of_work(|db| async move {
+            let draft = Self::get_own(&db, id, author).await?;
+            let post = draft.to_post()?;
+
+            // Claim the draft first, so a publish racing this one finds it gone
+            match db.drafts.delete(id).await {
+                Ok(true) => {}
+                Ok(false) => return Err("Draft does not exist.".to_string()),
+                Err(_) => {
+                    eprintln!("DATABASE ERROR: Error claiming draft in PostDraft Publish");
+                    return Err("Server error. Please try again.".to_string());
+                }
+            }
+            ExpressionPost::save(&db, post, author.clone()).await
+        })
+        .await
+    }
+
+    /// Publishes every draft whose time has come. Drafts that can no longer
+    /// be published, say because their author left the circle, are taken
+    /// off the schedule and kept. Drafts that fail for any other reason stay
+    /// scheduled, so the next run tries them again. Returns how many were
+    /// published and how many failed.
+    pub async fn publish_due(db: &DbController) -> Result<(usize, usize), String> {
+        let Ok(drafts) = db.drafts.due(db::now(), PUBLISH_BATCH_SIZE).await else {
+            eprintln!("DATABASE ERROR: Error retrieving drafts in PostDraft PublishDue");
+            return Err("Server error. Please try again.".to_string());
+        };
+
+        let (mut published, mut failed) = (0, 0);
+        for draft in drafts {
+            match Self::publish_blocker(db, &draft).await {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    eprintln!("PUBLISH_ERROR: Unscheduled draft {}: {reason}", draft.id);
+                    if db.drafts.schedule(&draft.id, None).await.is_err() {
+                        eprintln!(
+                            "DATABASE ERROR: Error unscheduling draft in PostDraft PublishDue"
+                        );
+                    }
+                    failed += 1;
+                    continue;
+                }
+                Err(_) => {
+                    failed += 1;
+                    continue;
+                }
+            }
+
+            if let Err(err) = Self::publish(db, draft.id.clone(), draft.author).await {
+                eprintln!("PUBLISH_ERROR: Unable to publish draft {}: {err}", draft.id);
+                failed += 1;
+            } else {
+                published += 1;
+            }
+        }
+        Ok((published, failed))
+    }
+
+    /// Why the draft can no longer be published, or `None` when it still
+    /// can. Errors only come from the database, which may well answer on the
+    /// next try.
+    async fn publish_blocker(
+        db: &DbController,
+        draft: &PostDraft,
+    ) -> Result<Option<String>, String> {
+        let post = match draft.to_post() {
+            Ok(post) => post,
+            Err(reason) => return Ok(Some(reason)),
+        };
+        let tags = match Topic::normalize_tags(&draft.tags) {
+            Ok(tags) => tags,
+            Err(reason) => return Ok(Some(reason)),
+        };
+        if let Some(unknown) = Topic::unknown(db, &tags).await?.first() {
+            return Ok(Some(format!("`{unknown}` is not a topic.")));
+        }
+        if let Some(circle_id) = &post.circle_id {
+            if !Circle::is_member(db, circle_id, &draft.author).await? {
+                return Ok(Some("Join this circle to post in it.".to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The draft, if `author` wrote it. Drafts of others do not exist as far
+    /// as anyone else can tell.
+    async fn get_own(db: &DbController, id: &str, author: &str) -> Result<Self, String> {
+        match db.drafts.get_by_id(id).await {
+            Ok(Some(draft)) if draft.author == author => Ok(draft),
+            Ok(_) => Err("Draft does not exist.".to_string()),
+            Err(_) => {
+                eprintln!("DATABASE ERROR: Error retrieving draft in PostDraft GetOwn");
+                Err("Server error. Please try again.".to_string())
+            }
+        }
+    }
+
+    /// The post the draft publishes as, once it has a title and content.
+    fn to_post(&self) -> Result<NewExpressionPost, String> {
+        let Some(title) = &self.title else {
+            return Err("Add a title before publishing the draft.".to_string());
+        };
+        let Some(content) = &self.content else {
+            return Err("Add content before publishing the draft.".to_string());
+        };
+
+        Ok(NewExpressionPost {
+            title: title.clone(),
+            subtitle: self.subtitle.clone(),
+            cover_image: self.cover_image.clone(),
+            content: content.clone(),
+            tags: Some(self.tags.clone()),
+            circle_id: self.circle_id.clone(),
+        })
+    }
+}
+
+#[derive(InputObject, Debug)]
+pub struct NewDraftRequest {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub cover_image: Option<String>,
+    pub content: Option<ExpressionPostContent>,
+    /// Names from the topic list. They are checked when the draft is
+    /// scheduled or published.
+    pub tags: Option<Vec<String>>,
+    /// Circle to publish the post into. Only its members may post there.
+    pub circle_id: Option<String>,
+}
+
+/// Fields left out keep their current value. An empty string clears the
+/// title, subtitle, cover image or circle, and an empty list the tags.
+#[derive(InputObject, Debug)]
+pub struct UpdateDraftRequest {
+    pub draft_id: String,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub cover_image: Option<String>,
+    pub content: Option<ExpressionPostContent>,
+    pub tags: Option<Vec<String>>,
+    pub circle_id: Option<String>,
+}
+
+/// Content kinds the database accepts.
+fn check_content_kind(content: &ExpressionPostContent) -> Result<(), String> {
+    match content.kind.as_str() {
+        "text" | "image" => Ok(()),
+        _ => Err("Content kind must be `text` or `image`.".to_string()),
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::models::fixtures::{self, backend_tests, profile};
+
+    async fn draft(db: &DbController, author: &str, tags: &[&str]) -> PostDraft {
+        let request = NewDraftRequest {
+            title: Some(fixtures::TITLE.to_string()),
+            subtitle: None,
+            cover_image: None,
+            content: Some(fixtures::content()),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            circle_id: None,
+        };
+        PostDraft::create(db, request, author.to_string())
+            .await
+            .unwrap()
+    }
+
+    async fn publish_turns_the_draft_into_a_post(db: DbController) {
+        profile(&db, "author").await;
+        let draft = draft(&db, "author", &["sleep"]).await;
+
+        PostDraft::publish(&db, draft.id.clone(), "author".to_string())
+            .await
+            .unwrap();
+        assert!(db.drafts.get_by_id(&draft.id).await.unwrap().is_none());
+    }
+
+    async fn failed_publish_keeps_the_draft(db: DbController) {
+        profile(&db, "author").await;
+        let draft = draft(&db, "author", &["not-a-topic"]).await;
+
+        assert!(
+            PostDraft::publish(&db, draft.id.clone(), "author".to_string())
+                .await
+                .is_err()
+        );
+        assert!(db.drafts.get_by_id(&draft.id).await.unwrap().is_some());
+    }
+
+    async fn publish_due_unschedules_drafts_that_cannot_be_published(db: DbController) {
+        profile(&db, "author").await;
+        let valid = draft(&db, "author", &["sleep"]).await;
+        let invalid = draft(&db, "author", &["not-a-topic"]).await;
+        let due = Utc::now() - chrono::Duration::minutes(1);
+        for draft in [&valid, &invalid] {
+            db.drafts.schedule(&draft.id, Some(due)).await.unwrap();
+        }
+
+        assert_eq!(PostDraft::publish_due(&db).await.unwrap(), (1, 1));
+        assert!(db.drafts.get_by_id(&valid.id).await.unwrap().is_none());
+        let kept = db.drafts.get_by_id(&invalid.id).await.unwrap().unwrap();
+        assert_eq!(kept.publish_at, None);
+    }
+
+    backend_tests!(
+        publish_turns_the_draft_into_a_post,
+        failed_publish_keeps_the_draft,
+        publish_due_unschedules_drafts_that_cannot_be_published
+    );
+}
@@ -1,45 +1,91 @@
-use std::vec;
-
-use crate::db::DbController;
-use async_graphql::{InputObject, SimpleObject};
-use chrono::{DateTime, Utc};
+use crate::{
+    community::{
+        loaders::{CommunityLoader, ViewerBookmark},
+        pagination::{self, ExpressionPostConnection, PageArgs},
+        viewer, CommunityEvent,
+    },
+    config::env_or,
+    db::{self, Cursor, DbController, NewPostRecord, NewReplyRecord, Page, PostFeed, SortKey},
+};
+use async_graphql::{
+    dataloader::DataLoader, ComplexObject, Context, Enum, InputObject, SimpleObject,
+};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, Row};
+use sqlx::FromRow;
 use ulid::Ulid;
 
 use super::{
+    circle::Circle,
     reply::{NewReplyRequest, Reply},
+    topic::{self, Topic},
     user_profile::UserProfile,
 };
 
+/// Default weight of a like in trending scores.
+const DEFAULT_LIKE_WEIGHT: f64 = 1.0;
+/// Default weight of a reply in trending scores.
+const DEFAULT_REPLY_WEIGHT: f64 = 2.0;
+/// Default time for the weight of a post's likes and replies to halve.
+const DEFAULT_HALF_LIFE_HOURS: f64 = 24.0;
+
 #[derive(Debug, FromRow, SimpleObject, Deserialize, Serialize)]
 pub struct ExpressionPostAggregate {
     pub posts: Vec<ExpressionPost>,
 }
 
-#[derive(Debug, FromRow, SimpleObject, InputObject, Deserialize, Serialize)]
+#[derive(Debug, Clone, FromRow, SimpleObject, InputObject, Deserialize, Serialize)]
 #[graphql(input_name = "NewExpressionPostContent")]
 pub struct ExpressionPostContent {
     pub kind: String,
     pub value: String,
 }
 
-#[derive(Debug, FromRow, SimpleObject, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct ExpressionPost {
-    id: String,
+    pub(crate) id: String,
     title: String,
     subtitle: Option<String>,
     cover_image: Option<String>,
     author: UserProfile,
     content: ExpressionPostContent,
+    /// Topics the post is tagged with, by name.
+    tags: Vec<String>,
+    /// Circle the post is published into, if any.
+    circle_id: Option<String>,
     replies: Vec<Reply>,
+    /// Live direct replies to the post. Replies to replies are counted on
+    /// the reply they answer, so they add neither to this count nor to the
+    /// post's trending score.
     reply_count: i32,
     likes: i32,
     created_at: DateTime<Utc>,
     last_modified: DateTime<Utc>,
+    /// Rank in trending feeds, see [`hot_score`].
+    #[graphql(skip)]
+    hot_score: f64,
 }
 
+#[ComplexObject]
 impl ExpressionPost {
+    /// Whether the logged in user bookmarked the post. Always false for
+    /// visitors.
+    async fn viewer_has_bookmarked(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let Some(viewer) = viewer(ctx) else {
+            return Ok(false);
+        };
+        let loader = ctx.data::<DataLoader<CommunityLoader>>()?;
+        let key = ViewerBookmark {
+            viewer,
+            post_id: self.id.clone(),
+        };
+        Ok(loader.load_one(key).await?.unwrap_or(false))
+    }
+}
+
+impl ExpressionPost {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         title: String,
@@ -64,79 +110,134 @@ impl ExpressionPost {
                 kind: content_type,
                 value: content_value,
             },
+            tags: vec![],
+            circle_id: None,
             replies,
             reply_count,
             likes,
             created_at,
             last_modified,
+            hot_score: 0.0,
+        }
+    }
+
+    pub fn with_hot_score(mut self, hot_score: f64) -> Self {
+        self.hot_score = hot_score;
+        self
+    }
+
+    pub fn with_circle(mut self, circle_id: Option<String>) -> Self {
+        self.circle_id = circle_id;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_replies(mut self, replies: Vec<Reply>) -> Self {
+        self.replies = replies;
+        self
+    }
+
+    /// Text a search looks through, body first.
+    pub fn searchable_text(&self) -> impl Iterator<Item = &str> {
+        [
+            Some(self.content.value.as_str()),
+            self.subtitle.as_deref(),
+            Some(self.title.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Where the post sits in `feed`.
+    pub fn page_cursor(&self, feed: &PostFeed) -> Cursor {
+        let key = match feed {
+            PostFeed::Trending { .. } | PostFeed::CircleTrending { .. } | PostFeed::Home { .. } => {
+                SortKey::Score(self.hot_score)
+            }
+            PostFeed::Recent
+            | PostFeed::ByAuthor(_)
+            | PostFeed::ByTag(_)
+            | PostFeed::CircleRecent(_) => SortKey::Time(self.created_at),
+        };
+        Cursor {
+            key,
+            id: self.id.clone(),
         }
     }
 
     pub async fn get_by_id(db: &DbController, id: String) -> Result<Self, String> {
         // Get post from database
-        let Ok(post) = sqlx::query(
-            r#"
-            SELECT
-                post.id AS id, 
-                post.title AS title, 
-                post.subtitle AS subtitle,
-                post.cover_image AS cover_image,
-                profile.id AS author_id, 
-                profile.username AS author_username, 
-                profile.avatar AS author_avatar, 
-                post.content_type AS content_type, 
-                post.content_value AS content_value, 
-                post.created_at AS created_at, 
-                post.last_modified AS last_modified,
-                IFNULL(COUNT(reply.id), 0) as reply_count,
-                (
-                    SELECT IFNULL(COUNT(parent_id), 0) FROM likes WHERE parent_id = post.id
-                ) AS likes
-            FROM expression_posts AS post
-            LEFT JOIN user_profiles AS profile ON profile.id = post.author
-            LEFT JOIN replies AS reply ON reply.parent = post.id
-            WHERE post.id = ?
-            GROUP BY post.id
-        "#,
-        )
-        .bind(id)
-        .fetch_one(&db.community_pool)
-        .await
-        else {
+        let Ok(Some(post)) = db.posts.get_by_id(&id).await else {
             eprintln!(
                 "DATABASE_ERROR: Error retrieving expression post in ExpressionPost GetById."
             );
             return Err("Expression post does not exist.".to_string());
         };
 
-        let post_id: String = post.get("id");
-
         // Get replies to post from database
-        let replies = match Reply::get_all_recursively(db, post_id.clone()).await {
+        let replies = match Reply::get_all_recursively(db, post.id.clone()).await {
             Ok(replies) => replies,
             Err(err) => return Err(err),
         };
 
         // Return post
-        Ok(Self::new(
-            post_id,
-            post.get("title"),
-            post.get("subtitle"),
-            post.get("cover_image"),
-            UserProfile::new(
-                post.get("author_id"),
-                post.get("author_username"),
-                post.get("author_avatar"),
-                vec![],
-            ),
-            post.get("content_type"),
-            post.get("content_value"),
-            replies,
-            post.get("reply_count"),
-            post.get("likes"),
-            post.get("created_at"),
-            post.get("last_modified"),
-        ))
+        Ok(post.with_replies(replies))
+    }
+
+    /// The post with its replies, if the viewer may read it. Posts in
+    /// circles that take join requests are only shown to their members.
+    pub async fn get_for_viewer(
+        db: &DbController,
+        id: String,
+        viewer: Option<String>,
+    ) -> Result<Self, String> {
+        let post = Self::get_by_id(db, id).await?;
+        if !post.can_be_read_by(db, viewer.as_deref()).await? {
+            return Err("Expression post does not exist.".to_string());
+        }
+        Ok(post)
+    }
+
+    /// The live post a thread hangs off, given the id of the post or of any
+    /// reply in its thread, if the viewer may read it. Threads of deleted
+    /// posts and of posts in circles the viewer cannot read are out of reach.
+    pub async fn of_thread(
+        db: &DbController,
+        parent_id: &str,
+        viewer: Option<&str>,
+    ) -> Result<Self, String> {
+        let Ok(post_id) = db.replies.post_of(parent_id).await else {
+            eprintln!("DATABASE_ERROR: Error retrieving thread post in ExpressionPost OfThread.");
+            return Err("Server error. Please try again.".to_string());
+        };
+        let post_id = post_id.as_deref().unwrap_or(parent_id);
+        match db.posts.get_by_id(post_id).await {
+            Ok(Some(post)) if post.can_be_read_by(db, viewer).await? => Ok(post),
+            Ok(_) => Err("Expression post does not exist.".to_string()),
+            Err(_) => {
+                eprintln!(
+                    "DATABASE_ERROR: Error retrieving expression post in ExpressionPost OfThread."
+                );
+                Err("Server error. Please try again.".to_string())
+            }
+        }
+    }
+
+    /// Whether the viewer may see the post: always, unless it is published
+    /// into a circle they cannot read.
+    pub async fn can_be_read_by(
+        &self,
+        db: &DbController,
+        viewer: Option<&str>,
+    ) -> Result<bool, String> {
+        match &self.circle_id {
+            Some(circle_id) => Circle::can_read(db, circle_id, viewer).await,
+            None => Ok(true),
+        }
     }
 
     pub async fn save(
@@ -144,77 +245,70 @@ impl ExpressionPost {
         post: NewExpressionPost,
         author: String,
     ) -> Result<Self, String> {
-        let profile = match UserProfile::get_by_id(db, author).await {
-            Ok(profile) => profile,
-            Err(err) => return Err(err),
-        };
+        let post = &post;
+        let author = &author;
+        db.unit_of_work(|db| async move {
+            let profile = match UserProfile::get_by_id(&db, author.clone()).await {
+                Ok(profile) => profile,
+                Err(err) => return Err(err),
+            };
+            let tags = Topic::validate_tags(&db, post.tags.as_deref().unwrap_or_default()).await?;
+            if let Some(circle_id) = &post.circle_id {
+                Circle::check_can_post(&db, circle_id, &profile.id).await?;
+            }
 
-        let post_id = Ulid::new().to_string();
-        if sqlx::query(
-            r#"
-            INSERT INTO expression_posts
-                (
-                    id, 
-                    title, 
-                    subtitle,
-                    cover_image,
-                    author, 
-                    content_type, 
-                    content_value
-                ) 
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-        "#,
-        )
-        .bind(&post_id)
-        .bind(&post.title)
-        .bind(&post.subtitle)
-        .bind(&post.cover_image)
-        .bind(&profile.id)
-        .bind(&post.content.kind)
-        .bind(&post.content.value)
-        .execute(&db.community_pool)
-        .await
-        .is_err()
-        {
-            eprintln!("DATABASE_ERROR: Error saving expression post in ExpressionPost Save.");
-            return Err("Server error. Please try again.".to_string());
-        }
+            let record = NewPostRecord {
+                id: Ulid::new().to_string(),
+                title: post.title.clone(),
+                subtitle: post.subtitle.clone(),
+                cover_image: post.cover_image.clone(),
+                author: profile.id.clone(),
+                content_type: post.content.kind.clone(),
+                content_value: post.content.value.clone(),
+                tags,
+                circle_id: post.circle_id.clone(),
+                created_at: db::now(),
+            };
 
-        let Ok(row) = sqlx::query(
-            r#"
-            SELECT 
-                created_at, 
-                last_modified 
-            FROM expression_posts 
-            WHERE id = ?
-        "#,
-        )
-        .bind(&post_id)
-        .fetch_one(&db.community_pool)
-        .await
-        else {
-            eprintln!(
-                "DATABASE_ERROR: Error getting expression post metadata in ExpressionPost Save."
-            );
-            return Err("Server error. Please try again.".to_string());
-        };
+            let score = hot_score(0, 0, record.created_at);
+            if db.posts.insert(&record).await.is_err()
+                || db
+                    .posts
+                    .set_hot_scores(&[(record.id.clone(), score)])
+                    .await
+                    .is_err()
+            {
+                eprintln!("DATABASE_ERROR: Error saving expression post in ExpressionPost Save.");
+                return Err("Server error. Please try again.".to_string());
+            }
+            db.invalidate(CommunityEvent::PostChanged);
 
-        Ok(ExpressionPost {
-            id: post_id,
-            title: post.title,
-            subtitle: post.subtitle,
-            cover_image: post.cover_image,
-            author: UserProfile::new(profile.id, profile.username, profile.avatar, profile.likes),
-            content: ExpressionPostContent {
-                kind: post.content.kind,
-                value: post.content.value,
-            },
-            replies: vec![],
-            reply_count: 0,
-            likes: 0,
-            created_at: row.get("created_at"),
-            last_modified: row.get("last_modified"),
+            Ok(ExpressionPost {
+                id: record.id,
+                title: record.title,
+                subtitle: record.subtitle,
+                cover_image: record.cover_image,
+                author: UserProfile::new(
+                    profile.id,
+                    profile.username,
+                    profile.avatar,
+                    profile.likes,
+                ),
+                content: ExpressionPostContent {
+                    kind: record.content_type,
+                    value: record.content_value,
+                },
+                tags: record.tags,
+                circle_id: record.circle_id,
+                replies: vec![],
+                reply_count: 0,
+                likes: 0,
+                created_at: record.created_at,
+                last_modified: record.created_at,
+                hot_score: score,
+            })
         })
+        .await
     }
 
     pub async fn update_likes(
@@ -222,33 +316,38 @@ impl ExpressionPost {
         update_request: UpdateLikesRequest,
         user_id: String,
     ) -> Result<(), String> {
-        let statement = if update_request.update_value == 1 {
-            r#"
-                INSERT INTO likes
-                    (
-                        parent_id, 
-                        author
-                    ) VALUES (?, ?)
-            "#
-        } else {
-            r#"
-                DELETE FROM likes 
-                WHERE parent_id = ? 
-                AND author = ?
-            "#
-        };
+        let update_request = &update_request;
+        let user_id = &user_id;
+        db.unit_of_work(|db| async move {
+            // Only live posts the user may read take likes, never replies
+            let Ok(post) = db.posts.get_by_id(&update_request.post_id).await else {
+                eprintln!("DATABASE_ERROR: Error retrieving expression post in ExpressionPost Update Likes.");
+                return Err("Server error. Please try again.".to_string());
+            };
+            let Some(post) = post else {
+                return Err("Expression post does not exist.".to_string());
+            };
+            if !post.can_be_read_by(&db, Some(user_id)).await? {
+                return Err("Expression post does not exist.".to_string());
+            }
 
-        if sqlx::query(statement)
-            .bind(update_request.post_id)
-            .bind(user_id)
-            .execute(&db.community_pool)
-            .await
-            .is_err()
-        {
-            eprintln!("DATABASE_ERROR: Error updating expression post likes in ExpressionPost Update Likes.");
-            return Err("Server error. Please try again.".to_string());
-        };
-        Ok(())
+            let result = if update_request.update_value == 1 {
+                db.posts.add_like(&update_request.post_id, user_id).await
+            } else {
+                db.posts.remove_like(&update_request.post_id, user_id).await
+            };
+
+            if result.is_err() {
+                eprintln!("DATABASE_ERROR: Error updating expression post likes in ExpressionPost Update Likes.");
+                return Err("Server error. Please try again.".to_string());
+            };
+            Self::refresh_hot_score(&db, &update_request.post_id).await?;
+            db.invalidate(CommunityEvent::LikeChanged {
+                user: user_id.clone(),
+            });
+            Ok(())
+        })
+        .await
     }
 
     pub async fn add_reply(
@@ -256,317 +355,533 @@ impl ExpressionPost {
         author: String,
         request: NewReplyRequest,
     ) -> Result<Reply, String> {
-        let reply_id = Ulid::new().to_string();
-        if sqlx::query(
-            r#"
-            INSERT INTO replies
-                (
-                    id, 
-                    author, 
-                    parent, 
-                    content
-                ) 
-            VALUES(?, ?, ?, ?)
-        "#,
-        )
-        .bind(&reply_id)
-        .bind(&author)
-        .bind(&request.parent)
-        .bind(&request.content)
-        .execute(&db.community_pool)
+        let author = &author;
+        let request = &request;
+        db.unit_of_work(|db| async move {
+            // Replies only go under live posts and live replies
+            let post = Self::of_thread(&db, &request.parent, Some(author)).await?;
+            if post.id != request.parent {
+                match db.replies.author_of(&request.parent).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => return Err("Reply does not exist.".to_string()),
+                    Err(_) => {
+                        eprintln!("DATABASE_ERROR: Error retrieving parent reply in ExpressionPost Add Reply.");
+                        return Err("Server error. Please try again.".to_string());
+                    }
+                }
+            }
+
+            let record = NewReplyRecord {
+                id: Ulid::new().to_string(),
+                author: author.clone(),
+                parent: request.parent.clone(),
+                content: request.content.clone(),
+                created_at: db::now(),
+            };
+
+            if db.replies.insert(&record).await.is_err() {
+                eprintln!("DATABASE_ERROR: Error adding reply to expression post in ExpressionPost Add Reply.");
+                return Err(
+                    "Seems there was an error adding your request. Please try again.".to_string(),
+                );
+            }
+            Self::refresh_hot_score(&db, &record.parent).await?;
+            db.invalidate(CommunityEvent::ReplyChanged);
+
+            let user_profile = match UserProfile::get_by_id(&db, record.author).await {
+                Ok(profile) => profile,
+                Err(err) => return Err(err),
+            };
+
+            Ok(Reply::new(
+                record.id,
+                user_profile,
+                record.parent,
+                record.content,
+                record.created_at,
+                record.created_at,
+                None,
+            ))
+        })
         .await
-        .is_err()
-        {
-            eprintln!("DATABASE_ERROR: Error adding reply to expression post in ExpressionPost Add Reply.");
-            return Err(
-                "Seems there was an error adding your request. Please try again.".to_string(),
-            );
+    }
+
+    /// Page of the feed of all posts, newest first. The first page is
+    /// served from the cache when possible.
+    pub async fn get_recent_posts(db: &DbController, page: Page) -> Result<Vec<Self>, String> {
+        let load = || async {
+            let Ok(posts) = db.posts.feed(&PostFeed::Recent, &page).await else {
+                eprintln!("DATABASE ERROR: Error retrieving recent expression posts in ExpressionPost GetRecentPosts");
+                return Err("Server error. Please try again.".to_string());
+            };
+
+            Ok(posts)
+        };
+
+        if page.is_first() {
+            db.cache.recent_posts(page.limit, load).await
+        } else {
+            load().await
         }
+    }
 
-        let user_profile = match UserProfile::get_by_id(db, author).await {
-            Ok(profile) => profile,
-            Err(err) => return Err(err),
+    /// Page of the posts created within `window`, highest trending score
+    /// first. The first page is served from the cache when possible.
+    pub async fn get_trending_posts(
+        db: &DbController,
+        window: TrendingWindow,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let load = || async {
+            let feed = PostFeed::Trending {
+                since: window.start(),
+            };
+            let Ok(posts) = db.posts.feed(&feed, &page).await else {
+                eprintln!("DATABASE ERROR: Error retrieving trending expression posts in ExpressionPost GetTrendingPosts");
+                return Err("Server error. Please try again.".to_string());
+            };
+
+            Ok(posts)
         };
 
-        let Ok(row) = sqlx::query(
-            r#"
-            SELECT 
-                created_at, 
-                last_modified 
-            FROM replies 
-            WHERE id = ?
-        "#,
-        )
-        .bind(&reply_id)
-        .fetch_one(&db.community_pool)
-        .await
-        else {
+        if page.is_first() {
+            db.cache.trending_posts(window, page.limit, load).await
+        } else {
+            load().await
+        }
+    }
+
+    /// Page of the posts of one author, newest first.
+    pub async fn get_posts_by_author(
+        db: &DbController,
+        author: String,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let Ok(posts) = db.posts.feed(&PostFeed::ByAuthor(author), &page).await else {
+            eprintln!("DATABASE ERROR: Error retrieving posts of author in ExpressionPost GetPostsByAuthor");
+            return Err("Server error. Please try again.".to_string());
+        };
+
+        Ok(posts)
+    }
+
+    /// Page of the posts tagged with a topic, newest first.
+    pub async fn get_posts_by_tag(
+        db: &DbController,
+        tag: String,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let feed = PostFeed::ByTag(topic::normalize(&tag));
+        let Ok(posts) = db.posts.feed(&feed, &page).await else {
             eprintln!(
-                "DATABASE_ERROR: Error getting expression post metadata in ExpressionPost Add Reply."
+                "DATABASE ERROR: Error retrieving posts of topic in ExpressionPost GetPostsByTag"
             );
             return Err("Server error. Please try again.".to_string());
         };
 
-        Ok(Reply::new(
-            reply_id,
-            user_profile,
-            request.parent,
-            request.content,
-            row.get("created_at"),
-            row.get("last_modified"),
-        ))
-    }
-
-    pub async fn get_recent_posts(db: &DbController, limit: u16) -> Result<Vec<Self>, String> {
-        let Ok(posts) = sqlx::query(
-            r#"
-            SELECT
-                post.id AS id, 
-                post.title AS title, 
-                post.subtitle AS subtitle, 
-                post.cover_image AS cover_image,
-                profile.id AS author_id, 
-                profile.username AS author_username, 
-                profile.avatar AS author_avatar, 
-                post.content_type AS content_type, 
-                post.content_value AS content_value,
-                IFNULL(COUNT(reply.parent), 0) AS reply_count, 
-                post.created_at AS created_at, 
-                post.last_modified AS last_modified,
-                (
-                    SELECT IFNULL(COUNT(DISTINCT parent_id), 0) FROM likes WHERE parent_id = post.id
-                ) AS likes
-            FROM expression_posts AS post
-            LEFT JOIN user_profiles AS profile ON profile.id = post.author
-            LEFT JOIN replies AS reply ON reply.parent = post.id
-            WHERE post.created_at > now() - interval 7 day
-            GROUP BY post.id
-            ORDER BY post.created_at
-            DESC LIMIT ?
-        "#,
-        )
-        .bind(limit)
-        .map(|x| {
-            ExpressionPost::new(
-                x.get("id"),
-                x.get("title"),
-                x.get("subtitle"),
-                x.get("cover_image"),
-                UserProfile::new(
-                    x.get("author_id"),
-                    x.get("author_username"),
-                    x.get("author_avatar"),
-                    vec![],
-                ),
-                x.get("content_type"),
-                x.get("content_value"),
-                vec![],
-                x.get("reply_count"),
-                x.get("likes"),
-                x.get("created_at"),
-                x.get("last_modified"),
-            )
-        })
-        .fetch_all(&db.community_pool)
-        .await
-        else {
-            eprintln!("DATABASE ERROR: Error retrieving recent expression posts in ExpressionPost GetRecentPosts");
+        Ok(posts)
+    }
+
+    /// Page of one of the feeds of a circle.
+    pub async fn get_circle_posts(
+        db: &DbController,
+        feed: &PostFeed,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let Ok(posts) = db.posts.feed(feed, &page).await else {
+            eprintln!(
+                "DATABASE ERROR: Error retrieving posts of circle in ExpressionPost GetCirclePosts"
+            );
             return Err("Server error. Please try again.".to_string());
         };
 
         Ok(posts)
     }
 
-    pub async fn get_trending_posts(db: &DbController, limit: u16) -> Result<Vec<Self>, String> {
-        // Change QUERY to find most liked within a two week span
-        let Ok(posts) = sqlx::query(
-            r#"
-            SELECT
-                post.id AS id, 
-                post.title AS title, 
-                post.subtitle AS subtitle, 
-                post.cover_image AS cover_image,
-                profile.id AS author_id, 
-                profile.username AS author_username, 
-                profile.avatar AS author_avatar, 
-                post.content_type AS content_type, 
-                post.content_value AS content_value,
-                IFNULL(COUNT(reply.parent), 0) AS reply_count, 
-                post.created_at AS created_at, 
-                post.last_modified AS last_modified,
-                (
-                    SELECT IFNULL(COUNT(DISTINCT parent_id), 0) FROM likes WHERE parent_id = post.id
-                ) AS likes
-            FROM expression_posts AS post
-            LEFT JOIN user_profiles AS profile ON profile.id = post.author
-            LEFT JOIN replies AS reply ON reply.parent = post.id
-            WHERE post.created_at > now() - interval 14 day
-            GROUP BY post.id
-            ORDER BY likes
-            DESC LIMIT ?
-        "#,
+    pub async fn get_recent_feed(
+        db: &DbController,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        pagination::connection(
+            args,
+            |post: &Self| post.page_cursor(&PostFeed::Recent),
+            |page| Self::get_recent_posts(db, page),
         )
-        .bind(limit)
-        .map(|x| {
-            ExpressionPost::new(
-                x.get("id"),
-                x.get("title"),
-                x.get("subtitle"),
-                x.get("cover_image"),
-                UserProfile::new(
-                    x.get("author_id"),
-                    x.get("author_username"),
-                    x.get("author_avatar"),
-                    vec![],
-                ),
-                x.get("content_type"),
-                x.get("content_value"),
-                vec![],
-                x.get("reply_count"),
-                x.get("likes"),
-                x.get("created_at"),
-                x.get("last_modified"),
-            )
-        })
-        .fetch_all(&db.community_pool)
         .await
-        else {
-            eprintln!("DATABASE ERROR: Error retrieving trending expression posts in ExpressionPost GetTrendingPosts");
+    }
+
+    pub async fn get_trending_feed(
+        db: &DbController,
+        window: TrendingWindow,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        let feed = PostFeed::Trending {
+            since: window.start(),
+        };
+        pagination::connection(
+            args,
+            |post: &Self| post.page_cursor(&feed),
+            |page| Self::get_trending_posts(db, window, page),
+        )
+        .await
+    }
+
+    pub async fn get_author_feed(
+        db: &DbController,
+        author: String,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        pagination::connection(
+            args,
+            |post: &Self| post.page_cursor(&PostFeed::Recent),
+            |page| Self::get_posts_by_author(db, author, page),
+        )
+        .await
+    }
+
+    pub async fn get_tag_feed(
+        db: &DbController,
+        tag: String,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        pagination::connection(
+            args,
+            |post: &Self| post.page_cursor(&PostFeed::Recent),
+            |page| Self::get_posts_by_tag(db, tag, page),
+        )
+        .await
+    }
+
+    /// Posts of a circle, newest first, or highest trending score first
+    /// within a `window`. Fails unless the viewer may read the circle.
+    pub async fn get_circle_feed(
+        db: &DbController,
+        circle_id: String,
+        window: Option<TrendingWindow>,
+        viewer: Option<String>,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        if !Circle::can_read(db, &circle_id, viewer.as_deref()).await? {
+            return Err("Join this circle to read its posts.".to_string());
+        }
+
+        let feed = match window {
+            Some(window) => PostFeed::CircleTrending {
+                circle: circle_id,
+                since: window.start(),
+            },
+            None => PostFeed::CircleRecent(circle_id),
+        };
+        let feed = &feed;
+        pagination::connection(
+            args,
+            |post: &Self| post.page_cursor(feed),
+            |page| Self::get_circle_posts(db, feed, page),
+        )
+        .await
+    }
+
+    /// Page of the home feed of a viewer.
+    pub async fn get_home_posts(
+        db: &DbController,
+        feed: &PostFeed,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let Ok(posts) = db.posts.feed(feed, &page).await else {
+            eprintln!("DATABASE ERROR: Error retrieving home feed in ExpressionPost GetHomePosts");
             return Err("Server error. Please try again.".to_string());
         };
 
         Ok(posts)
     }
 
+    /// Posts of the users and topics the viewer follows from the widest
+    /// trending window, highest trending score first. Viewers whose follows
+    /// posted nothing within that window, like viewers who follow nobody,
+    /// get the trending feed instead.
+    pub async fn get_home_feed(
+        db: &DbController,
+        viewer: String,
+        args: PageArgs,
+    ) -> Result<ExpressionPostConnection, String> {
+        let feed = PostFeed::Home {
+            viewer,
+            since: TrendingWindow::Month.start(),
+        };
+        let feed = &feed;
+        let home = pagination::connection(
+            args.clone(),
+            |post: &Self| post.page_cursor(feed),
+            |page| Self::get_home_posts(db, feed, page),
+        )
+        .await?;
+
+        // An empty page is either past the end of the home feed or all of it
+        if !home.edges.is_empty()
+            || !Self::get_home_posts(db, feed, Page::first(1))
+                .await?
+                .is_empty()
+        {
+            return Ok(home);
+        }
+        Self::get_trending_feed(db, TrendingWindow::default(), args).await
+    }
+
     pub async fn update_content(
         db: &DbController,
         request: UpdateContentRequest,
         logged_in_user: String,
     ) -> Result<Self, String> {
-        let Ok(mut tx) = db.community_pool.begin().await else {
-            eprintln!(
-                "DATABASE_ERROR: Error starting transaction in ExpressionPost UpdateContent."
-            );
-            return Err("Server error. Please try again".to_string());
-        };
+        let request = &request;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(author) = db.posts.author_of(&request.post_id).await else {
+                eprintln!("DATABASE_ERROR: Error getting author in ExpressionPost UpdateContent.");
+                return Err("Server error. Please try again.".to_string());
+            };
 
-        let Ok(author) = sqlx::query("SELECT author FROM expression_posts WHERE id = ?")
-            .bind(&logged_in_user)
-            .fetch_optional(&mut *tx)
-            .await
-        else {
-            eprintln!("DATABASE_ERROR: Error getting author in ExpressionPost UpdateContent.");
-            return Err("Server error. Please try again.".to_string());
-        };
-
-        // Check to make sure person updating post is author
-        if author.is_none() {
-            return Err("Expression post does not exist.".to_string());
-        } else {
-            let author: String = author.unwrap().get("author");
-            if author != logged_in_user {
+            // Check to make sure person updating post is author
+            let Some(author) = author else {
+                return Err("Expression post does not exist.".to_string());
+            };
+            if author != *logged_in_user {
                 return Err(
                     "User making request and expression post author do not match.".to_string(),
                 );
             }
-        }
+            // Posts in circles the author left are out of reach, as they are
+            // for replies
+            Self::of_thread(&db, &request.post_id, Some(logged_in_user)).await?;
 
-        if sqlx::query(
-            "UPDATE expression_posts SET content_type = ?, content_value = ? WHERE id = ?",
-        )
-        .bind(request.content_type)
-        .bind(request.content_value)
-        .bind(&request.post_id)
-        .execute(&mut *tx)
+            if db
+                .posts
+                .update_content(
+                    &request.post_id,
+                    &request.content_type,
+                    &request.content_value,
+                )
+                .await
+                .is_err()
+            {
+                eprintln!(
+                    "DATABASE_ERROR: Error updating expression post in ExpressionPost UpdateContent."
+                );
+                return Err("Server error. Please try again.".to_string());
+            };
+            db.invalidate(CommunityEvent::PostChanged);
+
+            Self::get_by_id(&db, request.post_id.clone()).await
+        })
         .await
-        .is_err()
-        {
-            eprintln!(
-                "DATABASE_ERROR: Error updating expression post in ExpressionPost UpdateContent."
-            );
-            return Err("Server error. Please try again.".to_string());
-        };
+    }
+
+    /// Tombstones the post. Its author and moderators may delete it; replies
+    /// and likes stay in place until the post is purged.
+    pub async fn delete(
+        db: &DbController,
+        post_id: String,
+        logged_in_user: String,
+    ) -> Result<bool, String> {
+        let post_id = &post_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(author) = db.posts.author_of(post_id).await else {
+                eprintln!("DATABASE_ERROR: Error getting author in ExpressionPost Delete.");
+                return Err("Server error. Please try again.".to_string());
+            };
 
-        let post = Self::get_by_id(db, request.post_id).await?;
+            // Check to make sure person deleting post is author or a moderator
+            let Some(author) = author else {
+                return Err("Expression post does not exist.".to_string());
+            };
+            if author != *logged_in_user && !UserProfile::is_moderator(&db, logged_in_user).await {
+                return Err(
+                    "User making request and expression post author do not match.".to_string(),
+                );
+            }
 
-        let _ = tx.commit().await;
+            if db
+                .posts
+                .soft_delete(post_id, logged_in_user, db::now())
+                .await
+                .is_err()
+            {
+                eprintln!(
+                    "DATABASE_ERROR: Error deleting expression post in ExpressionPost Delete."
+                );
+                return Err(
+                    "There seems to be an issue deleting this post. Please try again.".to_string(),
+                );
+            }
+            db.invalidate(CommunityEvent::PostChanged);
 
-        Ok(post)
+            Ok(true)
+        })
+        .await
     }
 
-    pub async fn delete(
+    /// Brings a deleted post back with its replies and likes. Authors may
+    /// undo their own deletions; moderators may undo any.
+    pub async fn restore(
         db: &DbController,
         post_id: String,
         logged_in_user: String,
     ) -> Result<bool, String> {
-        let Ok(mut tx) = db.community_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction in ExpressionPost Delete.");
-            return Err("Server error. Please try again".to_string());
-        };
+        let post_id = &post_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(deletion) = db.posts.deletion(post_id).await else {
+                eprintln!("DATABASE_ERROR: Error getting deletion in ExpressionPost Restore.");
+                return Err("Server error. Please try again.".to_string());
+            };
 
-        let Ok(author) = sqlx::query("SELECT author FROM expression_posts WHERE id = ?")
-            .bind(&logged_in_user)
-            .fetch_optional(&mut *tx)
-            .await
-        else {
-            eprintln!("DATABASE_ERROR: Error getting author in ExpressionPost Delete.");
-            return Err("Server error. Please try again.".to_string());
-        };
+            let Some(deletion) = deletion else {
+                return Err("Expression post does not exist or is not deleted.".to_string());
+            };
+            if !deletion.may_restore(logged_in_user)
+                && !UserProfile::is_moderator(&db, logged_in_user).await
+            {
+                return Err("Only the author or a moderator can restore this post.".to_string());
+            }
 
-        // Check to make sure person deleting post is author
-        if author.is_none() {
-            return Err("Expression post does not exist.".to_string());
-        } else {
-            let author: String = author.unwrap().get("author");
-            if author != logged_in_user {
-                return Err(
-                    "User making request and expression post author do not match.".to_string(),
+            if db.posts.restore(post_id).await.is_err() {
+                eprintln!(
+                    "DATABASE_ERROR: Error restoring expression post in ExpressionPost Restore."
                 );
+                return Err("Server error. Please try again.".to_string());
             }
+            db.invalidate(CommunityEvent::PostChanged);
+
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Permanently removes posts and replies deleted more than `retention`
+    /// ago. Returns how many posts and replies were removed.
+    pub async fn purge_deleted(
+        db: &DbController,
+        retention: Duration,
+    ) -> Result<(u64, u64), String> {
+        db.unit_of_work(|db| async move {
+            let before = db::now() - retention;
+
+            let posts = match db.posts.purge_deleted(before).await {
+                Ok(purged) => purged,
+                Err(err) => return Err(err.to_string()),
+            };
+            db.invalidate(CommunityEvent::Purged);
+            let replies = match db.replies.purge_deleted(before).await {
+                Ok(purged) => purged,
+                Err(err) => return Err(err.to_string()),
+            };
+
+            Ok((posts, replies))
+        })
+        .await
+    }
+
+    /// Recomputes the trending score of the post after its counters changed.
+    /// Does nothing for ids of replies.
+    async fn refresh_hot_score(db: &DbController, post_id: &str) -> Result<(), String> {
+        let Ok(post) = db.posts.get_by_id(post_id).await else {
+            eprintln!("DATABASE_ERROR: Error retrieving expression post in ExpressionPost RefreshHotScore.");
+            return Err("Server error. Please try again.".to_string());
+        };
+        let Some(post) = post else {
+            return Ok(());
+        };
+
+        let score = hot_score(post.likes, post.reply_count, post.created_at);
+        if db.posts.set_hot_scores(&[(post.id, score)]).await.is_err() {
+            eprintln!("DATABASE_ERROR: Error saving hot score in ExpressionPost RefreshHotScore.");
+            return Err("Server error. Please try again.".to_string());
         }
+        db.invalidate(CommunityEvent::ScoresChanged);
+        Ok(())
+    }
 
-        // Delete post and likes
-        if sqlx::query("DELETE FROM expression_posts WHERE id = ?")
-            .bind(&post_id)
-            .execute(&mut *tx)
+    /// Recomputes the trending score of every post the widest trending window
+    /// holds, picking up changes to the scoring settings and counters that
+    /// moved without a rescore. Only scores that changed are stored.
+    /// Returns the number of posts rescored.
+    pub async fn refresh_hot_scores(db: &DbController) -> Result<usize, String> {
+        let posts = db
+            .posts
+            .counts_since(TrendingWindow::Month.start())
             .await
-            .is_err()
-        {
-            eprintln!("DATABASE_ERROR: Error deleting expression post in ExpressionPost Delete.");
-            return Err(
-                "There seems to be an issue deleting this post. Please try again.".to_string(),
-            );
+            .map_err(|err| err.to_string())?;
+        let scores: Vec<(String, f64)> = posts
+            .into_iter()
+            .filter_map(|post| {
+                let score = hot_score(post.likes, post.replies, post.created_at);
+                (score != post.hot_score).then_some((post.id, score))
+            })
+            .collect();
+        if scores.is_empty() {
+            return Ok(0);
         }
 
-        if sqlx::query("DELETE FROM likes WHERE parent_id = ?")
-            .bind(&post_id)
-            .execute(&mut *tx)
+        db.posts
+            .set_hot_scores(&scores)
             .await
-            .is_err()
-        {
-            eprintln!(
-                "DATABASE_ERROR: Error deleting expression post likes in ExpressionPost Delete."
-            );
-            return Err(
-                "There seems to be an issue deleting this post. Please try again.".to_string(),
-            );
+            .map_err(|err| err.to_string())?;
+        db.invalidate(CommunityEvent::ScoresChanged);
+        Ok(scores.len())
+    }
+
+    /// Recomputes every post's like and reply counters from the likes and
+    /// replies themselves. Returns how many posts were off.
+    pub async fn repair_counts(db: &DbController) -> Result<u64, String> {
+        match db.posts.repair_counts().await {
+            Ok(repaired) => {
+                db.invalidate(CommunityEvent::PostChanged);
+                Ok(repaired)
+            }
+            Err(err) => Err(err.to_string()),
         }
+    }
+}
 
-        // Delete all replies associated with post
-        if Reply::delete_all_from_post(db, post_id).await.is_err() {
-            eprintln!(
-                "DATABASE_ERROR: Error deleting expression post replies in ExpressionPost Delete."
-            );
-            return Err(
-                "There seems to be an issue deleting this post. Please try again.".to_string(),
-            );
-        };
+/// Span of time a trending feed looks back over.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+pub enum TrendingWindow {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
 
-        let _ = tx.commit().await;
-        Ok(true)
+impl TrendingWindow {
+    /// Creation time of the oldest posts the window holds.
+    pub fn start(self) -> DateTime<Utc> {
+        let days = match self {
+            TrendingWindow::Day => 1,
+            TrendingWindow::Week => 7,
+            TrendingWindow::Month => 30,
+        };
+        db::now() - Duration::days(days)
     }
 }
 
+/// Trending score of a post. A like counts `TRENDING_LIKE_WEIGHT` and a
+/// direct reply `TRENDING_REPLY_WEIGHT`, and their total loses half its weight every
+/// `TRENDING_HALF_LIFE_HOURS` after the post was created.
+///
+/// The score is kept as `log2(1 + weight) + created_at / half_life`, which
+/// ranks posts exactly like the decayed weight does at any one moment but
+/// never changes while the post's counters stay the same. Only likes,
+/// replies and changes to the settings call for a rescore.
+fn hot_score(likes: i32, replies: i32, created_at: DateTime<Utc>) -> f64 {
+    let like_weight: f64 = env_or("TRENDING_LIKE_WEIGHT", DEFAULT_LIKE_WEIGHT);
+    let reply_weight: f64 = env_or("TRENDING_REPLY_WEIGHT", DEFAULT_REPLY_WEIGHT);
+    let half_life_hours: f64 = env_or("TRENDING_HALF_LIFE_HOURS", DEFAULT_HALF_LIFE_HOURS);
+
+    let weight = like_weight * f64::from(likes) + reply_weight * f64::from(replies);
+    let half_lives = created_at.timestamp() as f64 / (half_life_hours.max(1.0) * 3600.0);
+    (1.0 + weight.max(0.0)).log2() + half_lives
+}
+
 /********** REQUEST OBJECTS **********/
 
 /****** ADD VALIDATION CHECKS ******/
@@ -576,6 +891,10 @@ pub struct NewExpressionPost {
     pub subtitle: Option<String>,
     pub cover_image: Option<String>,
     pub content: ExpressionPostContent,
+    /// Names from the topic list, up to `POST_MAX_TAGS` of them.
+    pub tags: Option<Vec<String>>,
+    /// Circle to publish the post into. Only its members may post there.
+    pub circle_id: Option<String>,
 }
 
 /****** ADD VALIDATION CHECKS ******/
@@ -592,3 +911,317 @@ pub struct UpdateContentRequest {
     content_type: String,
     content_value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::connection::CursorType;
+
+    use super::*;
+    use crate::community::models::fixtures::{self, backend_tests, post, profile, reply};
+
+    fn like(post_id: &str, update_value: u8) -> UpdateLikesRequest {
+        UpdateLikesRequest {
+            post_id: post_id.to_string(),
+            update_value,
+        }
+    }
+
+    async fn likes(db: &DbController, post_id: &str) -> i32 {
+        ExpressionPost::get_by_id(db, post_id.to_string())
+            .await
+            .unwrap()
+            .likes
+    }
+
+    async fn save_update_and_delete_post(db: DbController) {
+        profile(&db, "ada").await;
+        profile(&db, "bo").await;
+        let (author, other) = ("ada".to_string(), "bo".to_string());
+        let saved = post(&db, &author, None).await;
+
+        let stored = ExpressionPost::get_by_id(&db, saved.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(stored.title, fixtures::TITLE);
+        assert_eq!(stored.content.value, fixtures::CONTENT);
+
+        let update = |value: &str| UpdateContentRequest {
+            post_id: saved.id.clone(),
+            content_type: "text".to_string(),
+            content_value: value.to_string(),
+        };
+        let updated = ExpressionPost::update_content(&db, update("Evening"), author.clone())
+            .await
+            .unwrap();
+        assert_eq!(updated.content.value, "Evening");
+        assert!(
+            ExpressionPost::update_content(&db, update("Hijacked"), other.clone())
+                .await
+                .is_err()
+        );
+
+        assert!(ExpressionPost::delete(&db, saved.id.clone(), other)
+            .await
+            .is_err());
+        assert!(
+            ExpressionPost::delete(&db, saved.id.clone(), author.clone())
+                .await
+                .unwrap()
+        );
+        assert!(ExpressionPost::get_by_id(&db, saved.id.clone())
+            .await
+            .is_err());
+
+        ExpressionPost::restore(&db, saved.id.clone(), author)
+            .await
+            .unwrap();
+        let restored = ExpressionPost::get_by_id(&db, saved.id).await.unwrap();
+        assert_eq!(restored.content.value, "Evening");
+    }
+
+    async fn likes_count_once_per_user(db: DbController) {
+        profile(&db, "ada").await;
+        profile(&db, "bo").await;
+        let (author, fan) = ("ada".to_string(), "bo".to_string());
+        let saved = post(&db, &author, None).await;
+
+        ExpressionPost::update_likes(&db, like(&saved.id, 1), fan.clone())
+            .await
+            .unwrap();
+        ExpressionPost::update_likes(&db, like(&saved.id, 1), fan.clone())
+            .await
+            .unwrap();
+        assert_eq!(likes(&db, &saved.id).await, 1);
+
+        ExpressionPost::update_likes(&db, like(&saved.id, 1), author)
+            .await
+            .unwrap();
+        assert_eq!(likes(&db, &saved.id).await, 2);
+
+        ExpressionPost::update_likes(&db, like(&saved.id, 0), fan.clone())
+            .await
+            .unwrap();
+        ExpressionPost::update_likes(&db, like(&saved.id, 0), fan)
+            .await
+            .unwrap();
+        assert_eq!(likes(&db, &saved.id).await, 1);
+    }
+
+    async fn replies_count_and_close_with_the_post(db: DbController) {
+        profile(&db, "ada").await;
+        let author = "ada".to_string();
+        let saved = post(&db, &author, None).await;
+        let reply = |parent: &str| NewReplyRequest {
+            content: "Lovely".to_string(),
+            parent: parent.to_string(),
+        };
+
+        ExpressionPost::add_reply(&db, author.clone(), reply(&saved.id))
+            .await
+            .unwrap();
+        let stored = ExpressionPost::get_by_id(&db, saved.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(stored.reply_count, 1);
+
+        ExpressionPost::delete(&db, saved.id.clone(), author.clone())
+            .await
+            .unwrap();
+        assert!(ExpressionPost::add_reply(&db, author, reply(&saved.id))
+            .await
+            .is_err());
+    }
+
+    async fn recent_feed_pages_hold_every_live_post_once(db: DbController) {
+        profile(&db, "ada").await;
+        let mut ids = vec![];
+        for _ in 0..6 {
+            ids.push(post(&db, "ada", None).await.id);
+        }
+        let deleted = ids.remove(2);
+        ExpressionPost::delete(&db, deleted, "ada".to_string())
+            .await
+            .unwrap();
+        // Newest first, with posts of the same second ordered by id. Ids grow
+        // with time down to the millisecond, so that is descending id order
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut seen = vec![];
+        let mut page = Page::first(2);
+        loop {
+            let posts = ExpressionPost::get_recent_posts(&db, page.clone())
+                .await
+                .unwrap();
+            let Some(last) = posts.last() else {
+                break;
+            };
+            page.after = Some(last.page_cursor(&PostFeed::Recent));
+            seen.extend(posts.into_iter().map(|post| post.id));
+        }
+        assert_eq!(seen, ids);
+
+        // Pages read back from a cursor come out in feed order too
+        let cursor = ExpressionPost::get_by_id(&db, ids[3].clone())
+            .await
+            .unwrap()
+            .page_cursor(&PostFeed::Recent);
+        let page = Page {
+            before: Some(cursor),
+            limit: 2,
+            from_end: true,
+            ..Page::default()
+        };
+        let posts = ExpressionPost::get_recent_posts(&db, page).await.unwrap();
+        let ids_before: Vec<String> = posts.into_iter().map(|post| post.id).collect();
+        assert_eq!(ids_before, ids[1..3]);
+    }
+
+    async fn repair_counts_keeps_correct_counters(db: DbController) {
+        profile(&db, "ada").await;
+        profile(&db, "bo").await;
+        let saved = post(&db, "ada", None).await;
+        ExpressionPost::update_likes(&db, like(&saved.id, 1), "bo".to_string())
+            .await
+            .unwrap();
+        reply(&db, "bo", &saved.id, "Lovely").await;
+        let removed = reply(&db, "bo", &saved.id, "Gone").await;
+        Reply::delete(&db, removed.id, "bo".to_string())
+            .await
+            .unwrap();
+
+        // Deleted replies stay out of the recount
+        assert_eq!(ExpressionPost::repair_counts(&db).await.unwrap(), 0);
+        let stored = ExpressionPost::get_by_id(&db, saved.id).await.unwrap();
+        assert_eq!((stored.likes, stored.reply_count), (1, 1));
+    }
+
+    async fn posts_in_circles_left_cannot_be_edited(db: DbController) {
+        profile(&db, "owner").await;
+        profile(&db, "member").await;
+        let circle_id = fixtures::circle_with_member(&db, "owner", "member").await;
+        let saved = post(&db, "member", Some(&circle_id)).await;
+        let update = || UpdateContentRequest {
+            post_id: saved.id.clone(),
+            content_type: "text".to_string(),
+            content_value: "Evening".to_string(),
+        };
+        ExpressionPost::update_content(&db, update(), "member".to_string())
+            .await
+            .unwrap();
+
+        Circle::remove_member(&db, circle_id, "member".to_string(), "owner".to_string())
+            .await
+            .unwrap();
+        let Err(err) = ExpressionPost::update_content(&db, update(), "member".to_string()).await
+        else {
+            panic!("A post in a circle its author left was edited");
+        };
+        assert_eq!(err, "Expression post does not exist.");
+    }
+
+    async fn trending_cursors_page_on_after_likes(db: DbController) {
+        profile(&db, "ada").await;
+        profile(&db, "bo").await;
+        for _ in 0..3 {
+            post(&db, "ada", None).await;
+        }
+        let page = |after: Option<String>| PageArgs {
+            after,
+            first: Some(2),
+            ..PageArgs::default()
+        };
+
+        let first = ExpressionPost::get_trending_feed(&db, TrendingWindow::Day, page(None))
+            .await
+            .unwrap();
+        let last = first.edges.last().unwrap();
+        let cursor = last.cursor.encode_cursor();
+        assert_eq!(ExpressionPost::refresh_hot_scores(&db).await.unwrap(), 0);
+
+        // The liked post moves up past the cursor, which still marks the
+        // score and id the first page ended on
+        ExpressionPost::update_likes(&db, like(&last.node.id, 1), "bo".to_string())
+            .await
+            .unwrap();
+        let next = ExpressionPost::get_trending_feed(&db, TrendingWindow::Day, page(Some(cursor)))
+            .await
+            .unwrap();
+        assert_eq!(next.edges.len(), 1);
+        assert!(first
+            .edges
+            .iter()
+            .all(|edge| edge.node.id != next.edges[0].node.id));
+
+        let first = ExpressionPost::get_trending_feed(&db, TrendingWindow::Day, page(None))
+            .await
+            .unwrap();
+        assert_eq!(first.edges[0].node.id, last.node.id);
+    }
+
+    async fn home_feed_falls_back_to_trending_without_recent_follows(db: DbController) {
+        for id in ["ada", "bo", "cy"] {
+            profile(&db, id).await;
+        }
+        db.topics.insert("rust", "bo", db::now()).await.unwrap();
+        // The followed author and topic last had a post before the window
+        let stale = NewPostRecord {
+            id: Ulid::new().to_string(),
+            title: fixtures::TITLE.to_string(),
+            subtitle: None,
+            cover_image: None,
+            author: "bo".to_string(),
+            content_type: "text".to_string(),
+            content_value: fixtures::CONTENT.to_string(),
+            tags: vec!["rust".to_string()],
+            circle_id: None,
+            created_at: db::now() - Duration::days(40),
+        };
+        db.posts.insert(&stale).await.unwrap();
+        UserProfile::follow(&db, "bo".to_string(), "ada".to_string())
+            .await
+            .unwrap();
+        Topic::follow(&db, "rust".to_string(), "ada".to_string())
+            .await
+            .unwrap();
+        let trending = post(&db, "cy", None).await;
+
+        let home_ids = || async {
+            let home = ExpressionPost::get_home_feed(&db, "ada".to_string(), PageArgs::default())
+                .await
+                .unwrap();
+            home.edges
+                .into_iter()
+                .map(|edge| edge.node.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(home_ids().await, [trending.id]);
+
+        // Once a follow posts again, the home feed only holds their posts
+        let followed = post(&db, "bo", None).await;
+        assert_eq!(home_ids().await, [followed.id]);
+    }
+
+    #[test]
+    fn hot_scores_trade_weight_against_age() {
+        let created = db::now();
+        let half_life_later = created + Duration::hours(DEFAULT_HALF_LIFE_HOURS as i64);
+
+        assert!(hot_score(1, 0, created) > hot_score(0, 0, created));
+        assert!(hot_score(0, 1, created) > hot_score(1, 0, created));
+        assert!(hot_score(0, 0, half_life_later) > hot_score(0, 0, created));
+        // Doubling one plus the weight makes up for one half-life of age
+        let doubled = hot_score(1, 0, created) - hot_score(0, 0, half_life_later);
+        assert!(doubled.abs() < 1e-9, "{doubled}");
+    }
+
+    backend_tests!(
+        save_update_and_delete_post,
+        likes_count_once_per_user,
+        replies_count_and_close_with_the_post,
+        recent_feed_pages_hold_every_live_post_once,
+        repair_counts_keeps_correct_counters,
+        posts_in_circles_left_cannot_be_edited,
+        trending_cursors_page_on_after_likes,
+        home_feed_falls_back_to_trending_without_recent_follows,
+    );
+}
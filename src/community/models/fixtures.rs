@@ -0,0 +1,104 @@
+//! Profiles, posts and replies the model tests build their scenarios on, and
+//! [`backend_tests!`] to run those scenarios against every store.
+
+use crate::{
+    community::{
+        models::{
+            circle::NewCircleRequest, expression_post::NewExpressionPost, reply::NewReplyRequest,
+        },
+        Circle, CircleVisibility, ExpressionPost, ExpressionPostContent, Reply, UserProfile,
+    },
+    db::DbController,
+};
+
+pub const TITLE: &str = "First light";
+pub const CONTENT: &str = "Morning over the bay";
+
+/// Registers a profile whose id and username are both `id`.
+pub async fn profile(db: &DbController, id: &str) {
+    UserProfile::register(db, id.to_string(), id.to_string())
+        .await
+        .unwrap();
+}
+
+/// Text content shared by every fixture post and draft.
+pub fn content() -> ExpressionPostContent {
+    ExpressionPostContent {
+        kind: "text".to_string(),
+        value: CONTENT.to_string(),
+    }
+}
+
+/// Publishes a post by `author`, into the circle when one is given.
+pub async fn post(db: &DbController, author: &str, circle_id: Option<&str>) -> ExpressionPost {
+    let post = NewExpressionPost {
+        title: TITLE.to_string(),
+        subtitle: None,
+        cover_image: None,
+        content: content(),
+        tags: None,
+        circle_id: circle_id.map(str::to_string),
+    };
+    ExpressionPost::save(db, post, author.to_string())
+        .await
+        .unwrap()
+}
+
+/// Replies to `parent`, which is either a post or another reply.
+pub async fn reply(db: &DbController, author: &str, parent: &str, content: &str) -> Reply {
+    let request = NewReplyRequest {
+        content: content.to_string(),
+        parent: parent.to_string(),
+    };
+    ExpressionPost::add_reply(db, author.to_string(), request)
+        .await
+        .unwrap()
+}
+
+/// Creates a request-to-join circle of `owner` and lets `member` in.
+/// Returns the id of the circle.
+pub async fn circle_with_member(db: &DbController, owner: &str, member: &str) -> String {
+    let request = NewCircleRequest {
+        name: "Night owls".to_string(),
+        description: None,
+        rules: None,
+        visibility: Some(CircleVisibility::Request),
+    };
+    let circle = Circle::create(db, request, owner.to_string())
+        .await
+        .unwrap();
+    Circle::join(db, circle.id.clone(), member.to_string())
+        .await
+        .unwrap();
+    Circle::approve_member(db, circle.id.clone(), member.to_string(), owner.to_string())
+        .await
+        .unwrap();
+    circle.id
+}
+
+/// Turns async scenario functions taking a [`DbController`] into tests run
+/// against the in-memory store and, with the `sqlite` feature, against
+/// migrated in-memory SQLite databases.
+macro_rules! backend_tests {
+    ($($scenario:ident),+ $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    super::$scenario($crate::db::DbController::in_memory()).await;
+                }
+            )+
+        }
+
+        #[cfg(feature = "sqlite")]
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    super::$scenario($crate::db::DbController::sqlite_in_memory().await).await;
+                }
+            )+
+        }
+    };
+}
+pub(crate) use backend_tests;
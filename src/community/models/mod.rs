@@ -1,3 +1,10 @@
+pub mod bookmark;
+pub mod circle;
+pub mod draft;
 pub mod expression_post;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod reply;
+pub mod search;
+pub mod topic;
 pub mod user_profile;
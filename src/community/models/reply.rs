@@ -1,23 +1,80 @@
-use async_graphql::{InputObject, SimpleObject};
+use std::collections::HashMap;
+
+use async_graphql::{
+    connection::{CursorType, OpaqueCursor},
+    ComplexObject, InputObject, SimpleObject,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{mysql::MySqlRow, Row};
 
-use crate::db::DbController;
+use crate::{
+    community::{
+        pagination::{self, PageArgs, ReplyConnection},
+        CommunityEvent,
+    },
+    config::env_or,
+    db::{self, Cursor, DbController, Page, SortKey, ThreadPage},
+};
+
+use super::{expression_post::ExpressionPost, user_profile::UserProfile};
+
+/// Shown instead of the author and content of a deleted reply.
+const REMOVED_PLACEHOLDER: &str = "[removed]";
+/// Default number of levels of replies loaded below a post or reply.
+const DEFAULT_THREAD_MAX_DEPTH: u16 = 5;
+/// Default number of replies loaded directly under any one post or reply.
+const DEFAULT_THREAD_PAGE_SIZE: u16 = 20;
+
+/// Content a reply had before one of its edits.
+#[derive(Debug, Clone, SimpleObject, Deserialize, Serialize)]
+pub struct ReplyEdit {
+    pub content: String,
+    pub edited_by: String,
+    /// When this content was replaced.
+    pub replaced_at: DateTime<Utc>,
+}
 
-use super::user_profile::UserProfile;
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct ReplyEditHistory {
+    pub edits: Vec<ReplyEdit>,
+}
 
-#[derive(Debug, SimpleObject, Serialize, Deserialize)]
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct Reply {
-    id: String,
+    pub(crate) id: String,
     author: UserProfile,
     parent: String, // Identification of parent object
     content: String,
     created_at: DateTime<Utc>,
     last_modified: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
+    edited: bool,
+    /// When the author last changed the content.
+    edited_at: Option<DateTime<Utc>>,
+    /// Levels below the post or reply the thread was loaded from, starting
+    /// at 1 for its direct replies.
+    depth: i32,
+    /// Direct replies to this one, deleted ones included. When more than
+    /// `children` holds, the rest are loaded with `getReplies`, after the
+    /// cursor of the last loaded child.
+    reply_count: i32,
+    /// Loaded direct replies, oldest first.
+    children: Vec<Reply>,
 }
 
+#[ComplexObject]
 impl Reply {
+    /// Position of the reply among the replies to its parent. Pass it as
+    /// `after` to `getReplies` to load the replies that follow it.
+    async fn cursor(&self) -> String {
+        OpaqueCursor(self.page_cursor()).encode_cursor()
+    }
+}
+
+impl Reply {
+    /// Deleted replies keep their place in the thread but lose their author
+    /// and content.
     pub fn new(
         id: String,
         author: UserProfile,
@@ -25,7 +82,19 @@ impl Reply {
         content: String,
         created_at: DateTime<Utc>,
         last_modified: DateTime<Utc>,
+        deleted_at: Option<DateTime<Utc>>,
     ) -> Self {
+        let (author, content) = match deleted_at {
+            Some(_) => (
+                UserProfile {
+                    username: REMOVED_PLACEHOLDER.to_string(),
+                    ..UserProfile::default()
+                },
+                REMOVED_PLACEHOLDER.to_string(),
+            ),
+            None => (author, content),
+        };
+
         Self {
             id,
             author,
@@ -33,113 +102,255 @@ impl Reply {
             content,
             created_at,
             last_modified,
+            deleted_at,
+            edited: false,
+            edited_at: None,
+            depth: 1,
+            reply_count: 0,
+            children: vec![],
+        }
+    }
+
+    pub fn with_edited_at(mut self, edited_at: Option<DateTime<Utc>>) -> Self {
+        self.edited = edited_at.is_some();
+        self.edited_at = edited_at;
+        self
+    }
+
+    pub fn with_thread(mut self, depth: i32, reply_count: i32) -> Self {
+        self.depth = depth;
+        self.reply_count = reply_count;
+        self
+    }
+
+    /// Where the reply sits among the replies to its parent.
+    pub fn page_cursor(&self) -> Cursor {
+        Cursor {
+            key: SortKey::Time(self.created_at),
+            id: self.id.clone(),
         }
     }
 
-    /* This method needs to be changed to a recursive search through the database */
+    /// Whether only the tombstone of the reply is left.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// First page of the thread below a post or reply, nested as far as
+    /// `REPLY_THREAD_MAX_DEPTH` allows.
     pub async fn get_all_recursively(
         db: &DbController,
         parent_id: String,
     ) -> Result<Vec<Self>, String> {
-        let Ok(_replies) = sqlx::query(
-            r#"
-            SELECT
-                reply.id AS id,
-                profile.id AS author_id,
-                profile.username AS author_username,
-                profile.avatar AS author_avatar,
-                reply.content AS content,
-                reply.created_at AS created_at,
-                reply.last_modified AS last_modified
-            FROM replies AS reply
-            JOIN user_profiles AS profile ON profile.id = reply.author
-            WHERE parent = ?
-        "#,
-        )
-        .bind(&parent_id)
-        .map(|reply: MySqlRow| {
-            Reply::new(
-                reply.get("id"),
-                UserProfile::new(
-                    reply.get("author_id"),
-                    reply.get("author_username"),
-                    reply.get("author_avatar"),
-                    vec![],
-                ),
-                parent_id.clone(),
-                reply.get("content"),
-                reply.get("created_at"),
-                reply.get("last_modified"),
-            )
+        Self::get_thread(db, parent_id, Page::first(thread_page_size())).await
+    }
+
+    /// Page of the direct replies to a post or reply, oldest first, each with
+    /// its own thread. At most `REPLY_THREAD_PAGE_SIZE` replies are nested
+    /// under any one of them; `reply_count` tells where there are more to
+    /// load.
+    pub async fn get_thread(
+        db: &DbController,
+        parent_id: String,
+        page: Page,
+    ) -> Result<Vec<Self>, String> {
+        let limit = usize::from(page.limit);
+        let page = ThreadPage {
+            page,
+            max_depth: env_or("REPLY_THREAD_MAX_DEPTH", DEFAULT_THREAD_MAX_DEPTH).max(1),
+            width: thread_page_size(),
+        };
+
+        let Ok(replies) = db.replies.get_thread(&parent_id, page).await else {
+            eprintln!("DATABASE ERROR: Error retrieving reply thread in Reply GetThread");
+            return Err("Server error. Please try again.".to_string());
+        };
+
+        let mut by_parent: HashMap<String, Vec<Self>> = HashMap::new();
+        for reply in replies {
+            by_parent
+                .entry(reply.parent.clone())
+                .or_default()
+                .push(reply);
+        }
+        Ok(nest(&parent_id, &mut by_parent, limit))
+    }
+
+    /// Page of the thread below a post or reply. Threads are hidden along
+    /// with their post when it is deleted or the viewer may not read it.
+    pub async fn get_replies(
+        db: &DbController,
+        parent_id: String,
+        viewer: Option<String>,
+        args: PageArgs,
+    ) -> Result<ReplyConnection, String> {
+        ExpressionPost::of_thread(db, &parent_id, viewer.as_deref()).await?;
+        pagination::connection(args, Self::page_cursor, |page| {
+            Self::get_thread(db, parent_id, page)
+        })
+        .await
+    }
+
+    /// Replaces the content of the reply. Only its author may edit it, and
+    /// only while they can still read its thread; the earlier version is kept
+    /// for moderators to review.
+    pub async fn update(
+        db: &DbController,
+        request: UpdateReplyRequest,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let request = &request;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(author) = db.replies.author_of(&request.reply_id).await else {
+                eprintln!("DATABASE ERROR: Error getting author in Reply Update");
+                return Err("Server error. Please try again.".to_string());
+            };
+
+            // Check to make sure person updating reply is author
+            let Some(author) = author else {
+                return Err("Reply does not exist.".to_string());
+            };
+            if author != *logged_in_user {
+                return Err("User making request and reply author do not match.".to_string());
+            }
+            // Replies under deleted posts or circles the author left are out
+            // of reach, as they are for new replies
+            ExpressionPost::of_thread(&db, &request.reply_id, Some(logged_in_user)).await?;
+
+            if db
+                .replies
+                .update_content(
+                    &request.reply_id,
+                    &request.content,
+                    logged_in_user,
+                    db::now(),
+                )
+                .await
+                .is_err()
+            {
+                eprintln!("DATABASE ERROR: Error updating reply in Reply Update");
+                return Err("Server error. Please try again.".to_string());
+            };
+            db.invalidate(CommunityEvent::ReplyChanged);
+
+            let Ok(Some(reply)) = db.replies.get_by_id(&request.reply_id).await else {
+                eprintln!("DATABASE ERROR: Error retrieving updated reply in Reply Update");
+                return Err("Server error. Please try again.".to_string());
+            };
+            Ok(reply)
         })
-        .fetch_all(&db.community_pool)
         .await
-        else {
-            eprintln!(
-                "DATABASE ERROR: Error retrieving recursive replies in Reply GetAllRecursively"
-            );
+    }
+
+    /// Earlier versions of the reply, oldest first. Only moderators may
+    /// review them.
+    pub async fn get_edits(
+        db: &DbController,
+        reply_id: String,
+        logged_in_user: String,
+    ) -> Result<Vec<ReplyEdit>, String> {
+        if !UserProfile::is_moderator(db, &logged_in_user).await {
+            return Err("Only moderators can review reply edits.".to_string());
+        }
+
+        let Ok(edits) = db.replies.edits(&reply_id).await else {
+            eprintln!("DATABASE ERROR: Error retrieving reply edits in Reply GetEdits");
             return Err("Server error. Please try again.".to_string());
         };
-        todo!()
+        Ok(edits)
     }
 
+    /// Tombstones the reply. Its author and moderators may delete it.
     pub async fn delete(
         db: &DbController,
         reply_id: String,
         logged_in_user: String,
     ) -> Result<bool, String> {
-        let Ok(author) = sqlx::query("SELECT author FROM replies WHERE id = ?")
-            .bind(&reply_id)
-            .fetch_optional(&db.community_pool)
-            .await
-        else {
-            eprintln!("DATABASE ERROR: Error getting author in Reply Delete");
-            return Err("Server error. Please try again.".to_string());
-        };
+        let reply_id = &reply_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(author) = db.replies.author_of(reply_id).await else {
+                eprintln!("DATABASE ERROR: Error getting author in Reply Delete");
+                return Err("Server error. Please try again.".to_string());
+            };
 
-        // Check to make sure person deleting post is author
-        if author.is_none() {
-            return Err("Reply does not exist.".to_string());
-        } else {
-            let author: String = author.unwrap().get("author");
-            if author != logged_in_user {
+            // Check to make sure person deleting reply is author or a moderator
+            let Some(author) = author else {
+                return Err("Reply does not exist.".to_string());
+            };
+            if author != *logged_in_user && !UserProfile::is_moderator(&db, logged_in_user).await {
                 return Err("User making request and reply author do not match.".to_string());
             }
-        }
 
-        if sqlx::query("DELETE FROM replies WHERE id = ?")
-            .bind(reply_id)
-            .execute(&db.community_pool)
-            .await
-            .is_err()
-        {
-            eprintln!("DATABASE ERROR: Error deleting replies in Reply Delete");
-            return Err("Server error. Please try again.".to_string());
-        };
-        Ok(true)
+            if db
+                .replies
+                .soft_delete(reply_id, logged_in_user, db::now())
+                .await
+                .is_err()
+            {
+                eprintln!("DATABASE ERROR: Error deleting reply in Reply Delete");
+                return Err("Server error. Please try again.".to_string());
+            };
+            db.invalidate(CommunityEvent::ReplyChanged);
+            Ok(true)
+        })
+        .await
     }
 
-    pub async fn delete_all_from_post(db: &DbController, post_id: String) -> Result<(), String> {
-        let Ok(mut tx) = db.community_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction in Reply DeleteAllFromPost.");
-            return Err("Server error. Please try again".to_string());
-        };
+    /// Brings a deleted reply back. Authors may undo their own deletions;
+    /// moderators may undo any.
+    pub async fn restore(
+        db: &DbController,
+        reply_id: String,
+        logged_in_user: String,
+    ) -> Result<bool, String> {
+        let reply_id = &reply_id;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            let Ok(deletion) = db.replies.deletion(reply_id).await else {
+                eprintln!("DATABASE ERROR: Error getting deletion in Reply Restore");
+                return Err("Server error. Please try again.".to_string());
+            };
 
-        if sqlx::query("DELETE FROM replies WHERE parent = ?")
-            .bind(post_id)
-            .execute(&mut *tx)
-            .await
-            .is_err()
-        {
-            eprintln!(
-                "DATABASE ERROR: Error deleting all replies from post in Reply DeleteAllFromPost"
-            );
-            return Err("Server error. Please try again.".to_string());
-        };
+            let Some(deletion) = deletion else {
+                return Err("Reply does not exist or is not deleted.".to_string());
+            };
+            if !deletion.may_restore(logged_in_user)
+                && !UserProfile::is_moderator(&db, logged_in_user).await
+            {
+                return Err("Only the author or a moderator can restore this reply.".to_string());
+            }
+
+            if db.replies.restore(reply_id).await.is_err() {
+                eprintln!("DATABASE ERROR: Error restoring reply in Reply Restore");
+                return Err("Server error. Please try again.".to_string());
+            };
+            db.invalidate(CommunityEvent::ReplyChanged);
+            Ok(true)
+        })
+        .await
+    }
+}
+
+/// Number of replies nested under any one post or reply.
+fn thread_page_size() -> u16 {
+    env_or("REPLY_THREAD_PAGE_SIZE", DEFAULT_THREAD_PAGE_SIZE).max(1)
+}
 
-        let _ = tx.commit().await;
-        Ok(())
+/// Takes the replies to `parent` out of `by_parent`, oldest first, with
+/// their own replies nested below them. The store already cut every level
+/// down to its page size.
+fn nest(parent: &str, by_parent: &mut HashMap<String, Vec<Reply>>, limit: usize) -> Vec<Reply> {
+    let Some(mut replies) = by_parent.remove(parent) else {
+        return vec![];
+    };
+    replies.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    replies.truncate(limit);
+    for reply in &mut replies {
+        reply.children = nest(&reply.id, by_parent, usize::MAX);
     }
+    replies
 }
 
 /********** REQUEST OBJECT **********/
@@ -150,3 +361,130 @@ pub struct NewReplyRequest {
     pub content: String,
     pub parent: String,
 }
+
+/****** ADD VALIDATION CHECKS ******/
+#[derive(Debug, InputObject)]
+pub struct UpdateReplyRequest {
+    pub reply_id: String,
+    pub content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::models::{
+        expression_post::UpdateLikesRequest,
+        fixtures::{backend_tests, post, profile, reply},
+    };
+
+    /// Post by a new author the thread hangs off. Returns the author and the
+    /// post id.
+    async fn thread_start(db: &DbController) -> (String, String) {
+        profile(db, "author").await;
+        let post = post(db, "author", None).await;
+        ("author".to_string(), post.id)
+    }
+
+    /// Contents of the replies, sorted. Replies made within the same second
+    /// are ordered by id, which is not the order they were made in.
+    fn contents(replies: &[Reply]) -> Vec<&str> {
+        let mut contents: Vec<&str> = replies.iter().map(|reply| reply.content.as_str()).collect();
+        contents.sort();
+        contents
+    }
+
+    fn find<'a>(replies: &'a [Reply], content: &str) -> &'a Reply {
+        replies
+            .iter()
+            .find(|reply| reply.content == content)
+            .unwrap()
+    }
+
+    async fn thread_nests_replies_under_their_parents(db: DbController) {
+        let (author, post_id) = thread_start(&db).await;
+
+        let first = reply(&db, &author, &post_id, "first").await;
+        reply(&db, &author, &first.id, "first.a").await;
+        let nested = reply(&db, &author, &first.id, "first.b").await;
+        reply(&db, &author, &nested.id, "first.b.i").await;
+        reply(&db, &author, &post_id, "second").await;
+
+        let thread = Reply::get_all_recursively(&db, post_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(contents(&thread), ["first", "second"]);
+        let first = find(&thread, "first");
+        assert_eq!(first.depth, 1);
+        assert_eq!(first.reply_count, 2);
+        assert_eq!(contents(&first.children), ["first.a", "first.b"]);
+        let nested = find(&first.children, "first.b");
+        assert_eq!(nested.depth, 2);
+        assert_eq!(contents(&nested.children), ["first.b.i"]);
+        assert_eq!(nested.children[0].depth, 3);
+        assert!(find(&thread, "second").children.is_empty());
+
+        // Threads can also be loaded from a reply, a page at a time
+        let page = Reply::get_thread(&db, first.id.clone(), Page::first(1))
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].depth, 1);
+    }
+
+    async fn replies_take_no_likes_and_close_with_their_post(db: DbController) {
+        let (author, post_id) = thread_start(&db).await;
+        let first = reply(&db, &author, &post_id, "first").await;
+
+        let like = UpdateLikesRequest {
+            post_id: first.id.clone(),
+            update_value: 1,
+        };
+        assert!(ExpressionPost::update_likes(&db, like, author.clone())
+            .await
+            .is_err());
+
+        ExpressionPost::delete(&db, post_id.clone(), author.clone())
+            .await
+            .unwrap();
+        let request = NewReplyRequest {
+            content: "late".to_string(),
+            parent: first.id.clone(),
+        };
+        assert!(ExpressionPost::add_reply(&db, author.clone(), request)
+            .await
+            .is_err());
+        let args = PageArgs::default();
+        assert!(Reply::get_replies(&db, post_id.clone(), Some(author), args)
+            .await
+            .is_err());
+    }
+
+    async fn replies_cannot_be_edited_under_deleted_posts(db: DbController) {
+        let (author, post_id) = thread_start(&db).await;
+        let first = reply(&db, &author, &post_id, "first").await;
+        let edit = |content: &str| UpdateReplyRequest {
+            reply_id: first.id.clone(),
+            content: content.to_string(),
+        };
+
+        let edited = Reply::update(&db, edit("first, edited"), author.clone())
+            .await
+            .unwrap();
+        assert_eq!(edited.content, "first, edited");
+
+        ExpressionPost::delete(&db, post_id, author.clone())
+            .await
+            .unwrap();
+        assert!(Reply::update(&db, edit("too late"), author.clone())
+            .await
+            .is_err());
+        let stored = db.replies.get_by_id(&first.id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "first, edited");
+    }
+
+    backend_tests!(
+        thread_nests_replies_under_their_parents,
+        replies_take_no_likes_and_close_with_their_post,
+        replies_cannot_be_edited_under_deleted_posts,
+    );
+}
@@ -0,0 +1,273 @@
+use async_graphql::{InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{DbController, PostSearch};
+
+use super::expression_post::ExpressionPost;
+
+/// Most words of a query that are searched for.
+const MAX_SEARCH_TERMS: usize = 16;
+/// Words shown before the first match in a snippet.
+const SNIPPET_LEAD_WORDS: usize = 8;
+/// Words in a snippet.
+const SNIPPET_WORDS: usize = 30;
+
+/// Narrows a search down. Unset filters match every post.
+#[derive(Debug, Default, InputObject)]
+pub struct SearchFilters {
+    pub author_id: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// `text` or `image`.
+    pub content_type: Option<String>,
+}
+
+/// Part of a snippet that matched the query, as character offsets into it.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct SearchHighlight {
+    pub start: i32,
+    pub end: i32,
+}
+
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct PostSearchResult {
+    pub post: ExpressionPost,
+    /// Relevance to the query. Only comparable between results of one search.
+    pub score: f64,
+    /// Passage around the first match, from the post or from the reply that
+    /// matched.
+    pub snippet: String,
+    pub highlights: Vec<SearchHighlight>,
+    /// Whether the snippet comes from a reply rather than the post itself.
+    pub in_reply: bool,
+}
+
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct PostSearchResults {
+    pub results: Vec<PostSearchResult>,
+}
+
+impl PostSearchResult {
+    /// Live posts containing every word of `query` in their own text or in a
+    /// reply of their thread, most relevant first.
+    pub async fn search(
+        db: &DbController,
+        query: String,
+        filters: SearchFilters,
+        limit: u16,
+        offset: u32,
+    ) -> Result<Vec<Self>, String> {
+        let terms = search_terms(&query);
+        if terms.is_empty() {
+            return Err("Enter a word to search for.".to_string());
+        }
+
+        let search = PostSearch {
+            terms,
+            author: filters.author_id,
+            created_after: filters.created_after,
+            created_before: filters.created_before,
+            content_type: filters.content_type,
+            limit,
+            offset,
+        };
+        let Ok(hits) = db.posts.search(&search).await else {
+            eprintln!(
+                "DATABASE ERROR: Error searching expression posts in PostSearchResult Search"
+            );
+            return Err("Server error. Please try again.".to_string());
+        };
+
+        Ok(hits
+            .into_iter()
+            .map(|hit| {
+                let in_reply = hit.reply.is_some();
+                let (snippet, highlights) = match &hit.reply {
+                    Some(reply) => snippet(reply, &search.terms),
+                    None => {
+                        let text = hit
+                            .post
+                            .searchable_text()
+                            .find(|text| {
+                                text.split_whitespace()
+                                    .any(|word| matches_term(word, &search.terms))
+                            })
+                            .or_else(|| hit.post.searchable_text().next())
+                            .unwrap_or_default();
+                        snippet(text, &search.terms)
+                    }
+                };
+
+                PostSearchResult {
+                    post: hit.post,
+                    score: hit.score,
+                    snippet,
+                    highlights,
+                    in_reply,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Distinct words of the query, lowercased and without punctuation.
+fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = vec![];
+    for word in query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let word = word.to_lowercase();
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms.truncate(MAX_SEARCH_TERMS);
+    terms
+}
+
+/// Whether a word of a text matches one of the terms, for highlighting.
+/// SQLite and Postgres match word stems, so "runs" finds "running", while
+/// MySQL matches words starting with a term and the in-memory store any
+/// text containing it. Words starting with a term, or that only differ from
+/// it in their last couple of letters, cover what any of them return.
+fn matches_term(word: &str, terms: &[String]) -> bool {
+    let word: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    !word.is_empty()
+        && terms.iter().any(|term| {
+            let term: Vec<char> = term.chars().collect();
+            let shared = word.iter().zip(&term).take_while(|(a, b)| a == b).count();
+            word.starts_with(&term) || (shared >= 3 && shared + 2 >= word.len().min(term.len()))
+        })
+}
+
+/// Up to `SNIPPET_WORDS` words of the text from just before the first match,
+/// with the matching words marked.
+fn snippet(text: &str, terms: &[String]) -> (String, Vec<SearchHighlight>) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let first = words
+        .iter()
+        .position(|word| matches_term(word, terms))
+        .unwrap_or_default();
+    let start = first.saturating_sub(SNIPPET_LEAD_WORDS);
+    let end = (start + SNIPPET_WORDS).min(words.len());
+
+    let mut snippet = String::new();
+    let mut highlights = vec![];
+    if start > 0 {
+        snippet.push_str("… ");
+    }
+    for (index, word) in words[start..end].iter().enumerate() {
+        if index > 0 {
+            snippet.push(' ');
+        }
+        if matches_term(word, terms) {
+            // Punctuation around the word is left unmarked
+            let lead = word.chars().take_while(|c| !c.is_alphanumeric()).count();
+            let len = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .chars()
+                .count();
+            let at = (snippet.chars().count() + lead) as i32;
+            highlights.push(SearchHighlight {
+                start: at,
+                end: at + len as i32,
+            });
+        }
+        snippet.push_str(word);
+    }
+    if end < words.len() {
+        snippet.push_str(" …");
+    }
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::models::fixtures::{self, backend_tests, post, profile, reply};
+
+    async fn search(db: &DbController, query: &str) -> Vec<PostSearchResult> {
+        PostSearchResult::search(db, query.to_string(), SearchFilters::default(), 10, 0)
+            .await
+            .unwrap()
+    }
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn search_terms_are_distinct_lowercase_words() {
+        assert_eq!(
+            search_terms("Morning, morning... over the BAY!"),
+            terms(&["morning", "over", "the", "bay"])
+        );
+        assert_eq!(search_terms("café au-lait"), terms(&["café", "au", "lait"]));
+        assert!(search_terms(" -- !? ").is_empty());
+
+        let many: Vec<String> = (0..20).map(|n| format!("word{n}")).collect();
+        assert_eq!(search_terms(&many.join(" ")).len(), MAX_SEARCH_TERMS);
+    }
+
+    #[test]
+    fn snippet_marks_matches_by_character_offset() {
+        let (text, highlights) = snippet("Morning over the bay.", &terms(&["bay"]));
+        assert_eq!(text, "Morning over the bay.");
+        assert_eq!(highlights.len(), 1);
+        let (start, end) = (highlights[0].start as usize, highlights[0].end as usize);
+        // Offsets count characters, and punctuation around a word is left out
+        assert_eq!((start, end), (17, 20));
+
+        let (text, highlights) = snippet("Café «running» late", &terms(&["run"]));
+        let marked: String = text
+            .chars()
+            .skip(highlights[0].start as usize)
+            .take((highlights[0].end - highlights[0].start) as usize)
+            .collect();
+        assert_eq!(marked, "running");
+    }
+
+    #[test]
+    fn snippet_starts_shortly_before_the_first_match() {
+        let words: Vec<String> = (0..50).map(|n| format!("w{n}")).collect();
+        let (text, highlights) = snippet(&words.join(" "), &terms(&["w20"]));
+
+        assert!(text.starts_with("… w12 "));
+        assert!(text.ends_with(" w41 …"));
+        let start = highlights[0].start as usize;
+        let marked: String = text.chars().skip(start).take(3).collect();
+        assert_eq!(marked, "w20");
+    }
+
+    async fn search_finds_live_posts_by_their_text_and_replies(db: DbController) {
+        profile(&db, "ada").await;
+        let saved = post(&db, "ada", None).await;
+        reply(&db, "ada", &saved.id, "Gulls were circling").await;
+
+        let results = search(&db, "Bay").await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].post.id, saved.id);
+        assert!(!results[0].in_reply);
+        assert_eq!(results[0].snippet, fixtures::CONTENT);
+
+        let results = search(&db, "gulls circling").await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].in_reply);
+        assert_eq!(results[0].snippet, "Gulls were circling");
+
+        assert!(search(&db, "bay harbour").await.is_empty());
+
+        ExpressionPost::delete(&db, saved.id, "ada".to_string())
+            .await
+            .unwrap();
+        assert!(search(&db, "bay").await.is_empty());
+    }
+
+    backend_tests!(search_finds_live_posts_by_their_text_and_replies);
+}
@@ -0,0 +1,213 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::env_or,
+    db::{self, DbController},
+};
+
+use super::user_profile::UserProfile;
+
+/// Default number of topics a post can be tagged with.
+const DEFAULT_MAX_TAGS: usize = 5;
+/// Longest topic name, in characters.
+const MAX_TOPIC_LENGTH: usize = 32;
+/// Topics returned by topic lists when no limit is given.
+const DEFAULT_TOPIC_LIMIT: u16 = 20;
+/// Largest topic list a client may ask for.
+const MAX_TOPIC_LIMIT: u16 = 100;
+
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Topic {
+    pub name: String,
+    /// Number of live posts tagged with the topic.
+    pub post_count: i64,
+}
+
+#[derive(Debug, SimpleObject, Deserialize, Serialize)]
+pub struct TopicList {
+    pub topics: Vec<Topic>,
+}
+
+impl Topic {
+    /// Topics starting with `prefix`, most used first. Without a prefix this
+    /// lists the most used topics.
+    pub async fn get_topics(
+        db: &DbController,
+        prefix: Option<String>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Self>, String> {
+        let prefix = prefix.as_deref().map(normalize).unwrap_or_default();
+        let limit = match limit {
+            Some(0) | None => DEFAULT_TOPIC_LIMIT,
+            Some(limit) => limit.min(MAX_TOPIC_LIMIT),
+        };
+
+        let Ok(topics) = db.topics.list(&prefix, limit).await else {
+            eprintln!("DATABASE ERROR: Error retrieving topics in Topic GetTopics");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(topics)
+    }
+
+    /// Adds a topic to the list posts can be tagged with. Only moderators may
+    /// extend the list.
+    pub async fn add(
+        db: &DbController,
+        name: String,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let name = &name;
+        let logged_in_user = &logged_in_user;
+        db.unit_of_work(|db| async move {
+            if !UserProfile::is_moderator(&db, logged_in_user).await {
+                return Err("Only moderators can add topics.".to_string());
+            }
+
+            let Some(topic) = valid_name(name) else {
+                return Err(format!("`{name}` is not a valid topic."));
+            };
+            if Self::unknown(&db, std::slice::from_ref(&topic))
+                .await?
+                .is_empty()
+            {
+                return Err("Topic already exists.".to_string());
+            }
+            if db
+                .topics
+                .insert(&topic, logged_in_user, db::now())
+                .await
+                .is_err()
+            {
+                eprintln!("DATABASE ERROR: Error adding topic in Topic Add");
+                return Err("Server error. Please try again.".to_string());
+            }
+
+            Ok(Topic {
+                name: topic,
+                post_count: 0,
+            })
+        })
+        .await
+    }
+
+    pub async fn follow(db: &DbController, name: String, user_id: String) -> Result<Self, String> {
+        let Some(topic) = valid_name(&name) else {
+            return Err(format!("`{name}` is not a valid topic."));
+        };
+        if !Self::unknown(db, std::slice::from_ref(&topic))
+            .await?
+            .is_empty()
+        {
+            return Err("Topic does not exist.".to_string());
+        }
+
+        if db.topics.follow(&user_id, &topic, db::now()).await.is_err() {
+            eprintln!("DATABASE ERROR: Error following topic in Topic Follow");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(Topic {
+            name: topic,
+            post_count: 0,
+        })
+    }
+
+    pub async fn unfollow(
+        db: &DbController,
+        name: String,
+        user_id: String,
+    ) -> Result<Self, String> {
+        let Some(topic) = valid_name(&name) else {
+            return Err(format!("`{name}` is not a valid topic."));
+        };
+
+        if db.topics.unfollow(&user_id, &topic).await.is_err() {
+            eprintln!("DATABASE ERROR: Error unfollowing topic in Topic Unfollow");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(Topic {
+            name: topic,
+            post_count: 0,
+        })
+    }
+
+    pub async fn get_followed(db: &DbController, user_id: String) -> Result<Vec<Self>, String> {
+        let Ok(topics) = db.topics.followed(&user_id).await else {
+            eprintln!("DATABASE ERROR: Error retrieving followed topics in Topic GetFollowed");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(topics)
+    }
+
+    /// Normalized, distinct tags of a new post. Every tag must be in the
+    /// topic list and a post takes at most `POST_MAX_TAGS` of them.
+    pub async fn validate_tags(db: &DbController, tags: &[String]) -> Result<Vec<String>, String> {
+        let names = Self::normalize_tags(tags)?;
+        if let Some(unknown) = Self::unknown(db, &names).await?.first() {
+            return Err(format!(
+                "`{unknown}` is not a topic. Pick one from the topic list."
+            ));
+        }
+        Ok(names)
+    }
+
+    /// Normalized, distinct tags, checked for everything but being in the
+    /// topic list.
+    pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+        let mut names: Vec<String> = vec![];
+        for tag in tags {
+            let Some(name) = valid_name(tag) else {
+                return Err(format!("`{tag}` is not a valid topic."));
+            };
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let max_tags = env_or("POST_MAX_TAGS", DEFAULT_MAX_TAGS);
+        if names.len() > max_tags {
+            return Err(format!("Posts can have at most {max_tags} topics."));
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Those of `names` that are not in the topic list.
+    pub async fn unknown(db: &DbController, names: &[String]) -> Result<Vec<String>, String> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let Ok(existing) = db.topics.existing(names).await else {
+            eprintln!("DATABASE ERROR: Error checking topics in Topic Unknown");
+            return Err("Server error. Please try again.".to_string());
+        };
+        Ok(names
+            .iter()
+            .filter(|name| !existing.contains(name))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Canonical form of a topic name: lowercase letters, digits and single
+/// dashes, so "Self Care" and "self_care" both become "self-care".
+pub fn normalize(name: &str) -> String {
+    let mut normalized = String::new();
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            normalized.push(c);
+        } else if matches!(c, ' ' | '-' | '_')
+            && !normalized.is_empty()
+            && !normalized.ends_with('-')
+        {
+            normalized.push('-');
+        }
+    }
+    normalized.trim_end_matches('-').to_string()
+}
+
+/// Normalized name, unless nothing of it is left or it is too long.
+fn valid_name(name: &str) -> Option<String> {
+    let name = normalize(name);
+    (!name.is_empty() && name.len() <= MAX_TOPIC_LENGTH).then_some(name)
+}
@@ -1,10 +1,21 @@
-use async_graphql::{InputObject, SimpleObject};
-use serde::{Deserialize, Serialize};
-use sqlx::{types::Json, FromRow, Row};
-
-use crate::db::DbController;
+use std::collections::HashMap;
 
-#[derive(Debug, FromRow, SimpleObject, InputObject, Default, Serialize, Deserialize)]
+use async_graphql::{dataloader::DataLoader, ComplexObject, Context, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{
+    community::{
+        loaders::{CommunityLoader, FollowCounts},
+        pagination::{self, FollowConnection, PageArgs},
+        CommunityEvent,
+    },
+    db::{self, Cursor, DbController, Page, SortKey},
+};
+
+#[derive(Debug, Clone, FromRow, SimpleObject, InputObject, Default, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct UserProfile {
     pub id: String,
     pub username: String,
@@ -12,7 +23,45 @@ pub struct UserProfile {
     pub likes: Vec<String>,
 }
 
+/// A user in a list of followers or followed users.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Follow {
+    pub user: UserProfile,
+    pub followed_at: DateTime<Utc>,
+}
+
+impl Follow {
+    /// Where the follow sits in its list, latest first.
+    pub fn page_cursor(&self) -> Cursor {
+        Cursor {
+            key: SortKey::Time(self.followed_at),
+            id: self.user.id.clone(),
+        }
+    }
+}
+
+#[ComplexObject]
 impl UserProfile {
+    /// Number of users following this one.
+    async fn follower_count(&self, ctx: &Context<'_>) -> async_graphql::Result<i64> {
+        Ok(self.load_follow_counts(ctx).await?.0)
+    }
+
+    /// Number of users this one follows.
+    async fn following_count(&self, ctx: &Context<'_>) -> async_graphql::Result<i64> {
+        Ok(self.load_follow_counts(ctx).await?.1)
+    }
+}
+
+impl UserProfile {
+    /// Follow counts of the profile, loaded along with those of every other
+    /// profile the response holds.
+    async fn load_follow_counts(&self, ctx: &Context<'_>) -> async_graphql::Result<(i64, i64)> {
+        let loader = ctx.data::<DataLoader<CommunityLoader>>()?;
+        let counts = loader.load_one(FollowCounts(self.id.clone())).await?;
+        Ok(counts.unwrap_or_default())
+    }
+
     pub fn new(id: String, username: String, avatar: String, likes: Vec<String>) -> Self {
         Self {
             id,
@@ -23,114 +72,152 @@ impl UserProfile {
     }
 
     pub async fn does_profile_exist(db: &DbController, id: &str, username: &str) -> bool {
-        sqlx::query("SELECT * FROM user_profiles WHERE id = ? OR username = ?")
-            .bind(id)
-            .bind(username)
-            .fetch_one(&db.community_pool)
-            .await
-            .is_ok()
+        db.profiles.exists(id, username).await.unwrap_or(false)
     }
 
-    pub async fn register(db: &DbController, id: String, username: String) -> Result<Self, String> {
-        if Self::does_profile_exist(db, &id, &username).await {
-            return Err("Profile already exists.".to_string());
-        }
-
-        let Ok(mut tx) = db.community_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction in UserProfile Register.");
-            return Err("Server error. Please try again".to_string());
-        };
+    pub async fn is_moderator(db: &DbController, id: &str) -> bool {
+        db.profiles.is_moderator(id).await.unwrap_or(false)
+    }
 
-        let avatar = format!("https://api.multiavatar.com/${id}.svg");
-
-        if let Err(_) = sqlx::query(
-            r#"
-            INSERT INTO user_profiles
-                (
-                    id, 
-                    username, 
-                    avatar
-                ) 
-                VALUES (?, ?, ?)
-            "#,
-        )
-        .bind(&id)
-        .bind(&username)
-        .bind(&avatar)
-        .execute(&mut *tx)
+    pub async fn register(db: &DbController, id: String, username: String) -> Result<Self, String> {
+        let id = &id;
+        let username = &username;
+        db.unit_of_work(|db| async move {
+            if Self::does_profile_exist(&db, id, username).await {
+                return Err("Profile already exists.".to_string());
+            }
+
+            let avatar = format!("https://api.multiavatar.com/${id}.svg");
+
+            if db.profiles.insert(id, username, &avatar).await.is_err() {
+                eprintln!("DATABASE_ERROR: Error inserting profile in UserProfile Register.");
+                return Err("Server error. Please try again".to_string());
+            };
+            db.invalidate(CommunityEvent::ProfileChanged { id: id.clone() });
+
+            Ok(Self::new(id.clone(), username.clone(), avatar, vec![]))
+        })
         .await
-        {
-            eprintln!("DATABASE_ERROR: Error inserting profile in UserProfile Register.");
-            return Err("Server error. Please try again".to_string());
-        };
-
-        let _ = tx.commit().await;
-        Ok(Self::new(id, username, avatar, vec![]))
     }
 
+    /// Served from the cache when possible. Missing profiles are not cached.
     pub async fn get_by_id(db: &DbController, id: String) -> Result<Self, String> {
-        let Ok(profile) = sqlx::query(
-            r#"
-            SELECT 
-                id, 
-                username, 
-                avatar,
-                (
-                    SELECT JSON_ARRAYAGG(parent_id) FROM likes WHERE author = id
-                ) AS likes
-            FROM user_profiles 
-            WHERE id = ? 
-        "#,
-        )
-        .bind(id)
-        .fetch_one(&db.community_pool)
-        .await
-        else {
-            return Err("User is either not logged in or does not exist".to_string());
-        };
-
-        let likes: Option<Json<Vec<String>>> = profile.get("likes");
-
-        Ok(Self::new(
-            profile.get("id"),
-            profile.get("username"),
-            profile.get("avatar"),
-            if likes.is_some() {
-                likes.unwrap().0
-            } else {
-                vec![]
-            },
-        ))
+        db.cache
+            .profile(&id, || async {
+                let Ok(Some(profile)) = db.profiles.get_by_id(&id).await else {
+                    return Err("User is either not logged in or does not exist".to_string());
+                };
+
+                Ok(profile)
+            })
+            .await
     }
 
     pub async fn delete(db: &DbController, id: String) -> Result<bool, String> {
+        let id = &id;
+        db.unit_of_work(|db| async move {
+            if db.profiles.delete(id).await.is_err() {
+                eprintln!("DATABASE_ERROR: Error deleting profile in UserProfile Delete.");
+                return Err("Server error. Please try again.".to_string());
+            };
+            db.invalidate(CommunityEvent::ProfileChanged { id: id.clone() });
+
+            Ok(true)
+        })
+        .await
+    }
 
-        let Ok(mut tx) = db.community_pool.begin().await else {
-            eprintln!("DATABASE_ERROR: Error starting transaction in UserProfile Delete.");
-            return Err("Server error. Please try again".to_string());
+    pub async fn follow(
+        db: &DbController,
+        user_id: String,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        if user_id == logged_in_user {
+            return Err("You cannot follow yourself.".to_string());
+        }
+        let Ok(Some(profile)) = db.profiles.get_by_id(&user_id).await else {
+            return Err("User does not exist.".to_string());
         };
 
-        if sqlx::query("DELETE FROM user_profiles WHERE id = ?")
-            .bind(&id)
-            .execute(&mut *tx)
+        if db
+            .profiles
+            .follow(&logged_in_user, &user_id, db::now())
             .await
             .is_err()
         {
-            eprintln!("DATABASE_ERROR: Error deleting profile in UserProfile Delete.");
+            eprintln!("DATABASE_ERROR: Error following user in UserProfile Follow.");
             return Err("Server error. Please try again.".to_string());
+        }
+        Ok(profile)
+    }
+
+    pub async fn unfollow(
+        db: &DbController,
+        user_id: String,
+        logged_in_user: String,
+    ) -> Result<Self, String> {
+        let Ok(Some(profile)) = db.profiles.get_by_id(&user_id).await else {
+            return Err("User does not exist.".to_string());
         };
 
-        if sqlx::query("DELETE FROM likes WHERE author = ?")
-            .bind(id)
-            .execute(&mut *tx)
+        if db
+            .profiles
+            .unfollow(&logged_in_user, &user_id)
             .await
             .is_err()
         {
-            eprintln!("DATABASE_ERROR: Error deleting likes in UserProfile Delete.");
+            eprintln!("DATABASE_ERROR: Error unfollowing user in UserProfile Unfollow.");
+            return Err("Server error. Please try again.".to_string());
+        }
+        Ok(profile)
+    }
+
+    /// Number of followers and of followed users of each profile. Profiles
+    /// with no follows either way are left out.
+    pub async fn follow_counts(
+        db: &DbController,
+        ids: &[String],
+    ) -> Result<HashMap<String, (i64, i64)>, String> {
+        let Ok(counts) = db.profiles.follow_counts(ids).await else {
+            eprintln!("DATABASE_ERROR: Error counting follows in UserProfile FollowCounts.");
             return Err("Server error. Please try again.".to_string());
         };
+        Ok(counts)
+    }
 
-        let _ = tx.commit().await;
-        Ok(true)
+    /// Users following the profile, latest follow first.
+    pub async fn get_followers(
+        db: &DbController,
+        id: String,
+        args: PageArgs,
+    ) -> Result<FollowConnection, String> {
+        pagination::connection(args, Follow::page_cursor, |page: Page| async move {
+            let Ok(followers) = db.profiles.followers(&id, &page).await else {
+                eprintln!(
+                    "DATABASE_ERROR: Error retrieving followers in UserProfile GetFollowers."
+                );
+                return Err("Server error. Please try again.".to_string());
+            };
+            Ok(followers)
+        })
+        .await
+    }
+
+    /// Users the profile follows, latest follow first.
+    pub async fn get_following(
+        db: &DbController,
+        id: String,
+        args: PageArgs,
+    ) -> Result<FollowConnection, String> {
+        pagination::connection(args, Follow::page_cursor, |page: Page| async move {
+            let Ok(following) = db.profiles.following(&id, &page).await else {
+                eprintln!(
+                    "DATABASE_ERROR: Error retrieving followed users in UserProfile GetFollowing."
+                );
+                return Err("Server error. Please try again.".to_string());
+            };
+            Ok(following)
+        })
+        .await
     }
 }
@@ -1,23 +1,30 @@
 use std::sync::Arc;
 
 use async_graphql::*;
+use chrono::{DateTime, Utc};
 use tower_cookies::Cookies;
 
 use crate::{
     auth::AccessToken,
-    community::models::{expression_post::NewExpressionPost, reply::Reply},
+    community::{
+        models::{expression_post::NewExpressionPost, reply::Reply, topic::Topic},
+        Bookmark, BookmarkKind, Circle, CircleMember, CircleRole, PostDraft, UserProfile,
+    },
     db::DbController,
     GatewayResponse,
 };
 
 use super::models::{
+    circle::{NewCircleRequest, UpdateCircleRequest},
+    draft::{NewDraftRequest, UpdateDraftRequest},
     expression_post::{ExpressionPost, UpdateContentRequest, UpdateLikesRequest},
-    reply::NewReplyRequest,
+    reply::{NewReplyRequest, UpdateReplyRequest},
 };
 
+#[derive(Default)]
 pub struct Mutation;
 
-#[Object]
+#[Object(name = "CommunityMutation")]
 impl Mutation {
     pub async fn create_new_expression_post(
         &self,
@@ -296,9 +303,1333 @@ impl Mutation {
         };
 
         if let Err(err) = ExpressionPost::delete(db, post_id, logged_in_user).await {
-            return Ok(GatewayResponse::new(false, Some(err), None, 500));
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 204))
+        }
+    }
+
+    pub async fn restore_expression_post(
+        &self,
+        ctx: &Context<'_>,
+        post_id: String,
+    ) -> Result<GatewayResponse<ExpressionPost>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Restore Expression Post");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Restore Expression Post");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to restore expression post.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Restore Expression Post");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error restoring expression post. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = ExpressionPost::restore(db, post_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 200))
+        }
+    }
+
+    pub async fn update_reply(
+        &self,
+        ctx: &Context<'_>,
+        request: UpdateReplyRequest,
+    ) -> Result<GatewayResponse<Reply>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Update Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Update Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to update reply.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Update Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error updating reply. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Reply::update(db, request, logged_in_user).await {
+            Ok(reply) => Ok(GatewayResponse::new(true, None, Some(reply), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn delete_reply(
+        &self,
+        ctx: &Context<'_>,
+        reply_id: String,
+    ) -> Result<GatewayResponse<Reply>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Delete Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Delete Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to delete reply.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Delete Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error deleting reply. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = Reply::delete(db, reply_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 204))
+        }
+    }
+
+    pub async fn restore_reply(
+        &self,
+        ctx: &Context<'_>,
+        reply_id: String,
+    ) -> Result<GatewayResponse<Reply>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Restore Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Restore Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to restore reply.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Restore Reply");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error restoring reply. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = Reply::restore(db, reply_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 200))
+        }
+    }
+
+    /// Adds a topic to the list posts can be tagged with. Moderators only.
+    pub async fn add_topic(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+    ) -> Result<GatewayResponse<Topic>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Add Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Add Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to add a topic.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Add Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error adding topic. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Topic::add(db, name, logged_in_user).await {
+            Ok(topic) => Ok(GatewayResponse::new(true, None, Some(topic), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn follow_topic(
+        &self,
+        ctx: &Context<'_>,
+        topic: String,
+    ) -> Result<GatewayResponse<Topic>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Follow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Follow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to follow a topic.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Follow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error following topic. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Topic::follow(db, topic, logged_in_user).await {
+            Ok(topic) => Ok(GatewayResponse::new(true, None, Some(topic), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn unfollow_topic(
+        &self,
+        ctx: &Context<'_>,
+        topic: String,
+    ) -> Result<GatewayResponse<Topic>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Unfollow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Unfollow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to unfollow a topic.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Unfollow Topic");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error unfollowing topic. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Topic::unfollow(db, topic, logged_in_user).await {
+            Ok(topic) => Ok(GatewayResponse::new(true, None, Some(topic), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn follow_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+    ) -> Result<GatewayResponse<UserProfile>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Follow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Follow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to follow a user.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Follow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error following user. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match UserProfile::follow(db, user_id, logged_in_user).await {
+            Ok(profile) => Ok(GatewayResponse::new(true, None, Some(profile), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn unfollow_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+    ) -> Result<GatewayResponse<UserProfile>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Unfollow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Unfollow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to unfollow a user.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Unfollow User");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error unfollowing user. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match UserProfile::unfollow(db, user_id, logged_in_user).await {
+            Ok(profile) => Ok(GatewayResponse::new(true, None, Some(profile), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Creates a circle with the logged in user as its owner.
+    pub async fn create_circle(
+        &self,
+        ctx: &Context<'_>,
+        circle: NewCircleRequest,
+    ) -> Result<GatewayResponse<Circle>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Create Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Create Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to create a circle.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Create Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error creating circle. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::create(db, circle, logged_in_user).await {
+            Ok(circle) => Ok(GatewayResponse::new(true, None, Some(circle), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn update_circle(
+        &self,
+        ctx: &Context<'_>,
+        circle: UpdateCircleRequest,
+    ) -> Result<GatewayResponse<Circle>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Update Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Update Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to edit a circle.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Update Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error updating circle. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::update(db, circle, logged_in_user).await {
+            Ok(circle) => Ok(GatewayResponse::new(true, None, Some(circle), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Joins a public circle right away. Circles that take requests list the
+    /// user as pending until an owner or moderator approves them.
+    pub async fn join_circle(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+    ) -> Result<GatewayResponse<CircleMember>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Join Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Join Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to join a circle.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Join Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error joining circle. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::join(db, circle_id, logged_in_user).await {
+            Ok(member) => Ok(GatewayResponse::new(true, None, Some(member), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn leave_circle(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+    ) -> Result<GatewayResponse<CircleMember>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Leave Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Leave Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to leave a circle.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Leave Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error leaving circle. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = Circle::leave(db, circle_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 204))
+        }
+    }
+
+    pub async fn approve_circle_member(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        user_id: String,
+    ) -> Result<GatewayResponse<CircleMember>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Approve Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Approve Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to approve members.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Approve Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error approving member. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::approve_member(db, circle_id, user_id, logged_in_user).await {
+            Ok(member) => Ok(GatewayResponse::new(true, None, Some(member), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Removes a member from a circle or declines their request to join.
+    pub async fn remove_circle_member(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        user_id: String,
+    ) -> Result<GatewayResponse<CircleMember>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Remove Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Remove Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to remove members.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Remove Circle Member");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error removing member. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = Circle::remove_member(db, circle_id, user_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 204))
+        }
+    }
+
+    pub async fn set_circle_role(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        user_id: String,
+        role: CircleRole,
+    ) -> Result<GatewayResponse<CircleMember>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Set Circle Role");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Set Circle Role");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to change member roles.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Set Circle Role");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error changing member role. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::set_role(db, circle_id, user_id, role, logged_in_user).await {
+            Ok(member) => Ok(GatewayResponse::new(true, None, Some(member), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Saves a post or reply for the logged in user, filed under
+    /// `collection` when one is given.
+    pub async fn add_bookmark(
+        &self,
+        ctx: &Context<'_>,
+        target_id: String,
+        kind: BookmarkKind,
+        collection: Option<String>,
+    ) -> Result<GatewayResponse<Bookmark>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Add Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Add Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to bookmark.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Add Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error adding bookmark. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Bookmark::add(db, target_id, kind, collection, logged_in_user).await {
+            Ok(bookmark) => Ok(GatewayResponse::new(true, None, Some(bookmark), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn remove_bookmark(
+        &self,
+        ctx: &Context<'_>,
+        target_id: String,
+    ) -> Result<GatewayResponse<Bookmark>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Remove Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Remove Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to remove a bookmark.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Remove Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error removing bookmark. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = Bookmark::remove(db, target_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
+        } else {
+            Ok(GatewayResponse::new(true, None, None, 204))
+        }
+    }
+
+    /// Files a bookmark under another collection, or under none when no
+    /// `collection` is given.
+    pub async fn move_bookmark(
+        &self,
+        ctx: &Context<'_>,
+        target_id: String,
+        collection: Option<String>,
+    ) -> Result<GatewayResponse<Bookmark>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Move Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Move Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to move a bookmark.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Move Bookmark");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error moving bookmark. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Bookmark::move_to(db, target_id, collection, logged_in_user).await {
+            Ok(bookmark) => Ok(GatewayResponse::new(true, None, Some(bookmark), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Starts a draft. Nothing about it needs to be filled in yet.
+    pub async fn create_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft: NewDraftRequest,
+    ) -> Result<GatewayResponse<PostDraft>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Create Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Create Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to save a draft.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Create Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error saving draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match PostDraft::create(db, draft, logged_in_user).await {
+            Ok(draft) => Ok(GatewayResponse::new(true, None, Some(draft), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Saves the fields that changed, for clients that autosave.
+    pub async fn update_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft: UpdateDraftRequest,
+    ) -> Result<GatewayResponse<PostDraft>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Update Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Update Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to save a draft.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Update Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error saving draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match PostDraft::update(db, draft, logged_in_user).await {
+            Ok(draft) => Ok(GatewayResponse::new(true, None, Some(draft), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Publishes the draft at `publishAt`, or unschedules it when left out.
+    pub async fn schedule_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft_id: String,
+        publish_at: Option<DateTime<Utc>>,
+    ) -> Result<GatewayResponse<PostDraft>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Schedule Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Schedule Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to schedule a draft.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Schedule Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error scheduling draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match PostDraft::schedule(db, draft_id, publish_at, logged_in_user).await {
+            Ok(draft) => Ok(GatewayResponse::new(true, None, Some(draft), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Publishes the draft right away. The draft is gone afterwards.
+    pub async fn publish_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft_id: String,
+    ) -> Result<GatewayResponse<ExpressionPost>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Publish Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Publish Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to publish a draft.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Publish Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error publishing draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match PostDraft::publish(db, draft_id, logged_in_user).await {
+            Ok(post) => Ok(GatewayResponse::new(true, None, Some(post), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    pub async fn delete_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft_id: String,
+    ) -> Result<GatewayResponse<PostDraft>> {
+        // Check if user is authenticated
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Delete Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Delete Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to delete a draft.".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let logged_in_user = match AccessToken::decode(cookie.value()) {
+            Ok(claims) => claims.sub,
+            Err(_) => {
+                return Ok(GatewayResponse::new(
+                    false,
+                    Some("Invalid user".to_string()),
+                    None,
+                    400,
+                ))
+            }
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Delete Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error deleting draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        if let Err(err) = PostDraft::delete(db, draft_id, logged_in_user).await {
+            Ok(GatewayResponse::new(false, Some(err), None, 500))
         } else {
-            return Ok(GatewayResponse::new(true, None, None, 204));
+            Ok(GatewayResponse::new(true, None, None, 204))
         }
     }
 }
@@ -0,0 +1,116 @@
+use std::future::Future;
+
+use async_graphql::{
+    connection::{self, Connection, CursorType, Edge, OpaqueCursor},
+    OutputType,
+};
+
+use crate::db::{Cursor, Page};
+
+use super::{Bookmark, ExpressionPost, Follow, PostDraft, Reply};
+
+/// Number of items in a page when the client asks for neither `first` nor
+/// `last`.
+pub const DEFAULT_PAGE_SIZE: u16 = 20;
+/// Largest page a client may ask for.
+pub const MAX_PAGE_SIZE: u16 = 100;
+
+/// Cursor handed to clients. It is the position of an item in its list,
+/// encoded so clients treat it as an opaque string.
+pub type PageCursor = OpaqueCursor<Cursor>;
+pub type ExpressionPostConnection = Connection<PageCursor, ExpressionPost>;
+pub type ReplyConnection = Connection<PageCursor, Reply>;
+pub type FollowConnection = Connection<PageCursor, Follow>;
+pub type BookmarkConnection = Connection<PageCursor, Bookmark>;
+pub type PostDraftConnection = Connection<PageCursor, PostDraft>;
+
+/// Relay pagination arguments of a connection field.
+#[derive(Debug, Clone, Default)]
+pub struct PageArgs {
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub first: Option<i32>,
+    pub last: Option<i32>,
+}
+
+impl PageArgs {
+    /// Largest number of items the page can hold.
+    pub fn size(&self) -> u16 {
+        let size = match self.first.or(self.last) {
+            Some(size) if size > 0 => u16::try_from(size).unwrap_or(MAX_PAGE_SIZE),
+            _ => DEFAULT_PAGE_SIZE,
+        };
+        size.min(MAX_PAGE_SIZE)
+    }
+}
+
+/// Builds a connection from the page of a list selected by `args`.
+/// `load` receives a page one item larger than asked for; the extra item
+/// only tells whether there is more to load past the end of the page.
+pub async fn connection<T, F, Fut>(
+    args: PageArgs,
+    cursor_of: impl Fn(&T) -> Cursor,
+    load: F,
+) -> Result<Connection<PageCursor, T>, String>
+where
+    T: OutputType,
+    F: FnOnce(Page) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, String>>,
+{
+    let limit = args.size();
+    let PageArgs {
+        after,
+        before,
+        first,
+        last,
+    } = args;
+    let from_end = last.is_some() && first.is_none();
+    if [&after, &before]
+        .into_iter()
+        .flatten()
+        .any(|cursor| PageCursor::decode_cursor(cursor).is_err())
+    {
+        return Err("Invalid page cursor.".to_string());
+    }
+
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<PageCursor>, before: Option<PageCursor>, _, _| async move {
+            let (has_after, has_before) = (after.is_some(), before.is_some());
+            let page = Page {
+                after: after.map(|cursor| cursor.0),
+                before: before.map(|cursor| cursor.0),
+                limit: limit + 1,
+                from_end,
+            };
+            let mut items = load(page).await?;
+
+            let has_more = items.len() > usize::from(limit);
+            if has_more {
+                if from_end {
+                    items.remove(0);
+                } else {
+                    items.truncate(usize::from(limit));
+                }
+            }
+            let (has_previous, has_next) = if from_end {
+                (has_more, has_before)
+            } else {
+                (has_after, has_more)
+            };
+
+            let mut connection = Connection::new(has_previous, has_next);
+            connection.edges.extend(
+                items
+                    .into_iter()
+                    .map(|item| Edge::new(OpaqueCursor(cursor_of(&item)), item)),
+            );
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+    .map_err(|err| err.message)
+}
@@ -6,16 +6,49 @@ use tower_cookies::Cookies;
 use crate::{
     auth::AccessToken,
     community::{
-        models::{expression_post::ExpressionPostAggregate, user_profile::UserProfile},
-        ExpressionPost,
+        models::{
+            expression_post::ExpressionPostAggregate, search::SearchFilters,
+            user_profile::UserProfile,
+        },
+        pagination::PageArgs,
+        viewer, Bookmark, BookmarkCollectionList, BookmarkConnection, Circle, CircleList,
+        CircleMemberList, CircleMemberStatus, ExpressionPost, ExpressionPostConnection,
+        FollowConnection, PostDraft, PostDraftConnection, PostSearchResult, PostSearchResults,
+        Reply, ReplyConnection, ReplyEditHistory, Topic, TopicList, TrendingWindow,
     },
-    db::DbController,
+    db::{DbController, Page},
     GatewayResponse,
 };
 
+/// Number of posts returned by feed queries when no limit is given.
+const DEFAULT_FEED_LIMIT: u16 = 20;
+
+fn feed_limit(limit: Option<u16>) -> u16 {
+    match limit {
+        Some(0) | None => DEFAULT_FEED_LIMIT,
+        Some(limit) => limit,
+    }
+}
+
+/// Complexity of a feed field scales with the number of posts it can return.
+fn feed_cost(limit: Option<u16>, child_complexity: usize) -> usize {
+    feed_limit(limit) as usize * child_complexity
+}
+
+/// Complexity of a connection field scales with the size of its pages.
+fn page_cost(first: Option<i32>, last: Option<i32>, child_complexity: usize) -> usize {
+    let args = PageArgs {
+        first,
+        last,
+        ..PageArgs::default()
+    };
+    usize::from(args.size()) * child_complexity
+}
+
+#[derive(Default)]
 pub struct Query;
 
-#[Object]
+#[Object(name = "CommunityQuery")]
 impl Query {
     async fn get_logged_in_user_profile(
         &self,
@@ -75,6 +108,93 @@ impl Query {
         Ok(GatewayResponse::new(true, None, Some(profile), 200))
     }
 
+    async fn get_user_profile(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+    ) -> Result<GatewayResponse<UserProfile>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get User Profile");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting user. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match UserProfile::get_by_id(db, user_id).await {
+            Ok(profile) => Ok(GatewayResponse::new(true, None, Some(profile), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Users following a user, latest follow first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_followers(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<FollowConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Followers");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting followers. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match UserProfile::get_followers(db, user_id, args).await {
+            Ok(followers) => Ok(GatewayResponse::new(true, None, Some(followers), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Users a user follows, latest follow first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_following(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<FollowConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Following");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting followed users. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match UserProfile::get_following(db, user_id, args).await {
+            Ok(following) => Ok(GatewayResponse::new(true, None, Some(following), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
     async fn get_expression_post(
         &self,
         ctx: &Context<'_>,
@@ -90,20 +210,111 @@ impl Query {
             ));
         };
 
-        match ExpressionPost::get_by_id(db, post_id).await {
-            Ok(post) => return Ok(GatewayResponse::new(true, None, Some(post), 200)),
-            Err(err) => return Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        match ExpressionPost::get_for_viewer(db, post_id, viewer(ctx)).await {
+            Ok(post) => Ok(GatewayResponse::new(true, None, Some(post), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Direct replies to a post or reply, oldest first, each with the start
+    /// of its own thread. Loads the branches that were cut short by the depth
+    /// or page size limits.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_replies(
+        &self,
+        ctx: &Context<'_>,
+        parent_id: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ReplyConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Replies");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting replies. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match Reply::get_replies(db, parent_id, viewer(ctx), args).await {
+            Ok(replies) => Ok(GatewayResponse::new(true, None, Some(replies), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Earlier versions of an edited reply. Moderators only.
+    async fn get_reply_edits(
+        &self,
+        ctx: &Context<'_>,
+        reply_id: String,
+    ) -> Result<GatewayResponse<ReplyEditHistory>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Reply Edits");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Reply Edits");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to review reply edits.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Reply Edits");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting reply edits. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Reply::get_edits(db, reply_id, claims.sub).await {
+            Ok(edits) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(ReplyEditHistory { edits }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
         }
     }
 
+    #[graphql(
+        complexity = "feed_cost(limit, child_complexity)",
+        deprecation = "Use getRecentFeed, which pages with cursors."
+    )]
     async fn get_recent_posts(
         &self,
         ctx: &Context<'_>,
-        mut limit: Option<u16>,
+        limit: Option<u16>,
     ) -> Result<GatewayResponse<ExpressionPostAggregate>> {
-        if limit.is_some() && limit == Some(0) || limit.is_none() {
-            limit = Some(20);
-        }
+        let limit = feed_limit(limit);
 
         let Ok(db) = ctx.data::<Arc<DbController>>() else {
             eprintln!("SERVER ERROR: Error getting database in Get Recent Expression Posts");
@@ -115,7 +326,7 @@ impl Query {
             ));
         };
 
-        match ExpressionPost::get_recent_posts(db, limit.unwrap()).await {
+        match ExpressionPost::get_recent_posts(db, Page::first(limit)).await {
             Ok(posts) => Ok(GatewayResponse::new(
                 true,
                 None,
@@ -126,14 +337,16 @@ impl Query {
         }
     }
 
+    #[graphql(
+        complexity = "feed_cost(limit, child_complexity)",
+        deprecation = "Use getTrendingFeed, which pages with cursors."
+    )]
     async fn get_trending_posts(
         &self,
         ctx: &Context<'_>,
-        mut limit: Option<u16>,
+        limit: Option<u16>,
     ) -> Result<GatewayResponse<ExpressionPostAggregate>> {
-        if limit.is_some() && limit == Some(0) || limit.is_none() {
-            limit = Some(20);
-        }
+        let limit = feed_limit(limit);
 
         let Ok(db) = ctx.data::<Arc<DbController>>() else {
             eprintln!("SERVER ERROR: Error getting database in Get Trending Expression Posts");
@@ -145,7 +358,9 @@ impl Query {
             ));
         };
 
-        match ExpressionPost::get_trending_posts(db, limit.unwrap()).await {
+        match ExpressionPost::get_trending_posts(db, TrendingWindow::default(), Page::first(limit))
+            .await
+        {
             Ok(posts) => Ok(GatewayResponse::new(
                 true,
                 None,
@@ -155,4 +370,687 @@ impl Query {
             Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
         }
     }
+
+    /// All posts, newest first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_recent_feed(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Recent Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting recent feed. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_recent_feed(db, args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts created within `window`, a week unless given, highest trending
+    /// score first. The score weighs likes and replies and decays with age.
+    /// Pages follow the stored scores, so a post whose score moves between
+    /// pages can be shown twice or skipped.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_trending_feed(
+        &self,
+        ctx: &Context<'_>,
+        window: Option<TrendingWindow>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Trending Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting trending feed. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_trending_feed(db, window.unwrap_or_default(), args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts of one user, newest first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_user_posts(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get User Posts");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting user posts. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_author_feed(db, user_id, args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts of the users and topics the logged in user follows from the
+    /// last 30 days, highest trending score first. Users whose follows posted
+    /// nothing in that time, or who follow nobody, get the trending feed.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_home_feed(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Home Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Home Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see your home feed.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Home Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting home feed. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_home_feed(db, claims.sub, args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts whose own text or replies contain every word of `query`, most
+    /// relevant first, each with a highlighted snippet of where it matched.
+    #[graphql(complexity = "feed_cost(limit, child_complexity)")]
+    async fn search_posts(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        filters: Option<SearchFilters>,
+        limit: Option<u16>,
+        offset: Option<u32>,
+    ) -> Result<GatewayResponse<PostSearchResults>> {
+        let limit = feed_limit(limit);
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Search Posts");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error searching expression posts. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let filters = filters.unwrap_or_default();
+        match PostSearchResult::search(db, query, filters, limit, offset.unwrap_or_default()).await
+        {
+            Ok(results) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(PostSearchResults { results }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts tagged with a topic, newest first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_posts_by_tag(
+        &self,
+        ctx: &Context<'_>,
+        tag: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Posts By Tag");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting expression posts. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_tag_feed(db, tag, args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Topics posts can be tagged with, most used first, each with its number
+    /// of posts. With a `prefix` this autocompletes a topic name.
+    async fn get_topics(
+        &self,
+        ctx: &Context<'_>,
+        prefix: Option<String>,
+        limit: Option<u16>,
+    ) -> Result<GatewayResponse<TopicList>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Topics");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting topics. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Topic::get_topics(db, prefix, limit).await {
+            Ok(topics) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(TopicList { topics }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Topics the logged in user follows.
+    async fn get_followed_topics(&self, ctx: &Context<'_>) -> Result<GatewayResponse<TopicList>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Followed Topics");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Followed Topics");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see the topics you follow.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Followed Topics");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting topics. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Topic::get_followed(db, claims.sub).await {
+            Ok(topics) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(TopicList { topics }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    async fn get_circle(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+    ) -> Result<GatewayResponse<Circle>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Circle");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting circle. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::get_by_id(db, &circle_id).await {
+            Ok(circle) => Ok(GatewayResponse::new(true, None, Some(circle), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Circles with the most members first.
+    async fn get_circles(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<u16>,
+        offset: Option<u32>,
+    ) -> Result<GatewayResponse<CircleList>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Circles");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting circles. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Circle::get_circles(db, limit, offset).await {
+            Ok(circles) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(CircleList { circles }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Active members of a circle, owners first, or with `PENDING` the
+    /// requests to join waiting for an owner or moderator.
+    async fn get_circle_members(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        status: Option<CircleMemberStatus>,
+    ) -> Result<GatewayResponse<CircleMemberList>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Circle Members");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting circle members. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let status = status.unwrap_or(CircleMemberStatus::Active);
+        match Circle::get_members(db, circle_id, status, viewer(ctx)).await {
+            Ok(members) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(CircleMemberList { members }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts of a circle, newest first. Circles that take join requests are
+    /// only readable by their members.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_circle_recent_feed(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Circle Recent Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting circle feed. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match ExpressionPost::get_circle_feed(db, circle_id, None, viewer(ctx), args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Posts of a circle created within `window`, a week unless given,
+    /// highest trending score first.
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_circle_trending_feed(
+        &self,
+        ctx: &Context<'_>,
+        circle_id: String,
+        window: Option<TrendingWindow>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<ExpressionPostConnection>> {
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Circle Trending Feed");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting circle feed. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        let window = Some(window.unwrap_or_default());
+        match ExpressionPost::get_circle_feed(db, circle_id, window, viewer(ctx), args).await {
+            Ok(posts) => Ok(GatewayResponse::new(true, None, Some(posts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Bookmarks of the logged in user, latest first. With a `collection`,
+    /// only the bookmarks filed under it.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_bookmarks(
+        &self,
+        ctx: &Context<'_>,
+        collection: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<BookmarkConnection>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Bookmarks");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Bookmarks");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see your bookmarks.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Bookmarks");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting bookmarks. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match Bookmark::get_bookmarks(db, claims.sub, collection, args).await {
+            Ok(bookmarks) => Ok(GatewayResponse::new(true, None, Some(bookmarks), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Bookmark collections of the logged in user by name, each with its
+    /// number of bookmarks.
+    async fn get_bookmark_collections(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<GatewayResponse<BookmarkCollectionList>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Bookmark Collections");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Bookmark Collections");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see your bookmark collections.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Bookmark Collections");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting bookmark collections. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match Bookmark::get_collections(db, claims.sub).await {
+            Ok(collections) => Ok(GatewayResponse::new(
+                true,
+                None,
+                Some(BookmarkCollectionList { collections }),
+                200,
+            )),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// Drafts of the logged in user, last saved first.
+    #[graphql(complexity = "page_cost(first, last, child_complexity)")]
+    async fn get_drafts(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GatewayResponse<PostDraftConnection>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Drafts");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Drafts");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see your drafts.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Drafts");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting drafts. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        let args = PageArgs {
+            after,
+            before,
+            first,
+            last,
+        };
+        match PostDraft::get_drafts(db, claims.sub, args).await {
+            Ok(drafts) => Ok(GatewayResponse::new(true, None, Some(drafts), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
+
+    /// A draft of the logged in user. Nobody else can load it.
+    async fn get_draft(
+        &self,
+        ctx: &Context<'_>,
+        draft_id: String,
+    ) -> Result<GatewayResponse<PostDraft>> {
+        let Ok(cookies) = ctx.data::<Cookies>() else {
+            eprintln!("SERVER ERROR: Error getting cookies in Get Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Server error. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+        let Some(cookie) = cookies.get("sat") else {
+            eprintln!("SERVER ERROR: Access token doesn't exist in Get Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Please log in to see your drafts.".to_string()),
+                None,
+                400,
+            ));
+        };
+        let Ok(claims) = AccessToken::decode(cookie.value()) else {
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Invalid user".to_string()),
+                None,
+                400,
+            ));
+        };
+
+        let Ok(db) = ctx.data::<Arc<DbController>>() else {
+            eprintln!("SERVER ERROR: Error getting database in Get Draft");
+            return Ok(GatewayResponse::new(
+                false,
+                Some("Error getting draft. Please try again.".to_string()),
+                None,
+                500,
+            ));
+        };
+
+        match PostDraft::get(db, draft_id, claims.sub).await {
+            Ok(draft) => Ok(GatewayResponse::new(true, None, Some(draft), 200)),
+            Err(err) => Ok(GatewayResponse::new(false, Some(err), None, 500)),
+        }
+    }
 }
@@ -0,0 +1,32 @@
+/// Parses the environment variable `key`, falling back to `default` when it
+/// is missing. A value that does not parse is reported and also falls back
+/// to `default`, so a typo never takes the server down.
+pub(crate) fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    let Ok(value) = dotenv::var(key) else {
+        return default;
+    };
+    match value.trim().parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("CONFIG_ERROR: Ignoring malformed {key} `{value}`, using the default.");
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_or_parses_values_and_falls_back_to_the_default() {
+        std::env::set_var("CONFIG_TEST_SECS", " 12 ");
+        std::env::set_var("CONFIG_TEST_FLAG", "true");
+        std::env::set_var("CONFIG_TEST_MALFORMED", "twelve");
+
+        assert_eq!(env_or("CONFIG_TEST_SECS", 5u64), 12);
+        assert!(env_or("CONFIG_TEST_FLAG", false));
+        assert_eq!(env_or("CONFIG_TEST_MALFORMED", 5u64), 5);
+        assert_eq!(env_or("CONFIG_TEST_MISSING", 5u64), 5);
+    }
+}
@@ -0,0 +1,1672 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::MigrateError;
+
+use crate::community::{
+    BookmarkCollection, Circle, CircleMember, CircleMemberStatus, CircleRole, ExpressionPost,
+    Follow, PostDraft, Reply, ReplyEdit, Topic, UserProfile,
+};
+
+use super::{
+    migrations::MigrationStatus, AccountOperation, AccountOperationStatus, AuthRecord, AuthRepo,
+    Backend, BookmarkRecord, BookmarkRepo, CircleRepo, CommunityTransaction, Cursor, DbError,
+    DbResult, DeletionRecord, DraftRepo, NewCircleRecord, NewPostRecord, NewReplyRecord, Page,
+    PostCounts, PostFeed, PostRepo, PostSearch, ProfileRepo, ReplyRepo, SearchHit, SortKey,
+    ThreadPage, TopicRepo,
+};
+
+/// Topics the store starts with, the same ones the topics migration seeds.
+const CURATED_TOPICS: [&str; 15] = [
+    "anxiety",
+    "depression",
+    "grief",
+    "recovery",
+    "stress",
+    "loneliness",
+    "relationships",
+    "family",
+    "work",
+    "burnout",
+    "self-care",
+    "mindfulness",
+    "sleep",
+    "trauma",
+    "addiction",
+];
+
+#[derive(Debug, Clone)]
+struct AuthRow {
+    record: AuthRecord,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct ProfileRow {
+    id: String,
+    username: String,
+    avatar: String,
+    is_moderator: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PostRow {
+    record: NewPostRecord,
+    last_modified: DateTime<Utc>,
+    like_count: i32,
+    reply_count: i32,
+    hot_score: f64,
+    deleted_at: Option<DateTime<Utc>>,
+    deleted_by: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ReplyRow {
+    record: NewReplyRecord,
+    last_modified: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
+    deleted_by: Option<String>,
+    edited_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct ReplyEditRow {
+    reply_id: String,
+    edit: ReplyEdit,
+}
+
+#[derive(Debug, Clone)]
+struct LikeRow {
+    parent_id: String,
+    author: String,
+}
+
+#[derive(Debug, Clone)]
+struct TopicFollowRow {
+    user_id: String,
+    topic: String,
+}
+
+#[derive(Debug, Clone)]
+struct UserFollowRow {
+    follower_id: String,
+    followee_id: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct CircleMemberRow {
+    circle_id: String,
+    user_id: String,
+    role: CircleRole,
+    status: CircleMemberStatus,
+    joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct MemoryState {
+    auths: Vec<AuthRow>,
+    operations: Vec<AccountOperation>,
+    profiles: Vec<ProfileRow>,
+    posts: Vec<PostRow>,
+    replies: Vec<ReplyRow>,
+    reply_edits: Vec<ReplyEditRow>,
+    likes: Vec<LikeRow>,
+    topics: Vec<String>,
+    topic_follows: Vec<TopicFollowRow>,
+    follows: Vec<UserFollowRow>,
+    circles: Vec<Circle>,
+    circle_members: Vec<CircleMemberRow>,
+    bookmarks: Vec<BookmarkRecord>,
+    drafts: Vec<PostDraft>,
+}
+
+impl MemoryState {
+    fn profile(&self, id: &str) -> Option<UserProfile> {
+        self.profiles.iter().find(|p| p.id == id).map(|p| {
+            UserProfile::new(
+                p.id.clone(),
+                p.username.clone(),
+                p.avatar.clone(),
+                self.likes
+                    .iter()
+                    .filter(|like| like.author == id)
+                    .map(|like| like.parent_id.clone())
+                    .collect(),
+            )
+        })
+    }
+
+    /// Builds a post the way the SQL joins do: the author is loaded without
+    /// likes and missing authors fall back to an empty profile.
+    fn post(&self, row: &PostRow) -> ExpressionPost {
+        let post = &row.record;
+        let author = self
+            .profiles
+            .iter()
+            .find(|p| p.id == post.author)
+            .map(|p| UserProfile::new(p.id.clone(), p.username.clone(), p.avatar.clone(), vec![]))
+            .unwrap_or_default();
+
+        ExpressionPost::new(
+            post.id.clone(),
+            post.title.clone(),
+            post.subtitle.clone(),
+            post.cover_image.clone(),
+            author,
+            post.content_type.clone(),
+            post.content_value.clone(),
+            vec![],
+            row.reply_count,
+            row.like_count,
+            post.created_at,
+            row.last_modified,
+        )
+        .with_hot_score(row.hot_score)
+        .with_tags(post.tags.clone())
+        .with_circle(post.circle_id.clone())
+    }
+
+    /// Shifts the counters of a post, the way the SQL stores do in the same
+    /// transaction as the change they track.
+    fn adjust_counts(&mut self, post_id: &str, likes: i32, replies: i32) {
+        if let Some(post) = self.posts.iter_mut().find(|post| post.record.id == post_id) {
+            post.like_count += likes;
+            post.reply_count += replies;
+        }
+    }
+
+    /// Recomputes every post's counters, returning how many were off.
+    fn recount(&mut self) -> u64 {
+        let mut repaired = 0;
+        for index in 0..self.posts.len() {
+            let id = &self.posts[index].record.id;
+            let likes = self
+                .likes
+                .iter()
+                .filter(|like| &like.parent_id == id)
+                .count() as i32;
+            let replies = self
+                .replies
+                .iter()
+                .filter(|reply| &reply.record.parent == id && reply.deleted_at.is_none())
+                .count() as i32;
+
+            let post = &mut self.posts[index];
+            if post.like_count != likes || post.reply_count != replies {
+                post.like_count = likes;
+                post.reply_count = replies;
+                repaired += 1;
+            }
+        }
+        repaired
+    }
+
+    /// Id of the post whose thread the reply belongs to.
+    fn post_of<'a>(&'a self, row: &'a ReplyRow) -> &'a str {
+        let mut parent = &row.record.parent;
+        while let Some(reply) = self.replies.iter().find(|reply| &reply.record.id == parent) {
+            parent = &reply.record.parent;
+        }
+        parent
+    }
+
+    fn reply(&self, row: &ReplyRow) -> Option<Reply> {
+        let reply = &row.record;
+        let author = self.profiles.iter().find(|p| p.id == reply.author)?;
+
+        Some(
+            Reply::new(
+                reply.id.clone(),
+                UserProfile::new(
+                    author.id.clone(),
+                    author.username.clone(),
+                    author.avatar.clone(),
+                    vec![],
+                ),
+                reply.parent.clone(),
+                reply.content.clone(),
+                reply.created_at,
+                row.last_modified,
+                row.deleted_at,
+            )
+            .with_edited_at(row.edited_at),
+        )
+    }
+
+    /// Mirrors the ON DELETE CASCADE foreign key from edits to replies.
+    fn drop_orphaned_edits(&mut self) {
+        let replies = &self.replies;
+        self.reply_edits
+            .retain(|edit| replies.iter().any(|reply| reply.record.id == edit.reply_id));
+    }
+
+    /// Mirrors the ON DELETE CASCADE foreign keys from bookmarks to posts
+    /// and replies.
+    fn drop_orphaned_bookmarks(&mut self) {
+        let (posts, replies) = (&self.posts, &self.replies);
+        self.bookmarks
+            .retain(|bookmark| match (&bookmark.post_id, &bookmark.reply_id) {
+                (Some(post_id), _) => posts.iter().any(|post| &post.record.id == post_id),
+                (None, Some(reply_id)) => replies.iter().any(|reply| &reply.record.id == reply_id),
+                (None, None) => false,
+            });
+    }
+
+    /// Whether the post or reply the bookmark saves is not deleted.
+    fn bookmark_is_live(&self, bookmark: &BookmarkRecord) -> bool {
+        match (&bookmark.post_id, &bookmark.reply_id) {
+            (Some(post_id), _) => self
+                .posts
+                .iter()
+                .any(|post| &post.record.id == post_id && post.deleted_at.is_none()),
+            (None, Some(reply_id)) => self
+                .replies
+                .iter()
+                .any(|reply| &reply.record.id == reply_id && reply.deleted_at.is_none()),
+            (None, None) => false,
+        }
+    }
+
+    /// Topic with the number of live posts tagged with it.
+    fn topic(&self, name: &str) -> Topic {
+        Topic {
+            name: name.to_string(),
+            post_count: self
+                .posts
+                .iter()
+                .filter(|post| {
+                    post.deleted_at.is_none()
+                        && post.record.circle_id.is_none()
+                        && post.record.tags.iter().any(|tag| tag == name)
+                })
+                .count() as i64,
+        }
+    }
+
+    /// Circle with its number of active members.
+    fn circle(&self, circle: &Circle) -> Circle {
+        Circle {
+            member_count: self
+                .circle_members
+                .iter()
+                .filter(|member| {
+                    member.circle_id == circle.id && member.status == CircleMemberStatus::Active
+                })
+                .count() as i64,
+            ..circle.clone()
+        }
+    }
+
+    /// Member with their profile, dropped like the SQL join drops members
+    /// whose profile is gone.
+    fn circle_member(&self, row: &CircleMemberRow) -> Option<CircleMember> {
+        let profile = self.profiles.iter().find(|p| p.id == row.user_id)?;
+        Some(CircleMember {
+            circle_id: row.circle_id.clone(),
+            user: UserProfile::new(
+                profile.id.clone(),
+                profile.username.clone(),
+                profile.avatar.clone(),
+                vec![],
+            ),
+            role: row.role,
+            status: row.status,
+            joined_at: row.joined_at,
+        })
+    }
+
+    /// Page of the users in follows `matches` picks, taking each user from
+    /// the follow with `user`.
+    fn follows(
+        &self,
+        matches: impl Fn(&UserFollowRow) -> bool,
+        user: impl Fn(&UserFollowRow) -> &str,
+        page: &Page,
+    ) -> Vec<Follow> {
+        let follows = self
+            .follows
+            .iter()
+            .filter(|follow| matches(follow))
+            .filter_map(|follow| {
+                let profile = self.profiles.iter().find(|p| p.id == user(follow))?;
+                Some(Follow {
+                    user: UserProfile::new(
+                        profile.id.clone(),
+                        profile.username.clone(),
+                        profile.avatar.clone(),
+                        vec![],
+                    ),
+                    followed_at: follow.created_at,
+                })
+            })
+            .collect();
+        page.apply(follows, Follow::page_cursor, true)
+    }
+
+    fn live_post(&self, id: &str) -> Option<&PostRow> {
+        self.posts
+            .iter()
+            .find(|post| post.record.id == id && post.deleted_at.is_none())
+    }
+}
+
+fn deletion(
+    author: &str,
+    deleted_by: &Option<String>,
+    deleted_at: Option<DateTime<Utc>>,
+) -> Option<DeletionRecord> {
+    Some(DeletionRecord {
+        author: author.to_string(),
+        deleted_by: deleted_by.clone().unwrap_or_default(),
+        deleted_at: deleted_at?,
+    })
+}
+
+/// Repository implementation that keeps everything in process memory. Used
+/// to exercise model and resolver logic without a database.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    state: Arc<Mutex<MemoryState>>,
+    /// State as it was when the unit of work this store is bound to began.
+    snapshot: Arc<Mutex<Option<MemoryState>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let store = Self::default();
+        if let Ok(mut state) = store.state() {
+            state.topics = CURATED_TOPICS.map(str::to_string).to_vec();
+        }
+        store
+    }
+
+    fn state(&self) -> DbResult<MutexGuard<'_, MemoryState>> {
+        self.state
+            .lock()
+            .map_err(|_| DbError::Backend("In-memory store is poisoned".to_string()))
+    }
+
+    fn take_snapshot(&self) -> DbResult<Option<MemoryState>> {
+        self.snapshot
+            .lock()
+            .map(|mut snapshot| snapshot.take())
+            .map_err(|_| DbError::Backend("In-memory store is poisoned".to_string()))
+    }
+}
+
+fn finished() -> DbError {
+    DbError::Backend("The unit of work has already finished".to_string())
+}
+
+/// Memory is always reachable and has no schema to migrate.
+#[async_trait]
+impl Backend for MemoryStore {
+    async fn close(&self) {}
+
+    async fn ping_auth(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn ping_community(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn migrate_up(&self) -> Result<(), MigrateError> {
+        Ok(())
+    }
+
+    async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), MigrateError> {
+        Ok((None, None))
+    }
+
+    async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), MigrateError> {
+        Ok((vec![], vec![]))
+    }
+
+    fn has_replicas(&self) -> bool {
+        false
+    }
+
+    async fn check_replicas(&self, _max_lag: Duration) {}
+
+    async fn begin_community(&self) -> DbResult<Arc<dyn CommunityTransaction>> {
+        let snapshot = self.state()?.clone();
+        Ok(Arc::new(Self {
+            state: self.state.clone(),
+            snapshot: Arc::new(Mutex::new(Some(snapshot))),
+        }))
+    }
+}
+
+/// Units of work share the live state, so writes are visible to everyone as
+/// soon as they happen. A rollback puts back the state the unit began with,
+/// undoing whatever other units wrote in the meantime too.
+#[async_trait]
+impl CommunityTransaction for MemoryStore {
+    async fn commit(&self) -> DbResult<()> {
+        match self.take_snapshot()? {
+            Some(_) => Ok(()),
+            None => Err(finished()),
+        }
+    }
+
+    async fn rollback(&self) -> DbResult<()> {
+        let Some(snapshot) = self.take_snapshot()? else {
+            return Err(finished());
+        };
+        *self.state()? = snapshot;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuthRepo for MemoryStore {
+    async fn email_exists(&self, email: &str) -> DbResult<bool> {
+        Ok(self
+            .state()?
+            .auths
+            .iter()
+            .any(|auth| auth.record.email == email))
+    }
+
+    async fn insert(&self, auth: &AuthRecord, operation: &AccountOperation) -> DbResult<()> {
+        let mut state = self.state()?;
+        if state
+            .auths
+            .iter()
+            .any(|a| a.record.id == auth.id || a.record.email == auth.email)
+        {
+            return Err(DbError::Backend("Duplicate auth".to_string()));
+        }
+        state.auths.push(AuthRow {
+            record: auth.clone(),
+            created_at: super::now(),
+        });
+        state.operations.push(operation.clone());
+        Ok(())
+    }
+
+    async fn find_by_email(&self, email: &str) -> DbResult<Option<AuthRecord>> {
+        Ok(self
+            .state()?
+            .auths
+            .iter()
+            .find(|auth| auth.record.email == email)
+            .map(|auth| auth.record.clone()))
+    }
+
+    async fn find_by_id(&self, id: &str) -> DbResult<Option<AuthRecord>> {
+        Ok(self
+            .state()?
+            .auths
+            .iter()
+            .find(|auth| auth.record.id == id)
+            .map(|auth| auth.record.clone()))
+    }
+
+    async fn set_refresh_token(&self, community_id: &str, token: &str) -> DbResult<()> {
+        for auth in self
+            .state()?
+            .auths
+            .iter_mut()
+            .filter(|auth| auth.record.community_id == community_id)
+        {
+            auth.record.refresh_token = Some(token.to_string());
+        }
+        Ok(())
+    }
+
+    async fn update_email(&self, community_id: &str, email: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        if state
+            .auths
+            .iter()
+            .any(|a| a.record.email == email && a.record.community_id != community_id)
+        {
+            return Err(DbError::Backend("Duplicate email".to_string()));
+        }
+        for auth in state
+            .auths
+            .iter_mut()
+            .filter(|auth| auth.record.community_id == community_id)
+        {
+            auth.record.email = email.to_string();
+        }
+        Ok(())
+    }
+
+    async fn update_hash(&self, community_id: &str, hash: &str) -> DbResult<()> {
+        for auth in self
+            .state()?
+            .auths
+            .iter_mut()
+            .filter(|auth| auth.record.community_id == community_id)
+        {
+            auth.record.hash = hash.to_string();
+        }
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        community_id: &str,
+        operation_id: &str,
+        status: AccountOperationStatus,
+    ) -> DbResult<()> {
+        let mut state = self.state()?;
+        state
+            .auths
+            .retain(|auth| auth.record.community_id != community_id);
+        if let Some(operation) = state.operations.iter_mut().find(|op| op.id == operation_id) {
+            operation.status = status;
+        }
+        Ok(())
+    }
+
+    async fn community_ids(&self, created_before: DateTime<Utc>) -> DbResult<Vec<String>> {
+        Ok(self
+            .state()?
+            .auths
+            .iter()
+            .filter(|auth| auth.created_at < created_before)
+            .map(|auth| auth.record.community_id.clone())
+            .collect())
+    }
+
+    async fn insert_operation(&self, operation: &AccountOperation) -> DbResult<()> {
+        self.state()?.operations.push(operation.clone());
+        Ok(())
+    }
+
+    async fn set_operation_status(&self, id: &str, status: AccountOperationStatus) -> DbResult<()> {
+        if let Some(operation) = self.state()?.operations.iter_mut().find(|op| op.id == id) {
+            operation.status = status;
+        }
+        Ok(())
+    }
+
+    async fn fail_operation(
+        &self,
+        id: &str,
+        status: AccountOperationStatus,
+        _error: &str,
+    ) -> DbResult<()> {
+        if let Some(operation) = self.state()?.operations.iter_mut().find(|op| op.id == id) {
+            operation.status = status;
+            operation.attempts += 1;
+        }
+        Ok(())
+    }
+
+    async fn open_operations(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> DbResult<Vec<AccountOperation>> {
+        Ok(self
+            .state()?
+            .operations
+            .iter()
+            .filter(|op| {
+                matches!(
+                    op.status,
+                    AccountOperationStatus::Pending | AccountOperationStatus::Compensating
+                ) && op.created_at < created_before
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ProfileRepo for MemoryStore {
+    async fn exists(&self, id: &str, username: &str) -> DbResult<bool> {
+        Ok(self
+            .state()?
+            .profiles
+            .iter()
+            .any(|p| p.id == id || p.username == username))
+    }
+
+    async fn insert(&self, id: &str, username: &str, avatar: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        if state
+            .profiles
+            .iter()
+            .any(|p| p.id == id || p.username == username)
+        {
+            return Err(DbError::Backend("Duplicate profile".to_string()));
+        }
+        state.profiles.push(ProfileRow {
+            id: id.to_string(),
+            username: username.to_string(),
+            avatar: avatar.to_string(),
+            is_moderator: false,
+        });
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<UserProfile>> {
+        Ok(self.state()?.profile(id))
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        let liked: Vec<String> = state
+            .likes
+            .iter()
+            .filter(|like| like.author == id)
+            .map(|like| like.parent_id.clone())
+            .collect();
+        let replied: Vec<String> = state
+            .replies
+            .iter()
+            .filter(|reply| reply.record.author == id && reply.deleted_at.is_none())
+            .map(|reply| reply.record.parent.clone())
+            .collect();
+        for post_id in liked {
+            state.adjust_counts(&post_id, -1, 0);
+        }
+        for post_id in replied {
+            state.adjust_counts(&post_id, 0, -1);
+        }
+
+        // Mirrors the ON DELETE CASCADE foreign keys on posts and replies
+        state.profiles.retain(|p| p.id != id);
+        state.posts.retain(|post| post.record.author != id);
+        state.replies.retain(|reply| reply.record.author != id);
+        state.drop_orphaned_edits();
+        state.bookmarks.retain(|bookmark| bookmark.user_id != id);
+        state.drafts.retain(|draft| draft.author != id);
+        state.drop_orphaned_bookmarks();
+        state.likes.retain(|like| like.author != id);
+        state.topic_follows.retain(|follow| follow.user_id != id);
+        state.circle_members.retain(|member| member.user_id != id);
+        state
+            .follows
+            .retain(|follow| follow.follower_id != id && follow.followee_id != id);
+        Ok(())
+    }
+
+    async fn ids(&self) -> DbResult<Vec<String>> {
+        Ok(self
+            .state()?
+            .profiles
+            .iter()
+            .map(|p| p.id.clone())
+            .collect())
+    }
+
+    async fn is_moderator(&self, id: &str) -> DbResult<bool> {
+        Ok(self
+            .state()?
+            .profiles
+            .iter()
+            .any(|p| p.id == id && p.is_moderator))
+    }
+
+    async fn follow(&self, follower: &str, followee: &str, at: DateTime<Utc>) -> DbResult<()> {
+        let mut state = self.state()?;
+        if !state
+            .follows
+            .iter()
+            .any(|follow| follow.follower_id == follower && follow.followee_id == followee)
+        {
+            state.follows.push(UserFollowRow {
+                follower_id: follower.to_string(),
+                followee_id: followee.to_string(),
+                created_at: at,
+            });
+        }
+        Ok(())
+    }
+
+    async fn unfollow(&self, follower: &str, followee: &str) -> DbResult<()> {
+        self.state()?
+            .follows
+            .retain(|follow| !(follow.follower_id == follower && follow.followee_id == followee));
+        Ok(())
+    }
+
+    async fn follow_counts(&self, ids: &[String]) -> DbResult<HashMap<String, (i64, i64)>> {
+        let state = self.state()?;
+        let count = |matches: &dyn Fn(&UserFollowRow) -> bool| {
+            state
+                .follows
+                .iter()
+                .filter(|follow| matches(follow))
+                .count() as i64
+        };
+        Ok(ids
+            .iter()
+            .map(|id| {
+                let counts = (
+                    count(&|follow| follow.followee_id == *id),
+                    count(&|follow| follow.follower_id == *id),
+                );
+                (id.clone(), counts)
+            })
+            .filter(|(_, counts)| *counts != (0, 0))
+            .collect())
+    }
+
+    async fn followers(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>> {
+        Ok(self.state()?.follows(
+            |follow| follow.followee_id == id,
+            |follow| &follow.follower_id,
+            page,
+        ))
+    }
+
+    async fn following(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>> {
+        Ok(self.state()?.follows(
+            |follow| follow.follower_id == id,
+            |follow| &follow.followee_id,
+            page,
+        ))
+    }
+}
+
+#[async_trait]
+impl PostRepo for MemoryStore {
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<ExpressionPost>> {
+        let state = self.state()?;
+        Ok(state.live_post(id).map(|post| state.post(post)))
+    }
+
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>> {
+        Ok(self
+            .state()?
+            .live_post(id)
+            .map(|post| post.record.author.clone()))
+    }
+
+    async fn insert(&self, post: &NewPostRecord) -> DbResult<()> {
+        self.state()?.posts.push(PostRow {
+            record: post.clone(),
+            last_modified: post.created_at,
+            like_count: 0,
+            reply_count: 0,
+            hot_score: 0.0,
+            deleted_at: None,
+            deleted_by: None,
+        });
+        Ok(())
+    }
+
+    async fn update_content(
+        &self,
+        id: &str,
+        content_type: &str,
+        content_value: &str,
+    ) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(post) = state.posts.iter_mut().find(|post| post.record.id == id) {
+            post.record.content_type = content_type.to_string();
+            post.record.content_value = content_value.to_string();
+            post.last_modified = super::now();
+        }
+        Ok(())
+    }
+
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(post) = state
+            .posts
+            .iter_mut()
+            .find(|post| post.record.id == id && post.deleted_at.is_none())
+        {
+            post.deleted_at = Some(at);
+            post.deleted_by = Some(deleted_by.to_string());
+        }
+        Ok(())
+    }
+
+    async fn restore(&self, id: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(post) = state.posts.iter_mut().find(|post| post.record.id == id) {
+            post.deleted_at = None;
+            post.deleted_by = None;
+        }
+        Ok(())
+    }
+
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>> {
+        Ok(self
+            .state()?
+            .posts
+            .iter()
+            .find(|post| post.record.id == id)
+            .and_then(|post| deletion(&post.record.author, &post.deleted_by, post.deleted_at)))
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        let mut state = self.state()?;
+        let purged: Vec<String> = state
+            .posts
+            .iter()
+            .filter(|post| post.deleted_at.is_some_and(|at| at < before))
+            .map(|post| post.record.id.clone())
+            .collect();
+
+        // Replies hang off posts or other replies, so walk each purged thread
+        let mut doomed = purged.clone();
+        let mut level = purged.clone();
+        while !level.is_empty() {
+            level = state
+                .replies
+                .iter()
+                .filter(|reply| level.contains(&reply.record.parent))
+                .map(|reply| reply.record.id.clone())
+                .collect();
+            doomed.extend(level.iter().cloned());
+        }
+
+        state.likes.retain(|like| !purged.contains(&like.parent_id));
+        state
+            .replies
+            .retain(|reply| !doomed.contains(&reply.record.id));
+        state.drop_orphaned_edits();
+        state.posts.retain(|post| !purged.contains(&post.record.id));
+        state.drop_orphaned_bookmarks();
+        Ok(purged.len() as u64)
+    }
+
+    async fn add_like(&self, post_id: &str, author: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        if state
+            .likes
+            .iter()
+            .any(|like| like.parent_id == post_id && like.author == author)
+        {
+            return Ok(());
+        }
+        state.likes.push(LikeRow {
+            parent_id: post_id.to_string(),
+            author: author.to_string(),
+        });
+        state.adjust_counts(post_id, 1, 0);
+        Ok(())
+    }
+
+    async fn remove_like(&self, post_id: &str, author: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        let count = state.likes.len();
+        state
+            .likes
+            .retain(|like| !(like.parent_id == post_id && like.author == author));
+        let removed = (count - state.likes.len()) as i32;
+        state.adjust_counts(post_id, -removed, 0);
+        Ok(())
+    }
+
+    async fn repair_counts(&self) -> DbResult<u64> {
+        Ok(self.state()?.recount())
+    }
+
+    async fn feed(&self, feed: &PostFeed, page: &Page) -> DbResult<Vec<ExpressionPost>> {
+        let state = self.state()?;
+        let posts = state
+            .posts
+            .iter()
+            .filter(|post| post.deleted_at.is_none())
+            .filter(|post| match feed {
+                PostFeed::Recent => post.record.circle_id.is_none(),
+                PostFeed::Trending { since } => {
+                    post.record.circle_id.is_none() && post.record.created_at > *since
+                }
+                PostFeed::ByAuthor(author) => {
+                    post.record.circle_id.is_none() && post.record.author == *author
+                }
+                PostFeed::ByTag(topic) => {
+                    post.record.circle_id.is_none() && post.record.tags.contains(topic)
+                }
+                PostFeed::CircleRecent(circle) => post.record.circle_id.as_ref() == Some(circle),
+                PostFeed::CircleTrending { circle, since } => {
+                    post.record.circle_id.as_ref() == Some(circle)
+                        && post.record.created_at > *since
+                }
+                PostFeed::Home { viewer, since } => {
+                    post.record.circle_id.is_none()
+                        && post.record.created_at > *since
+                        && (state.follows.iter().any(|follow| {
+                            follow.follower_id == *viewer
+                                && follow.followee_id == post.record.author
+                        }) || state.topic_follows.iter().any(|follow| {
+                            follow.user_id == *viewer && post.record.tags.contains(&follow.topic)
+                        }))
+                }
+            })
+            .map(|post| state.post(post))
+            .collect();
+        Ok(page.apply(posts, |post| post.page_cursor(feed), true))
+    }
+
+    async fn counts_since(&self, since: DateTime<Utc>) -> DbResult<Vec<PostCounts>> {
+        Ok(self
+            .state()?
+            .posts
+            .iter()
+            .filter(|post| post.record.created_at > since && post.deleted_at.is_none())
+            .map(|post| PostCounts {
+                id: post.record.id.clone(),
+                likes: post.like_count,
+                replies: post.reply_count,
+                created_at: post.record.created_at,
+                hot_score: post.hot_score,
+            })
+            .collect())
+    }
+
+    async fn search(&self, search: &PostSearch) -> DbResult<Vec<SearchHit>> {
+        let state = self.state()?;
+        // Number of times the terms occur, or zero unless all of them do
+        let relevance = |text: &str| {
+            let text = text.to_lowercase();
+            let counts: Vec<usize> = search
+                .terms
+                .iter()
+                .map(|term| text.matches(term.as_str()).count())
+                .collect();
+            if counts.contains(&0) {
+                0.0
+            } else {
+                counts.iter().sum::<usize>() as f64
+            }
+        };
+
+        let mut hits: Vec<(&str, SearchHit)> = state
+            .posts
+            .iter()
+            .filter(|post| post.deleted_at.is_none() && post.record.circle_id.is_none())
+            .filter(|post| {
+                let record = &post.record;
+                search
+                    .author
+                    .as_ref()
+                    .is_none_or(|author| &record.author == author)
+                    && search
+                        .created_after
+                        .is_none_or(|after| record.created_at >= after)
+                    && search
+                        .created_before
+                        .is_none_or(|before| record.created_at < before)
+                    && search
+                        .content_type
+                        .as_ref()
+                        .is_none_or(|kind| &record.content_type == kind)
+            })
+            .filter_map(|post| {
+                let record = &post.record;
+                let text = format!(
+                    "{} {} {}",
+                    record.title,
+                    record.subtitle.as_deref().unwrap_or_default(),
+                    record.content_value
+                );
+                let best_reply = state
+                    .replies
+                    .iter()
+                    .filter(|reply| reply.deleted_at.is_none())
+                    .filter(|reply| state.post_of(reply) == record.id)
+                    .map(|reply| (relevance(&reply.record.content) * 0.5, reply))
+                    .max_by(|a, b| a.0.total_cmp(&b.0));
+
+                let (score, reply) = match best_reply {
+                    Some((score, reply)) if score > relevance(&text) => {
+                        (score, Some(reply.record.content.clone()))
+                    }
+                    _ => (relevance(&text), None),
+                };
+                let hit = SearchHit {
+                    post: state.post(post),
+                    score,
+                    reply,
+                };
+                (score > 0.0).then_some((record.id.as_str(), hit))
+            })
+            .collect();
+
+        hits.sort_by(|(a_id, a), (b_id, b)| b.score.total_cmp(&a.score).then(b_id.cmp(a_id)));
+        Ok(hits
+            .into_iter()
+            .skip(search.offset as usize)
+            .take(usize::from(search.limit))
+            .map(|(_, hit)| hit)
+            .collect())
+    }
+
+    async fn set_hot_scores(&self, scores: &[(String, f64)]) -> DbResult<()> {
+        let mut state = self.state()?;
+        for (id, score) in scores {
+            if let Some(post) = state.posts.iter_mut().find(|post| &post.record.id == id) {
+                post.hot_score = *score;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReplyRepo for MemoryStore {
+    async fn insert(&self, reply: &NewReplyRecord) -> DbResult<()> {
+        let mut state = self.state()?;
+        state.replies.push(ReplyRow {
+            record: reply.clone(),
+            last_modified: reply.created_at,
+            deleted_at: None,
+            deleted_by: None,
+            edited_at: None,
+        });
+        state.adjust_counts(&reply.parent, 0, 1);
+        Ok(())
+    }
+
+    async fn get_thread(&self, parent_id: &str, page: ThreadPage) -> DbResult<Vec<Reply>> {
+        let state = self.state()?;
+        let children = |parent: &str| {
+            let mut children: Vec<&ReplyRow> = state
+                .replies
+                .iter()
+                .filter(|reply| reply.record.parent == parent)
+                .collect();
+            children.sort_by(|a, b| {
+                (a.record.created_at, &a.record.id).cmp(&(b.record.created_at, &b.record.id))
+            });
+            children
+        };
+
+        let mut level = page.page.apply(
+            children(parent_id),
+            |row| Cursor {
+                key: SortKey::Time(row.record.created_at),
+                id: row.record.id.clone(),
+            },
+            false,
+        );
+        let mut thread = vec![];
+        for depth in 1..=page.max_depth {
+            let mut next = vec![];
+            for row in level {
+                let replies = children(&row.record.id);
+                if let Some(reply) = state.reply(row) {
+                    thread.push(reply.with_thread(i32::from(depth), replies.len() as i32));
+                }
+                next.extend(replies.into_iter().take(usize::from(page.width)));
+            }
+            level = next;
+        }
+        Ok(thread)
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Reply>> {
+        let state = self.state()?;
+        let Some(row) = state.replies.iter().find(|reply| reply.record.id == id) else {
+            return Ok(None);
+        };
+        let replies = state
+            .replies
+            .iter()
+            .filter(|reply| reply.record.parent == id)
+            .count();
+        Ok(state
+            .reply(row)
+            .map(|reply| reply.with_thread(1, replies as i32)))
+    }
+
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>> {
+        Ok(self
+            .state()?
+            .replies
+            .iter()
+            .find(|reply| reply.record.id == id && reply.deleted_at.is_none())
+            .map(|reply| reply.record.author.clone()))
+    }
+
+    async fn post_of(&self, id: &str) -> DbResult<Option<String>> {
+        let state = self.state()?;
+        let parent_of = |id: &str| {
+            state
+                .replies
+                .iter()
+                .find(|reply| reply.record.id == id)
+                .map(|reply| reply.record.parent.clone())
+        };
+        let Some(mut parent) = parent_of(id) else {
+            return Ok(None);
+        };
+        while let Some(grandparent) = parent_of(&parent) {
+            parent = grandparent;
+        }
+        Ok(Some(parent))
+    }
+
+    async fn update_content(
+        &self,
+        id: &str,
+        content: &str,
+        edited_by: &str,
+        at: DateTime<Utc>,
+    ) -> DbResult<()> {
+        let mut state = self.state()?;
+        let Some(reply) = state
+            .replies
+            .iter_mut()
+            .find(|reply| reply.record.id == id && reply.deleted_at.is_none())
+        else {
+            return Err(DbError::NotFound);
+        };
+        let previous = std::mem::replace(&mut reply.record.content, content.to_string());
+        reply.edited_at = Some(at);
+        reply.last_modified = at;
+        state.reply_edits.push(ReplyEditRow {
+            reply_id: id.to_string(),
+            edit: ReplyEdit {
+                content: previous,
+                edited_by: edited_by.to_string(),
+                replaced_at: at,
+            },
+        });
+        Ok(())
+    }
+
+    async fn edits(&self, id: &str) -> DbResult<Vec<ReplyEdit>> {
+        Ok(self
+            .state()?
+            .reply_edits
+            .iter()
+            .filter(|edit| edit.reply_id == id)
+            .map(|edit| edit.edit.clone())
+            .collect())
+    }
+
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(reply) = state
+            .replies
+            .iter_mut()
+            .find(|reply| reply.record.id == id && reply.deleted_at.is_none())
+        {
+            reply.deleted_at = Some(at);
+            reply.deleted_by = Some(deleted_by.to_string());
+            let parent = reply.record.parent.clone();
+            state.adjust_counts(&parent, 0, -1);
+        }
+        Ok(())
+    }
+
+    async fn restore(&self, id: &str) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(reply) = state
+            .replies
+            .iter_mut()
+            .find(|reply| reply.record.id == id && reply.deleted_at.is_some())
+        {
+            reply.deleted_at = None;
+            reply.deleted_by = None;
+            let parent = reply.record.parent.clone();
+            state.adjust_counts(&parent, 0, 1);
+        }
+        Ok(())
+    }
+
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>> {
+        Ok(self
+            .state()?
+            .replies
+            .iter()
+            .find(|reply| reply.record.id == id)
+            .and_then(|reply| deletion(&reply.record.author, &reply.deleted_by, reply.deleted_at)))
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        let mut state = self.state()?;
+        let parents: Vec<String> = state
+            .replies
+            .iter()
+            .map(|reply| reply.record.parent.clone())
+            .collect();
+        let count = state.replies.len();
+        // Tombstones with replies of their own are kept
+        state.replies.retain(|reply| {
+            reply.deleted_at.is_none_or(|at| at >= before) || parents.contains(&reply.record.id)
+        });
+        state.drop_orphaned_edits();
+        state.drop_orphaned_bookmarks();
+        Ok((count - state.replies.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl TopicRepo for MemoryStore {
+    async fn list(&self, prefix: &str, limit: u16) -> DbResult<Vec<Topic>> {
+        let state = self.state()?;
+        let mut topics: Vec<Topic> = state
+            .topics
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| state.topic(name))
+            .collect();
+        topics.sort_by(|a, b| {
+            b.post_count
+                .cmp(&a.post_count)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        topics.truncate(usize::from(limit));
+        Ok(topics)
+    }
+
+    async fn existing(&self, names: &[String]) -> DbResult<Vec<String>> {
+        Ok(self
+            .state()?
+            .topics
+            .iter()
+            .filter(|name| names.contains(name))
+            .cloned()
+            .collect())
+    }
+
+    async fn insert(&self, name: &str, _created_by: &str, _at: DateTime<Utc>) -> DbResult<()> {
+        self.state()?.topics.push(name.to_string());
+        Ok(())
+    }
+
+    async fn follow(&self, user_id: &str, topic: &str, _at: DateTime<Utc>) -> DbResult<()> {
+        let mut state = self.state()?;
+        if !state
+            .topic_follows
+            .iter()
+            .any(|follow| follow.user_id == user_id && follow.topic == topic)
+        {
+            state.topic_follows.push(TopicFollowRow {
+                user_id: user_id.to_string(),
+                topic: topic.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn unfollow(&self, user_id: &str, topic: &str) -> DbResult<()> {
+        self.state()?
+            .topic_follows
+            .retain(|follow| !(follow.user_id == user_id && follow.topic == topic));
+        Ok(())
+    }
+
+    async fn followed(&self, user_id: &str) -> DbResult<Vec<Topic>> {
+        let state = self.state()?;
+        let mut topics: Vec<Topic> = state
+            .topic_follows
+            .iter()
+            .filter(|follow| follow.user_id == user_id)
+            .map(|follow| state.topic(&follow.topic))
+            .collect();
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(topics)
+    }
+}
+
+#[async_trait]
+impl CircleRepo for MemoryStore {
+    async fn insert(&self, circle: &NewCircleRecord) -> DbResult<()> {
+        let mut state = self.state()?;
+        state.circles.push(Circle {
+            id: circle.id.clone(),
+            name: circle.name.clone(),
+            description: circle.description.clone(),
+            rules: circle.rules.clone(),
+            visibility: circle.visibility,
+            member_count: 0,
+            created_by: circle.created_by.clone(),
+            created_at: circle.created_at,
+        });
+        state.circle_members.push(CircleMemberRow {
+            circle_id: circle.id.clone(),
+            user_id: circle.created_by.clone(),
+            role: CircleRole::Owner,
+            status: CircleMemberStatus::Active,
+            joined_at: circle.created_at,
+        });
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Circle>> {
+        let state = self.state()?;
+        Ok(state
+            .circles
+            .iter()
+            .find(|circle| circle.id == id)
+            .map(|circle| state.circle(circle)))
+    }
+
+    async fn name_taken(&self, name: &str) -> DbResult<bool> {
+        Ok(self
+            .state()?
+            .circles
+            .iter()
+            .any(|circle| circle.name.to_lowercase() == name.to_lowercase()))
+    }
+
+    async fn list(&self, limit: u16, offset: u32) -> DbResult<Vec<Circle>> {
+        let state = self.state()?;
+        let mut circles: Vec<Circle> = state
+            .circles
+            .iter()
+            .map(|circle| state.circle(circle))
+            .collect();
+        circles.sort_by(|a, b| {
+            b.member_count
+                .cmp(&a.member_count)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(circles
+            .into_iter()
+            .skip(offset as usize)
+            .take(usize::from(limit))
+            .collect())
+    }
+
+    async fn update(&self, circle: &Circle) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(stored) = state.circles.iter_mut().find(|c| c.id == circle.id) {
+            stored.description = circle.description.clone();
+            stored.rules = circle.rules.clone();
+            stored.visibility = circle.visibility;
+        }
+        Ok(())
+    }
+
+    async fn membership(&self, circle_id: &str, user_id: &str) -> DbResult<Option<CircleMember>> {
+        let state = self.state()?;
+        Ok(state
+            .circle_members
+            .iter()
+            .find(|member| member.circle_id == circle_id && member.user_id == user_id)
+            .and_then(|member| state.circle_member(member)))
+    }
+
+    async fn add_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+        at: DateTime<Utc>,
+    ) -> DbResult<()> {
+        self.state()?.circle_members.push(CircleMemberRow {
+            circle_id: circle_id.to_string(),
+            user_id: user_id.to_string(),
+            role,
+            status,
+            joined_at: at,
+        });
+        Ok(())
+    }
+
+    async fn set_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+    ) -> DbResult<()> {
+        let mut state = self.state()?;
+        if let Some(member) = state
+            .circle_members
+            .iter_mut()
+            .find(|member| member.circle_id == circle_id && member.user_id == user_id)
+        {
+            member.role = role;
+            member.status = status;
+        }
+        Ok(())
+    }
+
+    async fn remove_member(&self, circle_id: &str, user_id: &str) -> DbResult<()> {
+        self.state()?
+            .circle_members
+            .retain(|member| !(member.circle_id == circle_id && member.user_id == user_id));
+        Ok(())
+    }
+
+    async fn members(
+        &self,
+        circle_id: &str,
+        status: CircleMemberStatus,
+    ) -> DbResult<Vec<CircleMember>> {
+        let state = self.state()?;
+        let mut members: Vec<&CircleMemberRow> = state
+            .circle_members
+            .iter()
+            .filter(|member| member.circle_id == circle_id && member.status == status)
+            .collect();
+        let rank = |role: CircleRole| match role {
+            CircleRole::Owner => 0,
+            CircleRole::Moderator => 1,
+            CircleRole::Member => 2,
+        };
+        members.sort_by(|a, b| {
+            rank(a.role)
+                .cmp(&rank(b.role))
+                .then(a.joined_at.cmp(&b.joined_at))
+                .then_with(|| a.user_id.cmp(&b.user_id))
+        });
+        Ok(members
+            .into_iter()
+            .filter_map(|member| state.circle_member(member))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl BookmarkRepo for MemoryStore {
+    async fn insert(&self, bookmark: &BookmarkRecord) -> DbResult<()> {
+        self.state()?.bookmarks.push(bookmark.clone());
+        Ok(())
+    }
+
+    async fn find(&self, user_id: &str, target_id: &str) -> DbResult<Option<BookmarkRecord>> {
+        Ok(self
+            .state()?
+            .bookmarks
+            .iter()
+            .find(|bookmark| {
+                bookmark.user_id == user_id
+                    && (bookmark.post_id.as_deref() == Some(target_id)
+                        || bookmark.reply_id.as_deref() == Some(target_id))
+            })
+            .cloned())
+    }
+
+    async fn set_collection(&self, id: &str, collection: Option<&str>) -> DbResult<()> {
+        if let Some(bookmark) = self
+            .state()?
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.id == id)
+        {
+            bookmark.collection = collection.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<()> {
+        self.state()?.bookmarks.retain(|bookmark| bookmark.id != id);
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        user_id: &str,
+        collection: Option<&str>,
+        page: &Page,
+    ) -> DbResult<Vec<BookmarkRecord>> {
+        let state = self.state()?;
+        let bookmarks = state
+            .bookmarks
+            .iter()
+            .filter(|bookmark| {
+                bookmark.user_id == user_id
+                    && collection.is_none_or(|name| bookmark.collection.as_deref() == Some(name))
+                    && state.bookmark_is_live(bookmark)
+            })
+            .cloned()
+            .collect();
+        Ok(page.apply(
+            bookmarks,
+            |bookmark: &BookmarkRecord| Cursor {
+                key: SortKey::Time(bookmark.created_at),
+                id: bookmark.id.clone(),
+            },
+            true,
+        ))
+    }
+
+    async fn collections(&self, user_id: &str) -> DbResult<Vec<BookmarkCollection>> {
+        let state = self.state()?;
+        let mut collections: Vec<BookmarkCollection> = vec![];
+        for bookmark in state
+            .bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.user_id == user_id && state.bookmark_is_live(bookmark))
+        {
+            let Some(name) = &bookmark.collection else {
+                continue;
+            };
+            match collections
+                .iter_mut()
+                .find(|collection| &collection.name == name)
+            {
+                Some(collection) => collection.bookmark_count += 1,
+                None => collections.push(BookmarkCollection {
+                    name: name.clone(),
+                    bookmark_count: 1,
+                }),
+            }
+        }
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(collections)
+    }
+
+    async fn bookmarked_posts(&self, user_id: &str, post_ids: &[String]) -> DbResult<Vec<String>> {
+        let state = self.state()?;
+        Ok(post_ids
+            .iter()
+            .filter(|post_id| {
+                state.bookmarks.iter().any(|bookmark| {
+                    bookmark.user_id == user_id && bookmark.post_id.as_ref() == Some(*post_id)
+                })
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DraftRepo for MemoryStore {
+    async fn insert(&self, draft: &PostDraft) -> DbResult<()> {
+        self.state()?.drafts.push(draft.clone());
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<PostDraft>> {
+        Ok(self
+            .state()?
+            .drafts
+            .iter()
+            .find(|draft| draft.id == id)
+            .cloned())
+    }
+
+    async fn update(&self, draft: &PostDraft) -> DbResult<()> {
+        if let Some(saved) = self
+            .state()?
+            .drafts
+            .iter_mut()
+            .find(|saved| saved.id == draft.id)
+        {
+            *saved = PostDraft {
+                publish_at: saved.publish_at,
+                ..draft.clone()
+            };
+        }
+        Ok(())
+    }
+
+    async fn schedule(&self, id: &str, publish_at: Option<DateTime<Utc>>) -> DbResult<()> {
+        if let Some(draft) = self.state()?.drafts.iter_mut().find(|draft| draft.id == id) {
+            draft.publish_at = publish_at;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<bool> {
+        let mut state = self.state()?;
+        let count = state.drafts.len();
+        state.drafts.retain(|draft| draft.id != id);
+        Ok(state.drafts.len() < count)
+    }
+
+    async fn list(&self, author: &str, page: &Page) -> DbResult<Vec<PostDraft>> {
+        let drafts = self
+            .state()?
+            .drafts
+            .iter()
+            .filter(|draft| draft.author == author)
+            .cloned()
+            .collect();
+        Ok(page.apply(drafts, PostDraft::page_cursor, true))
+    }
+
+    async fn due(&self, at: DateTime<Utc>, limit: u16) -> DbResult<Vec<PostDraft>> {
+        let mut drafts: Vec<PostDraft> = self
+            .state()?
+            .drafts
+            .iter()
+            .filter(|draft| draft.publish_at.is_some_and(|publish_at| publish_at <= at))
+            .cloned()
+            .collect();
+        drafts.sort_by(|a, b| {
+            a.publish_at
+                .cmp(&b.publish_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        drafts.truncate(usize::from(limit));
+        Ok(drafts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::DbController;
+
+    async fn add_profile(db: &DbController, id: &str, outcome: Result<(), String>) {
+        let _ = db
+            .unit_of_work(|db| {
+                let outcome = outcome.clone();
+                async move {
+                    db.profiles.insert(id, id, "").await.unwrap();
+                    outcome
+                }
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn failed_units_of_work_leave_no_writes() {
+        let db = DbController::in_memory();
+
+        add_profile(&db, "kept", Ok(())).await;
+        add_profile(&db, "dropped", Err("Invalid".to_string())).await;
+
+        assert!(db.profiles.get_by_id("kept").await.unwrap().is_some());
+        assert!(db.profiles.get_by_id("dropped").await.unwrap().is_none());
+    }
+}
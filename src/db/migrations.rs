@@ -0,0 +1,126 @@
+use std::fmt;
+
+use sqlx::{
+    migrate::{AppliedMigration, Migrate, MigrateError, Migrator},
+    Database, Pool,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded file no longer matches what was run.
+    Modified,
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+impl fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+        })
+    }
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04} {:<40} {}",
+            self.version, self.description, self.state
+        )
+    }
+}
+
+pub async fn run<DB>(migrator: &Migrator, pool: &Pool<DB>) -> Result<(), MigrateError>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    migrator.run(pool).await
+}
+
+/// Reverts the most recently applied migration, if any. Returns its version.
+pub async fn revert_latest<DB>(
+    migrator: &Migrator,
+    pool: &Pool<DB>,
+) -> Result<Option<i64>, MigrateError>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let mut applied: Vec<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    drop(conn);
+
+    applied.sort_unstable();
+    let Some(latest) = applied.pop() else {
+        return Ok(None);
+    };
+
+    migrator
+        .undo(pool, applied.last().copied().unwrap_or(0))
+        .await?;
+    Ok(Some(latest))
+}
+
+/// Migrations recorded in the database. Callers check that the migrations
+/// table exists first, since how to ask differs between backends.
+pub async fn applied<DB>(pool: &Pool<DB>) -> Result<Vec<AppliedMigration>, MigrateError>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.list_applied_migrations().await
+}
+
+/// Compares the embedded migrations against those recorded in the database.
+/// A database that has never been migrated reports every migration pending.
+pub fn status(migrator: &Migrator, applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| {
+            let state = match applied.iter().find(|a| a.version == migration.version) {
+                Some(a) if a.checksum != migration.checksum => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+                None => MigrationState::Pending,
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect()
+}
+
+/// Returns an error describing the first migration that is not applied as
+/// embedded in this binary.
+pub fn ensure_current(statuses: &[MigrationStatus]) -> Result<(), String> {
+    match statuses
+        .iter()
+        .find(|status| status.state != MigrationState::Applied)
+    {
+        Some(status) => Err(format!(
+            "Migration {} ({}) is {}",
+            status.version, status.description, status.state
+        )),
+        None => Ok(()),
+    }
+}
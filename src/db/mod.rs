@@ -1,20 +1,470 @@
-use sqlx::{MySql, MySqlPool, Pool};
-use std::error::Error;
+use chrono::{DateTime, SubsecRound, Utc};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    error::Error,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-#[derive(Debug)]
+use crate::{
+    community::{CommunityCache, CommunityEvent},
+    config::env_or,
+};
+
+pub mod memory;
+pub mod migrations;
+pub mod mysql;
+mod page;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+mod repo;
+pub mod routing;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod unit;
+
+use memory::MemoryStore;
+use migrations::MigrationStatus;
+use mysql::MySqlStore;
+pub use page::{Cursor, Page, SortKey};
+#[cfg(feature = "postgres")]
+use postgres::PgStore;
+pub use repo::{
+    AccountOperation, AccountOperationKind, AccountOperationStatus, AuthRecord, AuthRepo, Backend,
+    BookmarkRecord, BookmarkRepo, CircleRepo, CommunityTransaction, DbError, DbResult,
+    DeletionRecord, DraftRepo, NewCircleRecord, NewPostRecord, NewReplyRecord, PostCounts,
+    PostFeed, PostRepo, PostSearch, ProfileRepo, ReplyRepo, SearchHit, ThreadPage, TopicRepo,
+};
+use routing::RecentWriters;
+#[cfg(feature = "sqlite")]
+use sqlite::SqliteStore;
+
+/// Entry point to persistence. Models only talk to the repositories; which
+/// implementation backs them is decided when the controller is built.
 pub struct DbController {
-    pub auth_pool: Pool<MySql>,
-    pub community_pool: Pool<MySql>,
+    pub auths: Arc<dyn AuthRepo>,
+    pub profiles: Arc<dyn ProfileRepo>,
+    pub posts: Arc<dyn PostRepo>,
+    pub replies: Arc<dyn ReplyRepo>,
+    pub topics: Arc<dyn TopicRepo>,
+    pub circles: Arc<dyn CircleRepo>,
+    pub bookmarks: Arc<dyn BookmarkRepo>,
+    pub drafts: Arc<dyn DraftRepo>,
+    /// Cached community reads. Models invalidate it through
+    /// [`DbController::invalidate`] whenever they write.
+    pub cache: Arc<CommunityCache>,
+    backend: Arc<dyn Backend>,
+    recent_writers: Arc<RecentWriters>,
+    max_replica_lag: Duration,
+    /// Set on controllers handed to the work of a unit of work.
+    unit: Option<Arc<ActiveUnit>>,
+}
+
+/// Times a unit of work is attempted before a deadlock or lock timeout is
+/// given back to the caller.
+const MAX_UNIT_ATTEMPTS: u32 = 3;
+
+/// Transaction of a running unit of work, along with the cache invalidations
+/// held back until it commits.
+struct ActiveUnit {
+    tx: Arc<dyn CommunityTransaction>,
+    events: Mutex<Vec<CommunityEvent>>,
+}
+
+/// Current time at the precision the databases store timestamps with.
+pub fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0)
+}
+
+/// Rules of a circle from the lines they are stored as.
+fn split_rules(rules: &str) -> Vec<String> {
+    rules.lines().map(str::to_string).collect()
+}
+
+/// Tags of a post from the comma separated list the stores aggregate them
+/// into. Topic names never contain commas.
+fn split_tags(tags: Option<String>) -> Vec<String> {
+    tags.map(|tags| tags.split(',').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Follower and following counts per profile from `(profile, side, count)`
+/// rows, where `side` is `followers` or `following`.
+fn tally_follows(
+    rows: impl IntoIterator<Item = (String, String, i64)>,
+) -> HashMap<String, (i64, i64)> {
+    let mut counts: HashMap<String, (i64, i64)> = HashMap::new();
+    for (id, side, count) in rows {
+        let entry = counts.entry(id).or_default();
+        if side == "followers" {
+            entry.0 = count;
+        } else {
+            entry.1 = count;
+        }
+    }
+    counts
+}
+
+/// Tags as the comma separated list drafts store them in, `None` when there
+/// are none.
+fn join_tags(tags: &[String]) -> Option<String> {
+    (!tags.is_empty()).then(|| tags.join(","))
 }
 
 impl DbController {
+    /// Connects to both databases. When `AUTO_MIGRATE` is `true`, pending
+    /// migrations are applied before the controller is returned.
     pub async fn init() -> Result<Self, Box<dyn Error>> {
+        let db = Self::connect().await?;
+
+        if env_or("AUTO_MIGRATE", false) {
+            db.migrate_up().await?;
+        }
+
+        Ok(db)
+    }
+
+    /// Connects to both databases without touching their schema. The backend
+    /// is picked from the scheme of `AUTH_DB_URL` and `COMMUNITY_DB_URL`,
+    /// which must agree. `AUTH_DB_REPLICA_URL` and `COMMUNITY_DB_REPLICA_URL`
+    /// optionally add a read replica for each database.
+    pub async fn connect() -> Result<Self, Box<dyn Error>> {
         let auth_db_url: String = dotenv::var("AUTH_DB_URL")?;
         let community_db_url: String = dotenv::var("COMMUNITY_DB_URL")?;
+        let auth_replica_url = replica_url("AUTH_DB_REPLICA_URL");
+        let community_replica_url = replica_url("COMMUNITY_DB_REPLICA_URL");
+
+        let scheme = url_scheme(&auth_db_url);
+        if scheme != url_scheme(&community_db_url) {
+            return Err(
+                "AUTH_DB_URL and COMMUNITY_DB_URL must use the same database backend".into(),
+            );
+        }
+        if [&auth_replica_url, &community_replica_url]
+            .into_iter()
+            .flatten()
+            .any(|url| url_scheme(url) != scheme)
+        {
+            return Err("Replica URLs must use the same database backend as their primary".into());
+        }
+
+        match scheme {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => {
+                if auth_replica_url.is_some() || community_replica_url.is_some() {
+                    return Err("SQLite does not support read replicas".into());
+                }
+                Ok(Self::from_store(Arc::new(
+                    SqliteStore::connect(&auth_db_url, &community_db_url).await?,
+                )))
+            }
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err("SQLite support requires building with the `sqlite` feature".into()),
+            #[cfg(feature = "postgres")]
+            "postgres" => Ok(Self::from_store(Arc::new(
+                PgStore::connect(
+                    &auth_db_url,
+                    &community_db_url,
+                    auth_replica_url.as_deref(),
+                    community_replica_url.as_deref(),
+                )
+                .await?,
+            ))),
+            #[cfg(not(feature = "postgres"))]
+            "postgres" => {
+                Err("Postgres support requires building with the `postgres` feature".into())
+            }
+            _ => Ok(Self::from_store(Arc::new(
+                MySqlStore::connect(
+                    &auth_db_url,
+                    &community_db_url,
+                    auth_replica_url.as_deref(),
+                    community_replica_url.as_deref(),
+                )
+                .await?,
+            ))),
+        }
+    }
+
+    /// Builds a controller backed by process memory, with no database at all.
+    pub fn in_memory() -> Self {
+        Self::from_store(Arc::new(MemoryStore::new()))
+    }
+
+    /// Builds a controller backed by fresh in-memory SQLite databases with
+    /// every migration applied, for running model tests against real SQL.
+    #[cfg(all(test, feature = "sqlite"))]
+    pub async fn sqlite_in_memory() -> Self {
+        let store = SqliteStore::connect("sqlite::memory:", "sqlite::memory:")
+            .await
+            .expect("Error opening in-memory SQLite databases");
+        Self::from_store(Arc::new(store))
+    }
+
+    fn from_store<S>(store: Arc<S>) -> Self
+    where
+        S: Backend
+            + AuthRepo
+            + ProfileRepo
+            + PostRepo
+            + ReplyRepo
+            + TopicRepo
+            + CircleRepo
+            + BookmarkRepo
+            + DraftRepo
+            + 'static,
+    {
+        let max_replica_lag = routing::max_replica_lag();
+        Self {
+            auths: store.clone(),
+            profiles: store.clone(),
+            posts: store.clone(),
+            replies: store.clone(),
+            topics: store.clone(),
+            circles: store.clone(),
+            bookmarks: store.clone(),
+            drafts: store.clone(),
+            cache: Arc::new(CommunityCache::from_env(
+                store.has_replicas().then_some(max_replica_lag),
+            )),
+            backend: store,
+            recent_writers: Arc::default(),
+            max_replica_lag,
+            unit: None,
+        }
+    }
+
+    /// Runs `work` against community repositories that share one transaction,
+    /// committing when it returns `Ok` and rolling back otherwise. Attempts
+    /// that fail on a deadlock or lock timeout are retried from the start, so
+    /// `work` may run more than once. Called from inside another unit of
+    /// work, `work` simply joins the outer one.
+    pub async fn unit_of_work<T, F, Fut>(&self, work: F) -> Result<T, String>
+    where
+        F: Fn(Arc<DbController>) -> Fut,
+        Fut: Future<Output = Result<T, String>>,
+    {
+        if let Some(unit) = &self.unit {
+            return work(Arc::new(self.bound_to(unit.clone()))).await;
+        }
+
+        let mut attempt = 1;
+        loop {
+            let tx = match self.backend.begin_community().await {
+                Ok(tx) => tx,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return Err("Unable to start a database transaction".to_string());
+                }
+            };
+            let unit = Arc::new(ActiveUnit {
+                tx: tx.clone(),
+                events: Mutex::default(),
+            });
+
+            let ((result, finished), conflict) = unit::attempt(async {
+                let result = work(Arc::new(self.bound_to(unit.clone()))).await;
+                let finished = match &result {
+                    Ok(_) => tx.commit().await,
+                    Err(_) => tx.rollback().await,
+                };
+                (result, finished)
+            })
+            .await;
+            if let Err(err) = &finished {
+                eprintln!("{err}");
+            }
+
+            // A conflict noted in a lookup that swallowed its error does not
+            // undo work that went on to commit, so only failed attempts rerun
+            let failed = result.is_err() || finished.is_err();
+            if conflict && failed && attempt < MAX_UNIT_ATTEMPTS {
+                attempt += 1;
+                let backoff = rand::thread_rng().gen_range(10..50) * u64::from(attempt);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+                continue;
+            }
+            if result.is_ok() && finished.is_err() {
+                return Err("Unable to commit the database transaction".to_string());
+            }
+
+            if result.is_ok() {
+                let events = unit
+                    .events
+                    .lock()
+                    .map(|mut events| std::mem::take(&mut *events))
+                    .unwrap_or_default();
+                for event in events {
+                    self.cache.invalidate(event);
+                }
+            }
+            return result;
+        }
+    }
+
+    /// Controller whose community repositories go through the unit's
+    /// transaction. Its reads skip the cache, which may not hold the unit's
+    /// own writes yet.
+    fn bound_to(&self, unit: Arc<ActiveUnit>) -> Self {
+        Self {
+            auths: self.auths.clone(),
+            profiles: unit.tx.clone(),
+            posts: unit.tx.clone(),
+            replies: unit.tx.clone(),
+            topics: unit.tx.clone(),
+            circles: unit.tx.clone(),
+            bookmarks: unit.tx.clone(),
+            drafts: unit.tx.clone(),
+            cache: Arc::new(CommunityCache::disabled()),
+            backend: self.backend.clone(),
+            recent_writers: self.recent_writers.clone(),
+            max_replica_lag: self.max_replica_lag,
+            unit: Some(unit),
+        }
+    }
+
+    /// Drops cached reads the change made stale. Inside a unit of work this
+    /// waits until the unit commits, and is forgotten if it rolls back.
+    pub fn invalidate(&self, event: CommunityEvent) {
+        match &self.unit {
+            Some(unit) => {
+                if let Ok(mut events) = unit.events.lock() {
+                    events.push(event);
+                }
+            }
+            None => self.cache.invalidate(event),
+        }
+    }
+
+    pub fn has_replicas(&self) -> bool {
+        self.backend.has_replicas()
+    }
+
+    /// Measures replica lag and moves reads off replicas that fell behind.
+    pub async fn check_replicas(&self) {
+        self.backend.check_replicas(self.max_replica_lag).await;
+    }
+
+    /// Keeps the viewer's reads on the primaries for as long as a healthy
+    /// replica may lag, so they always see their own writes.
+    pub fn note_write(&self, viewer: &str) {
+        if self.has_replicas() {
+            self.recent_writers.pin(viewer, self.max_replica_lag);
+        }
+    }
+
+    pub fn reads_from_primary(&self, viewer: &str) -> bool {
+        self.recent_writers.is_pinned(viewer)
+    }
+
+    pub async fn close(&self) {
+        self.backend.close().await;
+    }
+
+    pub async fn ping_auth(&self) -> Result<(), String> {
+        self.backend.ping_auth().await
+    }
+
+    pub async fn ping_community(&self) -> Result<(), String> {
+        self.backend.ping_community().await
+    }
+
+    /// Verifies that every embedded migration has been applied to both databases.
+    pub async fn check_migrations(&self) -> Result<(), String> {
+        let (auth, community) = match self.backend.migration_status().await {
+            Ok(statuses) => statuses,
+            Err(err) => return Err(err.to_string()),
+        };
+        migrations::ensure_current(&auth)?;
+        migrations::ensure_current(&community)
+    }
+
+    pub async fn migrate_up(&self) -> Result<(), Box<dyn Error>> {
+        Ok(self.backend.migrate_up().await?)
+    }
+
+    /// Reverts the latest migration of each database. Returns the reverted
+    /// versions for the auth and community databases.
+    pub async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), Box<dyn Error>> {
+        Ok(self.backend.migrate_down().await?)
+    }
+
+    pub async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), Box<dyn Error>> {
+        Ok(self.backend.migration_status().await?)
+    }
+}
+
+fn replica_url(key: &str) -> Option<String> {
+    dotenv::var(key).ok().filter(|url| !url.is_empty())
+}
+
+fn url_scheme(url: &str) -> &str {
+    match url.split(':').next().unwrap_or_default() {
+        "postgresql" => "postgres",
+        scheme => scheme,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    /// Runs a unit of work that adds a profile named after its attempt,
+    /// noting a conflict on the first one. Returns how many attempts ran.
+    async fn conflicting_unit(db: &DbController, first_fails: bool) -> (Result<(), String>, u32) {
+        let attempts = AtomicU32::new(0);
+        let result = db
+            .unit_of_work(|db| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    let id = attempt.to_string();
+                    db.profiles.insert(&id, &id, "").await.unwrap();
+                    if attempt == 1 {
+                        unit::note_conflict();
+                        if first_fails {
+                            return Err("Deadlock".to_string());
+                        }
+                    }
+                    Ok(())
+                }
+            })
+            .await;
+        (result, attempts.load(Ordering::SeqCst))
+    }
+
+    async fn conflicting_attempts_are_rolled_back_and_retried(db: DbController) {
+        let (result, attempts) = conflicting_unit(&db, true).await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, 2);
+        assert!(db.profiles.get_by_id("1").await.unwrap().is_none());
+        assert!(db.profiles.get_by_id("2").await.unwrap().is_some());
+    }
+
+    async fn conflicts_in_committed_attempts_are_not_retried(db: DbController) {
+        let (result, attempts) = conflicting_unit(&db, false).await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, 1);
+        assert!(db.profiles.get_by_id("1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn memory_units_of_work_retry_conflicts() {
+        conflicting_attempts_are_rolled_back_and_retried(DbController::in_memory()).await;
+        conflicts_in_committed_attempts_are_not_retried(DbController::in_memory()).await;
+    }
 
-        Ok(Self {
-            auth_pool: MySqlPool::connect(&auth_db_url).await?,
-            community_pool: MySqlPool::connect(&community_db_url).await?,
-        })
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn sqlite_units_of_work_retry_conflicts() {
+        conflicting_attempts_are_rolled_back_and_retried(DbController::sqlite_in_memory().await)
+            .await;
+        conflicts_in_committed_attempts_are_not_retried(DbController::sqlite_in_memory().await)
+            .await;
     }
 }
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{mysql::MySqlRow, Connection, Row};
+
+use crate::{
+    community::{
+        Circle, CircleMember, CircleMemberStatus, CircleRole, CircleVisibility, UserProfile,
+    },
+    db::{split_rules, CircleRepo, DbResult, NewCircleRecord},
+};
+
+use super::MySqlStore;
+
+/// Loads circles with their number of active members. Expects conditions
+/// and ordering to be appended.
+const CIRCLE_QUERY: &str = r#"
+    SELECT
+        circle.id AS id,
+        circle.name AS name,
+        circle.description AS description,
+        circle.rules AS rules,
+        circle.visibility AS visibility,
+        circle.created_by AS created_by,
+        circle.created_at AS created_at,
+        (
+            SELECT COUNT(*) FROM circle_members AS member
+            WHERE member.circle_id = circle.id AND member.status = 'active'
+        ) AS member_count
+    FROM circles AS circle
+"#;
+
+/// Loads members with their profiles. Expects conditions and ordering to be
+/// appended.
+const MEMBER_QUERY: &str = r#"
+    SELECT
+        member.circle_id AS circle_id,
+        member.role AS role,
+        member.status AS status,
+        member.joined_at AS joined_at,
+        profile.id AS user_id,
+        profile.username AS username,
+        profile.avatar AS avatar
+    FROM circle_members AS member
+    JOIN user_profiles AS profile ON profile.id = member.user_id
+"#;
+
+fn circle_from_row(row: MySqlRow) -> Circle {
+    Circle {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        rules: split_rules(row.get("rules")),
+        visibility: CircleVisibility::parse(row.get("visibility")),
+        member_count: row.get("member_count"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn member_from_row(row: MySqlRow) -> CircleMember {
+    CircleMember {
+        circle_id: row.get("circle_id"),
+        user: UserProfile::new(
+            row.get("user_id"),
+            row.get("username"),
+            row.get("avatar"),
+            vec![],
+        ),
+        role: CircleRole::parse(row.get("role")),
+        status: CircleMemberStatus::parse(row.get("status")),
+        joined_at: row.get("joined_at"),
+    }
+}
+
+#[async_trait]
+impl CircleRepo for MySqlStore {
+    async fn insert(&self, circle: &NewCircleRecord) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO circles
+                (id, name, description, rules, visibility, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(&circle.id)
+        .bind(&circle.name)
+        .bind(&circle.description)
+        .bind(circle.rules.join("\n"))
+        .bind(circle.visibility.as_str())
+        .bind(&circle.created_by)
+        .bind(circle.created_at)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO circle_members (circle_id, user_id, role, status, joined_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(&circle.id)
+        .bind(&circle.created_by)
+        .bind(CircleRole::Owner.as_str())
+        .bind(CircleMemberStatus::Active.as_str())
+        .bind(circle.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Circle>> {
+        let query = format!("{CIRCLE_QUERY} WHERE circle.id = ?");
+        if self.community_unit.is_some() {
+            return Ok(sqlx::query(&query)
+                .bind(id)
+                .map(circle_from_row)
+                .fetch_optional(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| {
+                let query = &query;
+                async move {
+                    sqlx::query(query)
+                        .bind(id)
+                        .map(circle_from_row)
+                        .fetch_optional(&pool)
+                        .await
+                }
+            })
+            .await?)
+    }
+
+    async fn name_taken(&self, name: &str) -> DbResult<bool> {
+        Ok(
+            sqlx::query("SELECT id FROM circles WHERE LOWER(name) = LOWER(?)")
+                .bind(name)
+                .fetch_optional(&mut *self.community().await?)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn list(&self, limit: u16, offset: u32) -> DbResult<Vec<Circle>> {
+        let query =
+            format!("{CIRCLE_QUERY} ORDER BY member_count DESC, circle.name ASC LIMIT ? OFFSET ?");
+        if self.community_unit.is_some() {
+            return Ok(sqlx::query(&query)
+                .bind(i64::from(limit))
+                .bind(i64::from(offset))
+                .map(circle_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| {
+                let query = &query;
+                async move {
+                    sqlx::query(query)
+                        .bind(i64::from(limit))
+                        .bind(i64::from(offset))
+                        .map(circle_from_row)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?)
+    }
+
+    async fn update(&self, circle: &Circle) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE circles
+            SET description = ?, rules = ?, visibility = ?
+            WHERE id = ?
+        "#,
+        )
+        .bind(&circle.description)
+        .bind(circle.rules.join("\n"))
+        .bind(circle.visibility.as_str())
+        .bind(&circle.id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn membership(&self, circle_id: &str, user_id: &str) -> DbResult<Option<CircleMember>> {
+        Ok(sqlx::query(&format!(
+            "{MEMBER_QUERY} WHERE member.circle_id = ? AND member.user_id = ?"
+        ))
+        .bind(circle_id)
+        .bind(user_id)
+        .map(member_from_row)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn add_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+        at: DateTime<Utc>,
+    ) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO circle_members (circle_id, user_id, role, status, joined_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(circle_id)
+        .bind(user_id)
+        .bind(role.as_str())
+        .bind(status.as_str())
+        .bind(at)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+    ) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE circle_members
+            SET role = ?, status = ?
+            WHERE circle_id = ? AND user_id = ?
+        "#,
+        )
+        .bind(role.as_str())
+        .bind(status.as_str())
+        .bind(circle_id)
+        .bind(user_id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_member(&self, circle_id: &str, user_id: &str) -> DbResult<()> {
+        sqlx::query("DELETE FROM circle_members WHERE circle_id = ? AND user_id = ?")
+            .bind(circle_id)
+            .bind(user_id)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn members(
+        &self,
+        circle_id: &str,
+        status: CircleMemberStatus,
+    ) -> DbResult<Vec<CircleMember>> {
+        let query = format!(
+            r#"
+            {MEMBER_QUERY}
+            WHERE member.circle_id = ? AND member.status = ?
+            ORDER BY
+                CASE member.role WHEN 'owner' THEN 0 WHEN 'moderator' THEN 1 ELSE 2 END,
+                member.joined_at,
+                member.user_id
+        "#
+        );
+        if self.community_unit.is_some() {
+            return Ok(sqlx::query(&query)
+                .bind(circle_id)
+                .bind(status.as_str())
+                .map(member_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| {
+                let query = &query;
+                async move {
+                    sqlx::query(query)
+                        .bind(circle_id)
+                        .bind(status.as_str())
+                        .map(member_from_row)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?)
+    }
+}
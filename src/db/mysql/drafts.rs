@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{mysql::MySqlRow, Row};
+
+use crate::{
+    community::{ExpressionPostContent, PostDraft},
+    db::{join_tags, split_tags, DbResult, DraftRepo, Page},
+};
+
+use super::{bind_cursor, MySqlStore};
+
+/// Loads drafts. Expects conditions and ordering to be appended.
+const DRAFT_QUERY: &str = r#"
+    SELECT
+        id,
+        author,
+        title,
+        subtitle,
+        cover_image,
+        content_type,
+        content_value,
+        tags,
+        circle_id,
+        publish_at,
+        created_at,
+        last_modified
+    FROM post_drafts
+"#;
+
+fn draft_from_row(row: MySqlRow) -> PostDraft {
+    let content_type: Option<String> = row.get("content_type");
+    let content_value: Option<String> = row.get("content_value");
+    PostDraft {
+        id: row.get("id"),
+        author: row.get("author"),
+        title: row.get("title"),
+        subtitle: row.get("subtitle"),
+        cover_image: row.get("cover_image"),
+        content: content_type
+            .zip(content_value)
+            .map(|(kind, value)| ExpressionPostContent { kind, value }),
+        tags: split_tags(row.get("tags")),
+        circle_id: row.get("circle_id"),
+        publish_at: row.get("publish_at"),
+        created_at: row.get("created_at"),
+        last_modified: row.get("last_modified"),
+    }
+}
+
+#[async_trait]
+impl DraftRepo for MySqlStore {
+    async fn insert(&self, draft: &PostDraft) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO post_drafts (
+                id,
+                author,
+                title,
+                subtitle,
+                cover_image,
+                content_type,
+                content_value,
+                tags,
+                circle_id,
+                publish_at,
+                created_at,
+                last_modified
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(&draft.id)
+        .bind(&draft.author)
+        .bind(&draft.title)
+        .bind(&draft.subtitle)
+        .bind(&draft.cover_image)
+        .bind(draft.content.as_ref().map(|content| &content.kind))
+        .bind(draft.content.as_ref().map(|content| &content.value))
+        .bind(join_tags(&draft.tags))
+        .bind(&draft.circle_id)
+        .bind(draft.publish_at)
+        .bind(draft.created_at)
+        .bind(draft.last_modified)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<PostDraft>> {
+        Ok(sqlx::query(&format!("{DRAFT_QUERY} WHERE id = ?"))
+            .bind(id)
+            .map(draft_from_row)
+            .fetch_optional(&mut *self.community().await?)
+            .await?)
+    }
+
+    async fn update(&self, draft: &PostDraft) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE post_drafts
+            SET
+                title = ?,
+                subtitle = ?,
+                cover_image = ?,
+                content_type = ?,
+                content_value = ?,
+                tags = ?,
+                circle_id = ?,
+                last_modified = ?
+            WHERE id = ?
+        "#,
+        )
+        .bind(&draft.title)
+        .bind(&draft.subtitle)
+        .bind(&draft.cover_image)
+        .bind(draft.content.as_ref().map(|content| &content.kind))
+        .bind(draft.content.as_ref().map(|content| &content.value))
+        .bind(join_tags(&draft.tags))
+        .bind(&draft.circle_id)
+        .bind(draft.last_modified)
+        .bind(&draft.id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn schedule(&self, id: &str, publish_at: Option<DateTime<Utc>>) -> DbResult<()> {
+        sqlx::query("UPDATE post_drafts SET publish_at = ? WHERE id = ?")
+            .bind(publish_at)
+            .bind(id)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<bool> {
+        let deleted = sqlx::query("DELETE FROM post_drafts WHERE id = ?")
+            .bind(id)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn list(&self, author: &str, page: &Page) -> DbResult<Vec<PostDraft>> {
+        let mut placeholder = || "?".to_string();
+        let (keyset, order) = page.sql("last_modified", "id", true, &mut placeholder);
+        let limit = placeholder();
+        let query =
+            format!("{DRAFT_QUERY} WHERE author = ?{keyset} ORDER BY {order} LIMIT {limit}");
+
+        let drafts = if self.community_unit.is_some() {
+            let mut query = sqlx::query(&query).bind(author);
+            for cursor in page.cursors() {
+                query = bind_cursor(query, cursor);
+            }
+            query
+                .bind(i64::from(page.limit))
+                .map(draft_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?
+        } else {
+            self.community_replica
+                .read(&self.community_pool, |pool| {
+                    let query = &query;
+                    async move {
+                        let mut query = sqlx::query(query).bind(author);
+                        for cursor in page.cursors() {
+                            query = bind_cursor(query, cursor);
+                        }
+                        query
+                            .bind(i64::from(page.limit))
+                            .map(draft_from_row)
+                            .fetch_all(&pool)
+                            .await
+                    }
+                })
+                .await?
+        };
+        Ok(page.finish(drafts))
+    }
+
+    async fn due(&self, at: DateTime<Utc>, limit: u16) -> DbResult<Vec<PostDraft>> {
+        Ok(sqlx::query(&format!(
+            "{DRAFT_QUERY} WHERE publish_at <= ? ORDER BY publish_at, id LIMIT ?"
+        ))
+        .bind(at)
+        .bind(i64::from(limit))
+        .map(draft_from_row)
+        .fetch_all(&mut *self.community().await?)
+        .await?)
+    }
+}
@@ -0,0 +1,312 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::{
+    migrate::{MigrateError, Migrator},
+    mysql::{MySqlArguments, MySqlRow},
+    query::Query,
+    Executor, MySql, MySqlConnection, MySqlPool, Pool, Row,
+};
+
+use super::{
+    migrations::{self, MigrationStatus},
+    routing::ReadReplica,
+    unit::{Conn, UnitTransaction},
+    Backend, CommunityTransaction, Cursor, DbResult, DeletionRecord, SortKey,
+};
+
+mod auth;
+mod bookmarks;
+mod circles;
+mod drafts;
+mod posts;
+mod profiles;
+mod replies;
+mod topics;
+
+/// MySQL migrations for the auth database, embedded at compile time.
+pub static MYSQL_AUTH_MIGRATOR: Migrator = sqlx::migrate!("./migrations/mysql/auth");
+/// MySQL migrations for the community database, embedded at compile time.
+pub static MYSQL_COMMUNITY_MIGRATOR: Migrator = sqlx::migrate!("./migrations/mysql/community");
+
+/// Repository implementation backed by the auth and community MySQL databases.
+#[derive(Debug)]
+pub struct MySqlStore {
+    pub auth_pool: Pool<MySql>,
+    pub community_pool: Pool<MySql>,
+    pub auth_replica: ReadReplica<MySql>,
+    pub community_replica: ReadReplica<MySql>,
+    /// Open transaction when the store is bound to a unit of work.
+    pub community_unit: Option<UnitTransaction<MySql>>,
+}
+
+impl MySqlStore {
+    /// Connects to both primaries. Replicas connect lazily, so one that is
+    /// down at startup only sends reads to its primary.
+    pub async fn connect(
+        auth_db_url: &str,
+        community_db_url: &str,
+        auth_replica_url: Option<&str>,
+        community_replica_url: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            auth_pool: MySqlPool::connect(auth_db_url).await?,
+            community_pool: MySqlPool::connect(community_db_url).await?,
+            auth_replica: ReadReplica::new(
+                auth_replica_url.map(MySqlPool::connect_lazy).transpose()?,
+            ),
+            community_replica: ReadReplica::new(
+                community_replica_url
+                    .map(MySqlPool::connect_lazy)
+                    .transpose()?,
+            ),
+            community_unit: None,
+        })
+    }
+
+    /// Copy of the store bound to a new community transaction.
+    async fn begin_unit(&self) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            auth_pool: self.auth_pool.clone(),
+            community_pool: self.community_pool.clone(),
+            // Everything in a unit of work reads from the primary
+            auth_replica: ReadReplica::new(None),
+            community_replica: ReadReplica::new(None),
+            community_unit: Some(UnitTransaction::begin(&self.community_pool).await?),
+        })
+    }
+
+    /// Connection for community queries, inside the unit of work when the
+    /// store is bound to one.
+    async fn community(&self) -> Result<Conn<'_, MySql>, sqlx::Error> {
+        Conn::acquire(&self.community_pool, self.community_unit.as_ref()).await
+    }
+}
+
+fn deletion_from_row(row: MySqlRow) -> DeletionRecord {
+    DeletionRecord {
+        author: row.get("author"),
+        deleted_by: row.get("deleted_by"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Binds the sort key and id of a page cursor, in the order [`Page::sql`]
+/// hands out their placeholders.
+fn bind_cursor<'q>(
+    query: Query<'q, MySql, MySqlArguments>,
+    cursor: &'q Cursor,
+) -> Query<'q, MySql, MySqlArguments> {
+    match &cursor.key {
+        SortKey::Time(at) => query.bind(*at),
+        SortKey::Score(score) => query.bind(*score),
+    }
+    .bind(&cursor.id)
+}
+
+/// Shifts the counters of a post inside the caller's transaction. Ids that
+/// are not posts match nothing. `last_modified` is kept, since counter
+/// changes are not edits.
+async fn adjust_counts(
+    conn: &mut MySqlConnection,
+    post_id: &str,
+    likes: i32,
+    replies: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE expression_posts
+        SET like_count = like_count + ?, reply_count = reply_count + ?, last_modified = last_modified
+        WHERE id = ?
+    "#,
+    )
+    .bind(likes)
+    .bind(replies)
+    .bind(post_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn ping(pool: &Pool<MySql>) -> Result<(), String> {
+    if let Err(err) = sqlx::query("SELECT 1").execute(pool).await {
+        return Err(err.to_string());
+    }
+
+    Ok(())
+}
+
+/// Seconds the replica is behind its source, or `None` when replication is
+/// stopped. A server that replicates from nothing is never behind.
+async fn replica_lag(pool: &Pool<MySql>) -> Result<Option<f64>, sqlx::Error> {
+    // Sent as plain text, since not every server can prepare SHOW statements
+    let Some(row) = pool.fetch_optional("SHOW REPLICA STATUS").await? else {
+        return Ok(Some(0.0));
+    };
+
+    // Reported as signed or unsigned depending on the server version
+    let lag: Option<i64> = row.try_get_unchecked("Seconds_Behind_Source")?;
+    Ok(lag.map(|secs| secs as f64))
+}
+
+async fn status(
+    migrator: &Migrator,
+    pool: &Pool<MySql>,
+) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let count: i64 = sqlx::query_scalar(
+        r#"
+        SELECT
+            COUNT(*)
+        FROM information_schema.tables
+        WHERE table_schema = DATABASE()
+        AND table_name = '_sqlx_migrations'
+    "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let applied = if count > 0 {
+        migrations::applied(pool).await?
+    } else {
+        vec![]
+    };
+    Ok(migrations::status(migrator, &applied))
+}
+
+#[async_trait]
+impl Backend for MySqlStore {
+    async fn close(&self) {
+        self.auth_pool.close().await;
+        self.community_pool.close().await;
+        self.auth_replica.close().await;
+        self.community_replica.close().await;
+    }
+
+    async fn ping_auth(&self) -> Result<(), String> {
+        ping(&self.auth_pool).await
+    }
+
+    async fn ping_community(&self) -> Result<(), String> {
+        ping(&self.community_pool).await
+    }
+
+    async fn migrate_up(&self) -> Result<(), MigrateError> {
+        migrations::run(&MYSQL_AUTH_MIGRATOR, &self.auth_pool).await?;
+        migrations::run(&MYSQL_COMMUNITY_MIGRATOR, &self.community_pool).await
+    }
+
+    async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), MigrateError> {
+        Ok((
+            migrations::revert_latest(&MYSQL_AUTH_MIGRATOR, &self.auth_pool).await?,
+            migrations::revert_latest(&MYSQL_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), MigrateError> {
+        Ok((
+            status(&MYSQL_AUTH_MIGRATOR, &self.auth_pool).await?,
+            status(&MYSQL_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    fn has_replicas(&self) -> bool {
+        self.auth_replica.pool().is_some() || self.community_replica.pool().is_some()
+    }
+
+    async fn check_replicas(&self, max_lag: Duration) {
+        for replica in [&self.auth_replica, &self.community_replica] {
+            if let Some(pool) = replica.pool() {
+                replica.record_lag(replica_lag(pool).await, max_lag);
+            }
+        }
+    }
+
+    async fn begin_community(&self) -> DbResult<Arc<dyn CommunityTransaction>> {
+        Ok(Arc::new(self.begin_unit().await?))
+    }
+}
+
+/// Stores that are not bound to a unit of work commit every call on its own,
+/// so there is nothing left to commit or roll back.
+#[async_trait]
+impl CommunityTransaction for MySqlStore {
+    async fn commit(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.commit().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.rollback().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tables of the database other than the migrations table.
+    async fn tables(pool: &Pool<MySql>) -> Vec<String> {
+        sqlx::query_scalar(
+            r#"
+            SELECT CAST(table_name AS CHAR) FROM information_schema.tables
+            WHERE table_schema = DATABASE() AND table_name <> '_sqlx_migrations'
+            ORDER BY table_name
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    /// Applies every migration, reverts them one at a time down to an empty
+    /// database, then applies them again and reverts them for the next run.
+    async fn round_trip(migrator: &Migrator, pool: &Pool<MySql>) {
+        migrations::run(migrator, pool).await.unwrap();
+        let migrated = tables(pool).await;
+        assert!(!migrated.is_empty());
+
+        let mut reverted = vec![];
+        while let Some(version) = migrations::revert_latest(migrator, pool).await.unwrap() {
+            reverted.push(version);
+        }
+        let mut expected: Vec<i64> = migrator
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| migration.version)
+            .collect();
+        expected.reverse();
+        assert_eq!(reverted, expected);
+        assert!(tables(pool).await.is_empty());
+
+        migrations::run(migrator, pool).await.unwrap();
+        assert_eq!(tables(pool).await, migrated);
+        let applied = migrations::applied(pool).await.unwrap();
+        migrations::ensure_current(&migrations::status(migrator, &applied)).unwrap();
+        while migrations::revert_latest(migrator, pool)
+            .await
+            .unwrap()
+            .is_some()
+        {}
+    }
+
+    /// Runs against the empty database at `MYSQL_TEST_URL`, and is skipped
+    /// when that is not set. Both migrators share the database, so they take
+    /// turns in one test.
+    #[tokio::test]
+    async fn migrations_round_trip() {
+        let Ok(url) = dotenv::var("MYSQL_TEST_URL") else {
+            eprintln!("Skipping the MySQL migration round trip, MYSQL_TEST_URL is not set.");
+            return;
+        };
+        let pool = MySqlPool::connect(&url).await.unwrap();
+        round_trip(&MYSQL_AUTH_MIGRATOR, &pool).await;
+        round_trip(&MYSQL_COMMUNITY_MIGRATOR, &pool).await;
+    }
+}
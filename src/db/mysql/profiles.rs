@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{mysql::MySqlRow, types::Json, Connection, Row};
+
+use crate::{
+    community::{Follow, UserProfile},
+    db::{tally_follows, DbResult, Page, ProfileRepo},
+};
+
+use super::{bind_cursor, MySqlStore};
+
+/// Loads a profile along with the ids of every post it liked.
+const PROFILE_BY_ID: &str = r#"
+    SELECT 
+        id, 
+        username, 
+        avatar,
+        (
+            SELECT JSON_ARRAYAGG(parent_id) FROM likes WHERE author = id
+        ) AS likes
+    FROM user_profiles 
+    WHERE id = ? 
+"#;
+
+/// Page of one side of the follow graph: the users in `user_column` of the
+/// follows whose `profile_column` is the profile bound first.
+fn follows_sql(user_column: &str, profile_column: &str, page: &Page) -> String {
+    let (keyset, order) = page.sql("follow.created_at", "profile.id", true, || "?".to_string());
+    format!(
+        r#"
+            SELECT
+                profile.id AS id,
+                profile.username AS username,
+                profile.avatar AS avatar,
+                follow.created_at AS followed_at
+            FROM user_follows AS follow
+            JOIN user_profiles AS profile ON profile.id = follow.{user_column}
+            WHERE follow.{profile_column} = ?{keyset}
+            ORDER BY {order}
+            LIMIT ?
+        "#
+    )
+}
+
+fn follow_from_row(row: MySqlRow) -> Follow {
+    Follow {
+        user: UserProfile::new(
+            row.get("id"),
+            row.get("username"),
+            row.get("avatar"),
+            vec![],
+        ),
+        followed_at: row.get("followed_at"),
+    }
+}
+
+impl MySqlStore {
+    async fn follows(&self, sql: &str, id: &str, page: &Page) -> DbResult<Vec<Follow>> {
+        let follows = if self.community_unit.is_some() {
+            let mut query = sqlx::query(sql).bind(id);
+            for cursor in page.cursors() {
+                query = bind_cursor(query, cursor);
+            }
+            query
+                .bind(i64::from(page.limit))
+                .map(follow_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?
+        } else {
+            self.community_replica
+                .read(&self.community_pool, |pool| async move {
+                    let mut query = sqlx::query(sql).bind(id);
+                    for cursor in page.cursors() {
+                        query = bind_cursor(query, cursor);
+                    }
+                    query
+                        .bind(i64::from(page.limit))
+                        .map(follow_from_row)
+                        .fetch_all(&pool)
+                        .await
+                })
+                .await?
+        };
+        Ok(page.finish(follows))
+    }
+}
+
+#[async_trait]
+impl ProfileRepo for MySqlStore {
+    async fn exists(&self, id: &str, username: &str) -> DbResult<bool> {
+        Ok(
+            sqlx::query("SELECT id FROM user_profiles WHERE id = ? OR username = ?")
+                .bind(id)
+                .bind(username)
+                .fetch_optional(&mut *self.community().await?)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn insert(&self, id: &str, username: &str, avatar: &str) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_profiles
+                (
+                    id, 
+                    username, 
+                    avatar
+                ) 
+                VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(id)
+        .bind(username)
+        .bind(avatar)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<UserProfile>> {
+        let profile = if self.community_unit.is_some() {
+            sqlx::query(PROFILE_BY_ID)
+                .bind(id)
+                .fetch_optional(&mut *self.community().await?)
+                .await?
+        } else {
+            self.community_replica
+                .read(&self.community_pool, |pool| async move {
+                    sqlx::query(PROFILE_BY_ID)
+                        .bind(id)
+                        .fetch_optional(&pool)
+                        .await
+                })
+                .await?
+        };
+        let Some(profile) = profile else {
+            return Ok(None);
+        };
+
+        let likes: Option<Json<Vec<String>>> = profile.get("likes");
+
+        Ok(Some(UserProfile::new(
+            profile.get("id"),
+            profile.get("username"),
+            profile.get("avatar"),
+            likes.map(|likes| likes.0).unwrap_or_default(),
+        )))
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        // Take the profile's likes and replies off the posts they were on
+        // before they disappear
+        sqlx::query(
+            r#"
+            UPDATE expression_posts 
+            SET like_count = like_count - (
+                SELECT COUNT(*) FROM likes 
+                WHERE likes.parent_id = expression_posts.id AND likes.author = ?
+            ), last_modified = last_modified
+            WHERE id IN (SELECT parent_id FROM likes WHERE author = ?)
+        "#,
+        )
+        .bind(id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE expression_posts 
+            SET reply_count = reply_count - (
+                SELECT COUNT(*) FROM replies 
+                WHERE replies.parent = expression_posts.id 
+                AND replies.author = ? 
+                AND replies.deleted_at IS NULL
+            ), last_modified = last_modified
+            WHERE id IN (SELECT parent FROM replies WHERE author = ? AND deleted_at IS NULL)
+        "#,
+        )
+        .bind(id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM user_profiles WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM likes WHERE author = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn ids(&self) -> DbResult<Vec<String>> {
+        Ok(sqlx::query_scalar("SELECT id FROM user_profiles")
+            .fetch_all(&mut *self.community().await?)
+            .await?)
+    }
+
+    async fn is_moderator(&self, id: &str) -> DbResult<bool> {
+        Ok(
+            sqlx::query_scalar("SELECT is_moderator FROM user_profiles WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&mut *self.community().await?)
+                .await?
+                .unwrap_or(false),
+        )
+    }
+
+    async fn follow(&self, follower: &str, followee: &str, at: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT IGNORE INTO user_follows (follower_id, followee_id, created_at)
+            VALUES (?, ?, ?)
+        "#,
+        )
+        .bind(follower)
+        .bind(followee)
+        .bind(at)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn unfollow(&self, follower: &str, followee: &str) -> DbResult<()> {
+        sqlx::query("DELETE FROM user_follows WHERE follower_id = ? AND followee_id = ?")
+            .bind(follower)
+            .bind(followee)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn follow_counts(&self, ids: &[String]) -> DbResult<HashMap<String, (i64, i64)>> {
+        let query = format!(
+            r#"
+            SELECT followee_id AS id, 'followers' AS side, COUNT(*) AS count
+            FROM user_follows
+            WHERE followee_id IN ({ids})
+            GROUP BY followee_id
+            UNION ALL
+            SELECT follower_id AS id, 'following' AS side, COUNT(*) AS count
+            FROM user_follows
+            WHERE follower_id IN ({ids})
+            GROUP BY follower_id
+        "#,
+            ids = vec!["?"; ids.len()].join(", ")
+        );
+        let mut query = sqlx::query(&query);
+        for id in ids.iter().chain(ids) {
+            query = query.bind(id);
+        }
+        let rows = query
+            .map(|row: MySqlRow| (row.get("id"), row.get("side"), row.get("count")))
+            .fetch_all(&mut *self.community().await?)
+            .await?;
+        Ok(tally_follows(rows))
+    }
+
+    async fn followers(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>> {
+        self.follows(&follows_sql("follower_id", "followee_id", page), id, page)
+            .await
+    }
+
+    async fn following(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>> {
+        self.follows(&follows_sql("followee_id", "follower_id", page), id, page)
+            .await
+    }
+}
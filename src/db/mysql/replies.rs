@@ -0,0 +1,371 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    mysql::{MySqlArguments, MySqlRow},
+    query::Query,
+    Connection, MySql, Row,
+};
+
+use crate::{
+    community::{Reply, ReplyEdit, UserProfile},
+    db::{DbError, DbResult, DeletionRecord, NewReplyRecord, Page, ReplyRepo, ThreadPage},
+};
+
+use super::{adjust_counts, bind_cursor, deletion_from_row, MySqlStore};
+
+/// Slice of the thread below a post or reply, with the author, depth and
+/// number of direct replies of every reply in it. Below the first level only
+/// the oldest `width` replies to each reply are followed, so wide branches
+/// are cut off before their descendants are read. Its parameters are bound
+/// by `thread_query`.
+fn thread_sql(page: &Page) -> String {
+    let (keyset, order) = page.sql("created_at", "id", false, || "?".to_string());
+    format!(
+        r#"
+    WITH RECURSIVE thread (id, depth) AS (
+        SELECT top.id, 1
+        FROM (
+            SELECT id FROM replies
+            WHERE parent = ?{keyset}
+            ORDER BY {order}
+            LIMIT ?
+        ) AS top
+        UNION ALL
+        SELECT child.id, thread.depth + 1
+        FROM replies AS child
+        JOIN thread ON child.parent = thread.id
+        WHERE thread.depth < ?
+            AND (
+                SELECT COUNT(*) FROM (
+                    SELECT 1 FROM replies AS sibling
+                    WHERE sibling.parent = child.parent
+                        AND (sibling.created_at, sibling.id) < (child.created_at, child.id)
+                    LIMIT ?
+                ) AS earlier
+            ) < ?
+    )
+    SELECT
+        reply.id AS id,
+        profile.id AS author_id,
+        profile.username AS author_username,
+        profile.avatar AS author_avatar,
+        reply.parent AS parent,
+        reply.content AS content,
+        reply.created_at AS created_at,
+        reply.last_modified AS last_modified,
+        reply.deleted_at AS deleted_at,
+        reply.edited_at AS edited_at,
+        thread.depth AS depth,
+        (SELECT COUNT(*) FROM replies AS child WHERE child.parent = reply.id) AS reply_count
+    FROM thread
+    JOIN replies AS reply ON reply.id = thread.id
+    JOIN user_profiles AS profile ON profile.id = reply.author
+"#
+    )
+}
+
+fn thread_query<'q>(
+    sql: &'q str,
+    parent_id: &'q str,
+    thread: &'q ThreadPage,
+) -> Query<'q, MySql, MySqlArguments> {
+    let mut query = sqlx::query(sql).bind(parent_id);
+    for cursor in thread.page.cursors() {
+        query = bind_cursor(query, cursor);
+    }
+    query
+        .bind(i64::from(thread.page.limit))
+        .bind(i64::from(thread.max_depth))
+        .bind(i64::from(thread.width))
+        .bind(i64::from(thread.width))
+}
+
+/// Parent of the topmost ancestor of the reply bound first, which is the
+/// post the thread hangs off.
+const POST_OF_REPLY: &str = r#"
+    WITH RECURSIVE ancestors (parent, depth) AS (
+        SELECT parent, 1 FROM replies WHERE id = ?
+        UNION ALL
+        SELECT reply.parent, ancestors.depth + 1
+        FROM replies AS reply
+        JOIN ancestors ON reply.id = ancestors.parent
+    )
+    SELECT parent FROM ancestors
+    ORDER BY depth DESC
+    LIMIT 1
+"#;
+
+/// A single reply with its author and number of direct replies.
+const REPLY_BY_ID: &str = r#"
+    SELECT
+        reply.id AS id,
+        profile.id AS author_id,
+        profile.username AS author_username,
+        profile.avatar AS author_avatar,
+        reply.parent AS parent,
+        reply.content AS content,
+        reply.created_at AS created_at,
+        reply.last_modified AS last_modified,
+        reply.deleted_at AS deleted_at,
+        reply.edited_at AS edited_at,
+        1 AS depth,
+        (SELECT COUNT(*) FROM replies AS child WHERE child.parent = reply.id) AS reply_count
+    FROM replies AS reply
+    JOIN user_profiles AS profile ON profile.id = reply.author
+    WHERE reply.id = ?
+"#;
+
+fn reply_from_row(reply: MySqlRow) -> Reply {
+    Reply::new(
+        reply.get("id"),
+        UserProfile::new(
+            reply.get("author_id"),
+            reply.get("author_username"),
+            reply.get("author_avatar"),
+            vec![],
+        ),
+        reply.get("parent"),
+        reply.get("content"),
+        reply.get("created_at"),
+        reply.get("last_modified"),
+        reply.get("deleted_at"),
+    )
+    .with_edited_at(reply.get("edited_at"))
+    .with_thread(
+        reply.get::<i64, _>("depth") as i32,
+        reply.get::<i64, _>("reply_count") as i32,
+    )
+}
+
+#[async_trait]
+impl ReplyRepo for MySqlStore {
+    async fn insert(&self, reply: &NewReplyRecord) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO replies
+                (
+                    id, 
+                    author, 
+                    parent, 
+                    content,
+                    created_at,
+                    last_modified
+                ) 
+            VALUES(?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(&reply.id)
+        .bind(&reply.author)
+        .bind(&reply.parent)
+        .bind(&reply.content)
+        .bind(reply.created_at)
+        .bind(reply.created_at)
+        .execute(&mut *tx)
+        .await?;
+        adjust_counts(&mut tx, &reply.parent, 0, 1).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_thread(&self, parent_id: &str, page: ThreadPage) -> DbResult<Vec<Reply>> {
+        let sql = thread_sql(&page.page);
+        if self.community_unit.is_some() {
+            return Ok(thread_query(&sql, parent_id, &page)
+                .map(reply_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| {
+                let (sql, page) = (&sql, &page);
+                async move {
+                    thread_query(sql, parent_id, page)
+                        .map(reply_from_row)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?)
+    }
+
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Reply>> {
+        Ok(sqlx::query(REPLY_BY_ID)
+            .bind(id)
+            .map(reply_from_row)
+            .fetch_optional(&mut *self.community().await?)
+            .await?)
+    }
+
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT author FROM replies WHERE id = ? AND deleted_at IS NULL")
+                .bind(id)
+                .fetch_optional(&mut *self.community().await?)
+                .await?,
+        )
+    }
+
+    async fn post_of(&self, id: &str) -> DbResult<Option<String>> {
+        Ok(sqlx::query_scalar(POST_OF_REPLY)
+            .bind(id)
+            .fetch_optional(&mut *self.community().await?)
+            .await?)
+    }
+
+    async fn update_content(
+        &self,
+        id: &str,
+        content: &str,
+        edited_by: &str,
+        at: DateTime<Utc>,
+    ) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO reply_edits (reply_id, content, edited_by, replaced_at)
+            SELECT id, content, ?, ? FROM replies
+            WHERE id = ? AND deleted_at IS NULL
+        "#,
+        )
+        .bind(edited_by)
+        .bind(at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE replies
+            SET content = ?, edited_at = ?
+            WHERE id = ? AND deleted_at IS NULL
+        "#,
+        )
+        .bind(content)
+        .bind(at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn edits(&self, id: &str) -> DbResult<Vec<ReplyEdit>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT content, edited_by, replaced_at
+            FROM reply_edits
+            WHERE reply_id = ?
+            ORDER BY id
+        "#,
+        )
+        .bind(id)
+        .map(|edit: MySqlRow| ReplyEdit {
+            content: edit.get("content"),
+            edited_by: edit.get("edited_by"),
+            replaced_at: edit.get("replaced_at"),
+        })
+        .fetch_all(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        let changed = sqlx::query(
+            r#"
+            UPDATE replies 
+            SET deleted_at = ?, deleted_by = ?, last_modified = last_modified 
+            WHERE id = ? AND deleted_at IS NULL
+        "#,
+        )
+        .bind(at)
+        .bind(deleted_by)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if changed > 0 {
+            let parent: String = sqlx::query_scalar("SELECT parent FROM replies WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            adjust_counts(&mut tx, &parent, 0, -1).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore(&self, id: &str) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        let changed = sqlx::query(
+            r#"
+            UPDATE replies 
+            SET deleted_at = NULL, deleted_by = NULL, last_modified = last_modified 
+            WHERE id = ? AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if changed > 0 {
+            let parent: String = sqlx::query_scalar("SELECT parent FROM replies WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            adjust_counts(&mut tx, &parent, 0, 1).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT author, deleted_by, deleted_at 
+            FROM replies 
+            WHERE id = ? AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .map(deletion_from_row)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    /// Tombstones that still have replies of their own are kept until those
+    /// are gone, so threads never lose a link.
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        // MySQL cannot select from the table it deletes from in a subquery
+        Ok(sqlx::query(
+            r#"
+            DELETE reply 
+            FROM replies AS reply
+            LEFT JOIN replies AS child ON child.parent = reply.id
+            WHERE reply.deleted_at < ? AND child.id IS NULL
+        "#,
+        )
+        .bind(before)
+        .execute(&mut *self.community().await?)
+        .await?
+        .rows_affected())
+    }
+}
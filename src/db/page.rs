@@ -0,0 +1,290 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Value a paginated list is ordered by.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SortKey {
+    Time(DateTime<Utc>),
+    /// Trending score, as stored with the post.
+    Score(#[serde(with = "score_bits")] f64),
+}
+
+/// Keeps scores in cursors as their bit pattern. Their decimal form does not
+/// always parse back to the same float, and a score off by the last bit
+/// moves the cursor past every row tied with it.
+mod score_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(score: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(score.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
+/// Position of a row in a paginated list. The id breaks ties between rows
+/// with the same sort key, so every row has exactly one position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub key: SortKey,
+    pub id: String,
+}
+
+/// Keyset page of an ordered list: up to `limit` rows strictly between
+/// `after` and `before`, taken from the start of that range or, with
+/// `from_end`, from its end. Rows always come back in list order.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub after: Option<Cursor>,
+    pub before: Option<Cursor>,
+    pub limit: u16,
+    pub from_end: bool,
+}
+
+impl Page {
+    pub fn first(limit: u16) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Whether this is the start of the list, the page most clients ask for.
+    pub fn is_first(&self) -> bool {
+        self.after.is_none() && self.before.is_none() && !self.from_end
+    }
+
+    /// Cursors in the order their placeholders appear in [`Page::sql`].
+    pub fn cursors(&self) -> impl Iterator<Item = &Cursor> {
+        self.after.iter().chain(self.before.iter())
+    }
+
+    /// Conditions, each starting with `AND`, and the `ORDER BY` clause that
+    /// select the page from a list ordered by `key` and then `id`.
+    /// `placeholder` hands out a bind parameter for each cursor value.
+    pub fn sql(
+        &self,
+        key: &str,
+        id: &str,
+        descending: bool,
+        mut placeholder: impl FnMut() -> String,
+    ) -> (String, String) {
+        let (later, earlier) = if descending { ("<", ">") } else { (">", "<") };
+        let mut conditions = String::new();
+        for (cursor, op) in [(&self.after, later), (&self.before, earlier)] {
+            if cursor.is_some() {
+                let (key_param, id_param) = (placeholder(), placeholder());
+                conditions.push_str(&format!(
+                    " AND ({key}, {id}) {op} ({key_param}, {id_param})"
+                ));
+            }
+        }
+
+        // Pages taken from the end are read backwards and flipped by `finish`
+        let direction = if descending != self.from_end {
+            "DESC"
+        } else {
+            "ASC"
+        };
+        (conditions, format!("{key} {direction}, {id} {direction}"))
+    }
+
+    /// Puts rows read with the order from [`Page::sql`] back in list order.
+    pub fn finish<T>(&self, mut rows: Vec<T>) -> Vec<T> {
+        if self.from_end {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// Selects the page from rows held in memory.
+    pub fn apply<T>(
+        &self,
+        mut rows: Vec<T>,
+        cursor: impl Fn(&T) -> Cursor,
+        descending: bool,
+    ) -> Vec<T> {
+        let compare = |a: &Cursor, b: &Cursor| {
+            sort_value(&a.key)
+                .total_cmp(&sort_value(&b.key))
+                .then_with(|| a.id.cmp(&b.id))
+        };
+        rows.sort_by(|a, b| {
+            let ordering = compare(&cursor(a), &cursor(b));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let is_later = |row: &T, than: &Cursor| {
+            let ordering = compare(&cursor(row), than);
+            if descending {
+                ordering.is_lt()
+            } else {
+                ordering.is_gt()
+            }
+        };
+        rows.retain(|row| {
+            self.after.as_ref().is_none_or(|after| is_later(row, after))
+                && self.before.as_ref().is_none_or(|before| {
+                    compare(&cursor(row), before).is_ne() && !is_later(row, before)
+                })
+        });
+
+        let limit = usize::from(self.limit);
+        if self.from_end {
+            rows.split_off(rows.len().saturating_sub(limit))
+        } else {
+            rows.truncate(limit);
+            rows
+        }
+    }
+}
+
+/// Comparable form of a sort key. Lists never mix kinds of keys.
+fn sort_value(key: &SortKey) -> f64 {
+    match key {
+        SortKey::Time(at) => at.timestamp_micros() as f64,
+        SortKey::Score(score) => *score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(secs: i64) -> Cursor {
+        Cursor {
+            key: SortKey::Time(Utc.timestamp_opt(secs, 0).unwrap()),
+            id: format!("post-{secs}"),
+        }
+    }
+
+    fn numbered() -> impl FnMut() -> String {
+        let mut next = 0;
+        move || {
+            next += 1;
+            format!("${next}")
+        }
+    }
+
+    #[test]
+    fn sql_bounds_pages_by_key_then_id() {
+        let page = Page {
+            after: Some(at(1)),
+            before: Some(at(5)),
+            limit: 10,
+            from_end: false,
+        };
+        let (conditions, order) = page.sql("created_at", "id", true, numbered());
+        assert_eq!(
+            conditions,
+            " AND (created_at, id) < ($1, $2) AND (created_at, id) > ($3, $4)"
+        );
+        assert_eq!(order, "created_at DESC, id DESC");
+
+        let (conditions, order) = Page::first(10).sql("created_at", "id", false, numbered());
+        assert_eq!(conditions, "");
+        assert_eq!(order, "created_at ASC, id ASC");
+    }
+
+    #[test]
+    fn pages_from_the_end_are_read_backwards_and_flipped() {
+        let page = Page {
+            before: Some(at(5)),
+            limit: 2,
+            from_end: true,
+            ..Page::default()
+        };
+        let (conditions, order) = page.sql("created_at", "id", true, numbered());
+        assert_eq!(conditions, " AND (created_at, id) > ($1, $2)");
+        assert_eq!(order, "created_at ASC, id ASC");
+        assert_eq!(page.finish(vec![6, 7]), vec![7, 6]);
+        assert_eq!(Page::first(2).finish(vec![7, 6]), vec![7, 6]);
+    }
+
+    #[test]
+    fn apply_selects_the_page_between_cursors_in_list_order() {
+        let rows: Vec<Cursor> = [3, 1, 5, 2, 4].into_iter().map(at).collect();
+        let keys = |rows: Vec<Cursor>| -> Vec<String> {
+            rows.into_iter().map(|cursor| cursor.id).collect()
+        };
+
+        let page = Page::first(2);
+        assert_eq!(
+            keys(page.apply(rows.clone(), Cursor::clone, true)),
+            ["post-5", "post-4"]
+        );
+
+        let page = Page {
+            after: Some(at(4)),
+            limit: 2,
+            ..Page::default()
+        };
+        assert_eq!(
+            keys(page.apply(rows.clone(), Cursor::clone, true)),
+            ["post-3", "post-2"]
+        );
+
+        let page = Page {
+            before: Some(at(2)),
+            limit: 2,
+            from_end: true,
+            ..Page::default()
+        };
+        assert_eq!(
+            keys(page.apply(rows.clone(), Cursor::clone, true)),
+            ["post-4", "post-3"]
+        );
+
+        let page = Page {
+            after: Some(at(1)),
+            before: Some(at(4)),
+            limit: 10,
+            from_end: false,
+        };
+        assert_eq!(
+            keys(page.apply(rows, Cursor::clone, false)),
+            ["post-2", "post-3"]
+        );
+    }
+
+    #[test]
+    fn apply_breaks_ties_by_id() {
+        let tied = |id: &str| Cursor {
+            key: SortKey::Score(1.5),
+            id: id.to_string(),
+        };
+        let rows = vec![tied("a"), tied("c"), tied("b")];
+        let page = Page {
+            after: Some(tied("c")),
+            limit: 10,
+            ..Page::default()
+        };
+        let ids: Vec<String> = page
+            .apply(rows, Cursor::clone, true)
+            .into_iter()
+            .map(|cursor| cursor.id)
+            .collect();
+        assert_eq!(ids, ["b", "a"]);
+    }
+
+    #[test]
+    fn score_cursors_decode_to_the_same_score() {
+        // Both parse back one bit off from their decimal form
+        for score in [20745.048877314814, 20745.048969907406] {
+            let cursor = Cursor {
+                key: SortKey::Score(score),
+                id: "post".to_string(),
+            };
+            let encoded = serde_json::to_string(&cursor).unwrap();
+            assert_eq!(serde_json::from_str::<Cursor>(&encoded).unwrap(), cursor);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, Row};
+
+use crate::{
+    community::BookmarkCollection,
+    db::{BookmarkRecord, BookmarkRepo, DbResult, Page},
+};
+
+use super::{bind_cursor, PgStore};
+
+const BOOKMARK_COLUMNS: &str = r#"
+    bookmark.id AS id,
+    bookmark.user_id AS user_id,
+    bookmark.post_id AS post_id,
+    bookmark.reply_id AS reply_id,
+    bookmark.collection AS collection,
+    bookmark.created_at AS created_at
+"#;
+
+/// Bookmarks of the user bound to `$1` whose content is not deleted.
+/// Expects conditions to be appended.
+const LIVE_BOOKMARKS: &str = r#"
+    FROM bookmarks AS bookmark
+    LEFT JOIN expression_posts AS post ON post.id = bookmark.post_id
+    LEFT JOIN replies AS reply ON reply.id = bookmark.reply_id
+    WHERE bookmark.user_id = $1
+        AND post.deleted_at IS NULL
+        AND reply.deleted_at IS NULL
+"#;
+
+fn bookmark_from_row(row: PgRow) -> BookmarkRecord {
+    BookmarkRecord {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        post_id: row.get("post_id"),
+        reply_id: row.get("reply_id"),
+        collection: row.get("collection"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn collection_from_row(row: PgRow) -> BookmarkCollection {
+    BookmarkCollection {
+        name: row.get("name"),
+        bookmark_count: row.get("bookmark_count"),
+    }
+}
+
+#[async_trait]
+impl BookmarkRepo for PgStore {
+    async fn insert(&self, bookmark: &BookmarkRecord) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO bookmarks (id, user_id, post_id, reply_id, collection, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        )
+        .bind(&bookmark.id)
+        .bind(&bookmark.user_id)
+        .bind(&bookmark.post_id)
+        .bind(&bookmark.reply_id)
+        .bind(&bookmark.collection)
+        .bind(bookmark.created_at)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn find(&self, user_id: &str, target_id: &str) -> DbResult<Option<BookmarkRecord>> {
+        Ok(sqlx::query(&format!(
+            r#"
+            SELECT {BOOKMARK_COLUMNS}
+            FROM bookmarks AS bookmark
+            WHERE bookmark.user_id = $1
+                AND (bookmark.post_id = $2 OR bookmark.reply_id = $2)
+        "#
+        ))
+        .bind(user_id)
+        .bind(target_id)
+        .map(bookmark_from_row)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn set_collection(&self, id: &str, collection: Option<&str>) -> DbResult<()> {
+        sqlx::query("UPDATE bookmarks SET collection = $1 WHERE id = $2")
+            .bind(collection)
+            .bind(id)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> DbResult<()> {
+        sqlx::query("DELETE FROM bookmarks WHERE id = $1")
+            .bind(id)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        user_id: &str,
+        collection: Option<&str>,
+        page: &Page,
+    ) -> DbResult<Vec<BookmarkRecord>> {
+        let mut params = 1;
+        let mut placeholder = || {
+            params += 1;
+            format!("${params}")
+        };
+        let filter = match collection {
+            Some(_) => format!(" AND bookmark.collection = {}", placeholder()),
+            None => String::new(),
+        };
+        let (keyset, order) =
+            page.sql("bookmark.created_at", "bookmark.id", true, &mut placeholder);
+        let limit = placeholder();
+        let query = format!(
+            r#"
+            SELECT {BOOKMARK_COLUMNS}
+            {LIVE_BOOKMARKS}{filter}{keyset}
+            ORDER BY {order}
+            LIMIT {limit}
+        "#
+        );
+
+        let bookmarks = if self.community_unit.is_some() {
+            let mut query = sqlx::query(&query).bind(user_id);
+            if let Some(collection) = collection {
+                query = query.bind(collection);
+            }
+            for cursor in page.cursors() {
+                query = bind_cursor(query, cursor);
+            }
+            query
+                .bind(i64::from(page.limit))
+                .map(bookmark_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?
+        } else {
+            self.community_replica
+                .read(&self.community_pool, |pool| {
+                    let query = &query;
+                    async move {
+                        let mut query = sqlx::query(query).bind(user_id);
+                        if let Some(collection) = collection {
+                            query = query.bind(collection);
+                        }
+                        for cursor in page.cursors() {
+                            query = bind_cursor(query, cursor);
+                        }
+                        query
+                            .bind(i64::from(page.limit))
+                            .map(bookmark_from_row)
+                            .fetch_all(&pool)
+                            .await
+                    }
+                })
+                .await?
+        };
+        Ok(page.finish(bookmarks))
+    }
+
+    async fn collections(&self, user_id: &str) -> DbResult<Vec<BookmarkCollection>> {
+        let query = format!(
+            r#"
+            SELECT bookmark.collection AS name, COUNT(*) AS bookmark_count
+            {LIVE_BOOKMARKS}
+                AND bookmark.collection IS NOT NULL
+            GROUP BY bookmark.collection
+            ORDER BY bookmark.collection ASC
+        "#
+        );
+        if self.community_unit.is_some() {
+            return Ok(sqlx::query(&query)
+                .bind(user_id)
+                .map(collection_from_row)
+                .fetch_all(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| {
+                let query = &query;
+                async move {
+                    sqlx::query(query)
+                        .bind(user_id)
+                        .map(collection_from_row)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?)
+    }
+
+    async fn bookmarked_posts(&self, user_id: &str, post_ids: &[String]) -> DbResult<Vec<String>> {
+        let query =
+            "SELECT DISTINCT post_id FROM bookmarks WHERE user_id = $1 AND post_id = ANY($2)";
+        if self.community_unit.is_some() {
+            return Ok(sqlx::query_scalar(query)
+                .bind(user_id)
+                .bind(post_ids)
+                .fetch_all(&mut *self.community().await?)
+                .await?);
+        }
+
+        Ok(self
+            .community_replica
+            .read(&self.community_pool, |pool| async move {
+                sqlx::query_scalar(query)
+                    .bind(user_id)
+                    .bind(post_ids)
+                    .fetch_all(&pool)
+                    .await
+            })
+            .await?)
+    }
+}
@@ -0,0 +1,251 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::{
+    migrate::{MigrateError, Migrator},
+    postgres::{PgArguments, PgRow},
+    query::Query,
+    PgConnection, PgPool, Pool, Postgres, Row,
+};
+
+use super::{
+    migrations::{self, MigrationStatus},
+    routing::ReadReplica,
+    unit::{Conn, UnitTransaction},
+    Backend, CommunityTransaction, Cursor, DbResult, DeletionRecord, SortKey,
+};
+
+mod auth;
+mod bookmarks;
+mod circles;
+mod drafts;
+mod posts;
+mod profiles;
+mod replies;
+mod topics;
+
+/// Postgres migrations for the auth database, embedded at compile time.
+pub static POSTGRES_AUTH_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres/auth");
+/// Postgres migrations for the community database, embedded at compile time.
+pub static POSTGRES_COMMUNITY_MIGRATOR: Migrator =
+    sqlx::migrate!("./migrations/postgres/community");
+
+/// Repository implementation backed by the auth and community Postgres databases.
+#[derive(Debug)]
+pub struct PgStore {
+    pub auth_pool: Pool<Postgres>,
+    pub community_pool: Pool<Postgres>,
+    pub auth_replica: ReadReplica<Postgres>,
+    pub community_replica: ReadReplica<Postgres>,
+    /// Open transaction when the store is bound to a unit of work.
+    pub community_unit: Option<UnitTransaction<Postgres>>,
+}
+
+impl PgStore {
+    /// Connects to both primaries. Replicas connect lazily, so one that is
+    /// down at startup only sends reads to its primary.
+    pub async fn connect(
+        auth_db_url: &str,
+        community_db_url: &str,
+        auth_replica_url: Option<&str>,
+        community_replica_url: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            auth_pool: PgPool::connect(auth_db_url).await?,
+            community_pool: PgPool::connect(community_db_url).await?,
+            auth_replica: ReadReplica::new(auth_replica_url.map(PgPool::connect_lazy).transpose()?),
+            community_replica: ReadReplica::new(
+                community_replica_url
+                    .map(PgPool::connect_lazy)
+                    .transpose()?,
+            ),
+            community_unit: None,
+        })
+    }
+
+    /// Copy of the store bound to a new community transaction.
+    async fn begin_unit(&self) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            auth_pool: self.auth_pool.clone(),
+            community_pool: self.community_pool.clone(),
+            // Everything in a unit of work reads from the primary
+            auth_replica: ReadReplica::new(None),
+            community_replica: ReadReplica::new(None),
+            community_unit: Some(UnitTransaction::begin(&self.community_pool).await?),
+        })
+    }
+
+    /// Connection for community queries, inside the unit of work when the
+    /// store is bound to one.
+    async fn community(&self) -> Result<Conn<'_, Postgres>, sqlx::Error> {
+        Conn::acquire(&self.community_pool, self.community_unit.as_ref()).await
+    }
+}
+
+fn deletion_from_row(row: PgRow) -> DeletionRecord {
+    DeletionRecord {
+        author: row.get("author"),
+        deleted_by: row.get("deleted_by"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Binds the sort key and id of a page cursor, in the order [`Page::sql`]
+/// hands out their placeholders.
+fn bind_cursor<'q>(
+    query: Query<'q, Postgres, PgArguments>,
+    cursor: &'q Cursor,
+) -> Query<'q, Postgres, PgArguments> {
+    match &cursor.key {
+        SortKey::Time(at) => query.bind(*at),
+        SortKey::Score(score) => query.bind(*score),
+    }
+    .bind(&cursor.id)
+}
+
+/// Shifts the counters of a post inside the caller's transaction. Ids that
+/// are not posts match nothing.
+async fn adjust_counts(
+    conn: &mut PgConnection,
+    post_id: &str,
+    likes: i32,
+    replies: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE expression_posts
+        SET like_count = like_count + $1, reply_count = reply_count + $2
+        WHERE id = $3
+    "#,
+    )
+    .bind(likes)
+    .bind(replies)
+    .bind(post_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn ping(pool: &Pool<Postgres>) -> Result<(), String> {
+    if let Err(err) = sqlx::query("SELECT 1").execute(pool).await {
+        return Err(err.to_string());
+    }
+
+    Ok(())
+}
+
+/// Seconds since the replica last replayed a transaction, or zero when it
+/// has replayed everything it received. A server that is not in recovery is
+/// never behind.
+async fn replica_lag(pool: &Pool<Postgres>) -> Result<Option<f64>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT
+            CASE
+                WHEN NOT pg_is_in_recovery() THEN 0
+                WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())
+            END::FLOAT8
+    "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+async fn status(
+    migrator: &Migrator,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let count: i64 = sqlx::query_scalar(
+        r#"
+        SELECT
+            COUNT(*)
+        FROM information_schema.tables
+        WHERE table_schema = current_schema()
+        AND table_name = '_sqlx_migrations'
+    "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let applied = if count > 0 {
+        migrations::applied(pool).await?
+    } else {
+        vec![]
+    };
+    Ok(migrations::status(migrator, &applied))
+}
+
+#[async_trait]
+impl Backend for PgStore {
+    async fn close(&self) {
+        self.auth_pool.close().await;
+        self.community_pool.close().await;
+        self.auth_replica.close().await;
+        self.community_replica.close().await;
+    }
+
+    async fn ping_auth(&self) -> Result<(), String> {
+        ping(&self.auth_pool).await
+    }
+
+    async fn ping_community(&self) -> Result<(), String> {
+        ping(&self.community_pool).await
+    }
+
+    async fn migrate_up(&self) -> Result<(), MigrateError> {
+        migrations::run(&POSTGRES_AUTH_MIGRATOR, &self.auth_pool).await?;
+        migrations::run(&POSTGRES_COMMUNITY_MIGRATOR, &self.community_pool).await
+    }
+
+    async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), MigrateError> {
+        Ok((
+            migrations::revert_latest(&POSTGRES_AUTH_MIGRATOR, &self.auth_pool).await?,
+            migrations::revert_latest(&POSTGRES_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), MigrateError> {
+        Ok((
+            status(&POSTGRES_AUTH_MIGRATOR, &self.auth_pool).await?,
+            status(&POSTGRES_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    fn has_replicas(&self) -> bool {
+        self.auth_replica.pool().is_some() || self.community_replica.pool().is_some()
+    }
+
+    async fn check_replicas(&self, max_lag: Duration) {
+        for replica in [&self.auth_replica, &self.community_replica] {
+            if let Some(pool) = replica.pool() {
+                replica.record_lag(replica_lag(pool).await, max_lag);
+            }
+        }
+    }
+
+    async fn begin_community(&self) -> DbResult<Arc<dyn CommunityTransaction>> {
+        Ok(Arc::new(self.begin_unit().await?))
+    }
+}
+
+/// Stores that are not bound to a unit of work commit every call on its own,
+/// so there is nothing left to commit or roll back.
+#[async_trait]
+impl CommunityTransaction for PgStore {
+    async fn commit(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.commit().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.rollback().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,541 @@
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::MigrateError;
+use ulid::Ulid;
+
+use crate::community::{
+    BookmarkCollection, Circle, CircleMember, CircleMemberStatus, CircleRole, CircleVisibility,
+    ExpressionPost, Follow, PostDraft, Reply, ReplyEdit, Topic, UserProfile,
+};
+
+use super::{migrations::MigrationStatus, page::Page, unit};
+
+/// Errors returned by every repository implementation. Models translate them
+/// into user facing messages.
+#[derive(Debug)]
+pub enum DbError {
+    NotFound,
+    Backend(String),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::NotFound => f.write_str("Record not found"),
+            DbError::Backend(err) => f.write_str(err),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        if unit::is_conflict(&err) {
+            unit::note_conflict();
+        }
+        match err {
+            sqlx::Error::RowNotFound => DbError::NotFound,
+            err => DbError::Backend(err.to_string()),
+        }
+    }
+}
+
+pub type DbResult<T> = Result<T, DbError>;
+
+/// Row of the `auths` table.
+#[derive(Debug, Clone)]
+pub struct AuthRecord {
+    pub id: String,
+    pub email: String,
+    pub hash: String,
+    pub community_id: String,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountOperationKind {
+    Register,
+    Delete,
+}
+
+/// Progress of an account operation. `Pending` operations are rolled
+/// forward, `Compensating` ones are rolled back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountOperationStatus {
+    Pending,
+    Compensating,
+    Completed,
+    Compensated,
+}
+
+impl AccountOperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountOperationKind::Register => "register",
+            AccountOperationKind::Delete => "delete",
+        }
+    }
+
+    pub fn parse(kind: &str) -> DbResult<Self> {
+        match kind {
+            "register" => Ok(AccountOperationKind::Register),
+            "delete" => Ok(AccountOperationKind::Delete),
+            _ => Err(DbError::Backend(format!(
+                "Unknown account operation `{kind}`"
+            ))),
+        }
+    }
+}
+
+impl AccountOperationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountOperationStatus::Pending => "pending",
+            AccountOperationStatus::Compensating => "compensating",
+            AccountOperationStatus::Completed => "completed",
+            AccountOperationStatus::Compensated => "compensated",
+        }
+    }
+
+    pub fn parse(status: &str) -> DbResult<Self> {
+        match status {
+            "pending" => Ok(AccountOperationStatus::Pending),
+            "compensating" => Ok(AccountOperationStatus::Compensating),
+            "completed" => Ok(AccountOperationStatus::Completed),
+            "compensated" => Ok(AccountOperationStatus::Compensated),
+            _ => Err(DbError::Backend(format!(
+                "Unknown account operation status `{status}`"
+            ))),
+        }
+    }
+}
+
+/// Row of the `account_operations` outbox. Records a change that spans the
+/// auth and community databases so it can be finished or undone later.
+#[derive(Debug, Clone)]
+pub struct AccountOperation {
+    pub id: String,
+    pub community_id: String,
+    pub kind: AccountOperationKind,
+    /// Username of the profile to create, for registrations.
+    pub username: Option<String>,
+    pub status: AccountOperationStatus,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AccountOperation {
+    pub fn new(community_id: String, kind: AccountOperationKind, username: Option<String>) -> Self {
+        Self {
+            id: Ulid::new().to_string(),
+            community_id,
+            kind,
+            username,
+            status: AccountOperationStatus::Pending,
+            attempts: 0,
+            created_at: super::now(),
+        }
+    }
+}
+
+/// Values required to insert a row into `expression_posts`.
+#[derive(Debug, Clone)]
+pub struct NewPostRecord {
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub cover_image: Option<String>,
+    pub author: String,
+    pub content_type: String,
+    pub content_value: String,
+    /// Normalized names of topics in the topic list.
+    pub tags: Vec<String>,
+    /// Circle the post is published into, if any.
+    pub circle_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Counters and age of a post, the inputs of its trending score, along
+/// with the score last stored for it.
+#[derive(Debug, Clone)]
+pub struct PostCounts {
+    pub id: String,
+    pub likes: i32,
+    pub replies: i32,
+    pub created_at: DateTime<Utc>,
+    pub hot_score: f64,
+}
+
+/// Full-text search over live posts and their replies. A post matches when
+/// it or any reply in its thread contains every term.
+#[derive(Debug, Clone)]
+pub struct PostSearch {
+    /// Lowercase words made of letters and digits only.
+    pub terms: Vec<String>,
+    pub author: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub content_type: Option<String>,
+    pub limit: u16,
+    pub offset: u32,
+}
+
+/// Post found by a search with its relevance. Matches in replies count for
+/// half of matches in the post itself; `reply` holds the text of the reply
+/// when one ranked the post.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub post: ExpressionPost,
+    pub score: f64,
+    pub reply: Option<String>,
+}
+
+/// Values required to insert a row into `replies`.
+#[derive(Debug, Clone)]
+pub struct NewReplyRecord {
+    pub id: String,
+    pub author: String,
+    pub parent: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Row of `bookmarks`. Exactly one of `post_id` and `reply_id` is set.
+#[derive(Debug, Clone)]
+pub struct BookmarkRecord {
+    pub id: String,
+    pub user_id: String,
+    pub post_id: Option<String>,
+    pub reply_id: Option<String>,
+    pub collection: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Values required to insert a row into `circles`.
+#[derive(Debug, Clone)]
+pub struct NewCircleRecord {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Single line rules, stored one per line.
+    pub rules: Vec<String>,
+    pub visibility: CircleVisibility,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tombstone left on a soft deleted post or reply.
+#[derive(Debug, Clone)]
+pub struct DeletionRecord {
+    pub author: String,
+    pub deleted_by: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl DeletionRecord {
+    /// Whether `user` may undo the deletion without being a moderator: only
+    /// authors who deleted their own content.
+    pub fn may_restore(&self, user: &str) -> bool {
+        self.author == user && self.deleted_by == user
+    }
+}
+
+/// Slice of a reply thread to load: a page of the parent's direct replies,
+/// oldest first, with their descendants down to `max_depth` levels below
+/// the parent. Only the oldest `width` replies to each of those replies are
+/// loaded.
+#[derive(Debug, Clone)]
+pub struct ThreadPage {
+    pub page: Page,
+    pub max_depth: u16,
+    pub width: u16,
+}
+
+/// Posts a feed lists and the order it lists them in. Posts published into
+/// a circle are only listed by the feeds of their circle.
+#[derive(Debug, Clone)]
+pub enum PostFeed {
+    /// Newest first.
+    Recent,
+    /// Posts created after `since`, highest trending score first.
+    Trending { since: DateTime<Utc> },
+    /// Posts of one author, newest first.
+    ByAuthor(String),
+    /// Posts tagged with one topic, newest first.
+    ByTag(String),
+    /// Posts of one circle, newest first.
+    CircleRecent(String),
+    /// Posts of one circle created after `since`, highest trending score
+    /// first.
+    CircleTrending {
+        circle: String,
+        since: DateTime<Utc>,
+    },
+    /// Posts created after `since` by users `viewer` follows or tagged with
+    /// topics they follow, highest trending score first.
+    Home {
+        viewer: String,
+        since: DateTime<Utc>,
+    },
+}
+
+/// Connection and schema management every store provides next to its
+/// repositories. Results are reported for the auth and community databases.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    async fn close(&self);
+    async fn ping_auth(&self) -> Result<(), String>;
+    async fn ping_community(&self) -> Result<(), String>;
+    async fn migrate_up(&self) -> Result<(), MigrateError>;
+    /// Reverts the latest migration of each database, returning its version.
+    async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), MigrateError>;
+    async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), MigrateError>;
+    fn has_replicas(&self) -> bool;
+    /// Measures how far each replica is behind and takes the ones lagging
+    /// more than `max_lag`, or unreachable, out of rotation.
+    async fn check_replicas(&self, max_lag: Duration);
+    /// Opens a transaction on the community database for a unit of work.
+    async fn begin_community(&self) -> DbResult<Arc<dyn CommunityTransaction>>;
+}
+
+/// Community repositories bound to one open transaction. Calls made through
+/// them see each other's writes and read from the primary only. Transactions
+/// the repositories open themselves become savepoints.
+#[async_trait]
+pub trait CommunityTransaction:
+    ProfileRepo + PostRepo + ReplyRepo + TopicRepo + CircleRepo + BookmarkRepo + DraftRepo
+{
+    async fn commit(&self) -> DbResult<()>;
+    async fn rollback(&self) -> DbResult<()>;
+}
+
+#[async_trait]
+pub trait AuthRepo: Send + Sync {
+    async fn email_exists(&self, email: &str) -> DbResult<bool>;
+    /// Inserts the auth together with the operation that creates its
+    /// profile, in a single transaction.
+    async fn insert(&self, auth: &AuthRecord, operation: &AccountOperation) -> DbResult<()>;
+    async fn find_by_email(&self, email: &str) -> DbResult<Option<AuthRecord>>;
+    async fn find_by_id(&self, id: &str) -> DbResult<Option<AuthRecord>>;
+    async fn set_refresh_token(&self, community_id: &str, token: &str) -> DbResult<()>;
+    async fn update_email(&self, community_id: &str, email: &str) -> DbResult<()>;
+    async fn update_hash(&self, community_id: &str, hash: &str) -> DbResult<()>;
+    /// Deletes the auth and moves the operation that removed it to `status`,
+    /// in a single transaction.
+    async fn delete(
+        &self,
+        community_id: &str,
+        operation_id: &str,
+        status: AccountOperationStatus,
+    ) -> DbResult<()>;
+    /// Community ids of every auth created before `created_before`.
+    async fn community_ids(&self, created_before: DateTime<Utc>) -> DbResult<Vec<String>>;
+
+    async fn insert_operation(&self, operation: &AccountOperation) -> DbResult<()>;
+    async fn set_operation_status(&self, id: &str, status: AccountOperationStatus) -> DbResult<()>;
+    /// Moves the operation to `status`, counting a failed attempt.
+    async fn fail_operation(
+        &self,
+        id: &str,
+        status: AccountOperationStatus,
+        error: &str,
+    ) -> DbResult<()>;
+    /// Pending and compensating operations created before `created_before`.
+    async fn open_operations(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> DbResult<Vec<AccountOperation>>;
+}
+
+#[async_trait]
+pub trait ProfileRepo: Send + Sync {
+    async fn exists(&self, id: &str, username: &str) -> DbResult<bool>;
+    async fn insert(&self, id: &str, username: &str, avatar: &str) -> DbResult<()>;
+    /// Loads a profile along with the ids of everything it has liked.
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<UserProfile>>;
+    /// Deletes the profile and every like it has given, taking them and its
+    /// replies off the counters of the posts they were on.
+    async fn delete(&self, id: &str) -> DbResult<()>;
+    async fn ids(&self) -> DbResult<Vec<String>>;
+    async fn is_moderator(&self, id: &str) -> DbResult<bool>;
+    /// Makes `follower` follow `followee`. Following again changes nothing.
+    async fn follow(&self, follower: &str, followee: &str, at: DateTime<Utc>) -> DbResult<()>;
+    async fn unfollow(&self, follower: &str, followee: &str) -> DbResult<()>;
+    /// Number of users following each of the profiles and number each one
+    /// follows. Profiles with no follows either way are left out.
+    async fn follow_counts(&self, ids: &[String]) -> DbResult<HashMap<String, (i64, i64)>>;
+    /// Page of the users following the profile, latest follow first.
+    async fn followers(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>>;
+    /// Page of the users the profile follows, latest follow first.
+    async fn following(&self, id: &str, page: &Page) -> DbResult<Vec<Follow>>;
+}
+
+#[async_trait]
+pub trait PostRepo: Send + Sync {
+    /// Loads a post with its author, like count and reply count. Replies
+    /// themselves are loaded through [`ReplyRepo`]. Deleted posts are never
+    /// returned, here or by any other query.
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<ExpressionPost>>;
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>>;
+    /// Inserts the post and its tags in one transaction.
+    async fn insert(&self, post: &NewPostRecord) -> DbResult<()>;
+    async fn update_content(
+        &self,
+        id: &str,
+        content_type: &str,
+        content_value: &str,
+    ) -> DbResult<()>;
+    /// Tombstones the post. Its likes and replies are kept for a restore.
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()>;
+    async fn restore(&self, id: &str) -> DbResult<()>;
+    /// Tombstone of the post, if it is deleted.
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>>;
+    /// Removes posts deleted before `before` together with their likes and
+    /// replies. Returns the number of posts removed.
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64>;
+    /// Records the like and bumps the post's `like_count` in one transaction.
+    /// Liking a post twice changes nothing.
+    async fn add_like(&self, post_id: &str, author: &str) -> DbResult<()>;
+    async fn remove_like(&self, post_id: &str, author: &str) -> DbResult<()>;
+    /// Recomputes `like_count` and `reply_count` of every post from the
+    /// likes and replies tables. Returns the number of posts corrected.
+    async fn repair_counts(&self) -> DbResult<u64>;
+    /// Page of the live posts a feed lists, in feed order.
+    async fn feed(&self, feed: &PostFeed, page: &Page) -> DbResult<Vec<ExpressionPost>>;
+    /// Counters and trending scores of the live posts created after `since`.
+    async fn counts_since(&self, since: DateTime<Utc>) -> DbResult<Vec<PostCounts>>;
+    /// Stores the trending score of each post, in one transaction.
+    async fn set_hot_scores(&self, scores: &[(String, f64)]) -> DbResult<()>;
+    /// Posts outside circles matching the search, most relevant first.
+    async fn search(&self, search: &PostSearch) -> DbResult<Vec<SearchHit>>;
+}
+
+#[async_trait]
+pub trait ReplyRepo: Send + Sync {
+    /// Inserts the reply and bumps its parent post's `reply_count` in one
+    /// transaction. Deleting and restoring replies keep the count in step.
+    async fn insert(&self, reply: &NewReplyRecord) -> DbResult<()>;
+    /// Replies in the slice of the thread below a post or reply, deleted ones
+    /// included so threads keep their shape. Rows come back flat, each with
+    /// its depth below the parent and its number of direct replies set.
+    async fn get_thread(&self, parent_id: &str, page: ThreadPage) -> DbResult<Vec<Reply>>;
+    /// The reply on its own, without any of its replies loaded.
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Reply>>;
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>>;
+    /// Id of the post at the top of the thread the reply is in, or `None`
+    /// for ids that are not replies.
+    async fn post_of(&self, id: &str) -> DbResult<Option<String>>;
+    /// Replaces the content of a live reply and marks it edited at `at`. The
+    /// previous content goes to its edit history in the same transaction.
+    async fn update_content(
+        &self,
+        id: &str,
+        content: &str,
+        edited_by: &str,
+        at: DateTime<Utc>,
+    ) -> DbResult<()>;
+    /// Earlier versions of the reply, oldest first.
+    async fn edits(&self, id: &str) -> DbResult<Vec<ReplyEdit>>;
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()>;
+    async fn restore(&self, id: &str) -> DbResult<()>;
+    /// Tombstone of the reply, if it is deleted.
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>>;
+    /// Removes replies deleted before `before`. Returns how many were removed.
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64>;
+}
+
+#[async_trait]
+pub trait TopicRepo: Send + Sync {
+    /// Topics whose name starts with `prefix`, each with its number of live
+    /// posts, most used first.
+    async fn list(&self, prefix: &str, limit: u16) -> DbResult<Vec<Topic>>;
+    /// Those of `names` that are in the topic list.
+    async fn existing(&self, names: &[String]) -> DbResult<Vec<String>>;
+    async fn insert(&self, name: &str, created_by: &str, at: DateTime<Utc>) -> DbResult<()>;
+    /// Follows the topic. Following a topic twice is not an error.
+    async fn follow(&self, user_id: &str, topic: &str, at: DateTime<Utc>) -> DbResult<()>;
+    async fn unfollow(&self, user_id: &str, topic: &str) -> DbResult<()>;
+    /// Topics the user follows with their post counts, by name.
+    async fn followed(&self, user_id: &str) -> DbResult<Vec<Topic>>;
+}
+
+#[async_trait]
+pub trait CircleRepo: Send + Sync {
+    /// Inserts the circle with its creator as its owner, in one transaction.
+    async fn insert(&self, circle: &NewCircleRecord) -> DbResult<()>;
+    /// Loads a circle with its number of active members.
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<Circle>>;
+    /// Whether a circle already has the name, ignoring case.
+    async fn name_taken(&self, name: &str) -> DbResult<bool>;
+    /// Circles with the most active members first, then by name.
+    async fn list(&self, limit: u16, offset: u32) -> DbResult<Vec<Circle>>;
+    /// Stores the description, rules and visibility of the circle.
+    async fn update(&self, circle: &Circle) -> DbResult<()>;
+    async fn membership(&self, circle_id: &str, user_id: &str) -> DbResult<Option<CircleMember>>;
+    async fn add_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+        at: DateTime<Utc>,
+    ) -> DbResult<()>;
+    async fn set_member(
+        &self,
+        circle_id: &str,
+        user_id: &str,
+        role: CircleRole,
+        status: CircleMemberStatus,
+    ) -> DbResult<()>;
+    async fn remove_member(&self, circle_id: &str, user_id: &str) -> DbResult<()>;
+    /// Members with the status, owners first, then moderators, then members,
+    /// each by when they joined.
+    async fn members(
+        &self,
+        circle_id: &str,
+        status: CircleMemberStatus,
+    ) -> DbResult<Vec<CircleMember>>;
+}
+
+#[async_trait]
+pub trait BookmarkRepo: Send + Sync {
+    async fn insert(&self, bookmark: &BookmarkRecord) -> DbResult<()>;
+    /// The user's bookmark of a post or reply, even if the content is deleted.
+    async fn find(&self, user_id: &str, target_id: &str) -> DbResult<Option<BookmarkRecord>>;
+    async fn set_collection(&self, id: &str, collection: Option<&str>) -> DbResult<()>;
+    async fn delete(&self, id: &str) -> DbResult<()>;
+    /// Page of the user's bookmarks of live content, latest first. With a
+    /// `collection`, only the bookmarks filed under it.
+    async fn list(
+        &self,
+        user_id: &str,
+        collection: Option<&str>,
+        page: &Page,
+    ) -> DbResult<Vec<BookmarkRecord>>;
+    /// The user's collections by name, each with its number of bookmarks of
+    /// live content.
+    async fn collections(&self, user_id: &str) -> DbResult<Vec<BookmarkCollection>>;
+    /// Ids among `post_ids` of the posts the user bookmarked.
+    async fn bookmarked_posts(&self, user_id: &str, post_ids: &[String]) -> DbResult<Vec<String>>;
+}
+
+#[async_trait]
+pub trait DraftRepo: Send + Sync {
+    async fn insert(&self, draft: &PostDraft) -> DbResult<()>;
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<PostDraft>>;
+    /// Saves every field of the draft but its schedule.
+    async fn update(&self, draft: &PostDraft) -> DbResult<()>;
+    async fn schedule(&self, id: &str, publish_at: Option<DateTime<Utc>>) -> DbResult<()>;
+    /// Deletes the draft, returning whether it was still there. Publishing
+    /// claims drafts this way, so none is ever published twice.
+    async fn delete(&self, id: &str) -> DbResult<bool>;
+    /// Page of the author's drafts, last saved first.
+    async fn list(&self, author: &str, page: &Page) -> DbResult<Vec<PostDraft>>;
+    /// Up to `limit` drafts scheduled at or before `at`, earliest first.
+    async fn due(&self, at: DateTime<Utc>, limit: u16) -> DbResult<Vec<PostDraft>>;
+}
@@ -0,0 +1,220 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::{Database, Pool};
+
+use crate::config::env_or;
+
+/// Default replica lag tolerated before reads move back to the primary. Also
+/// how long a viewer keeps reading from the primary after a mutation.
+const DEFAULT_REPLICA_MAX_LAG_SECS: u64 = 5;
+/// Number of pinned viewers above which expired entries are pruned.
+const PRUNE_THRESHOLD: usize = 1024;
+
+tokio::task_local! {
+    static ROUTE: ReadRoute;
+}
+
+/// Routing state of the request running on the current task.
+struct ReadRoute {
+    primary: Cell<bool>,
+    mutated: Cell<bool>,
+}
+
+/// Runs a request with replica reads allowed unless `primary` is set. Work
+/// outside of a scope, like background tasks, always reads from the primary.
+/// Returns whether the request turned out to be a mutation.
+pub async fn scope<F: Future>(primary: bool, request: F) -> (F::Output, bool) {
+    let route = ReadRoute {
+        primary: Cell::new(primary),
+        mutated: Cell::new(false),
+    };
+    ROUTE
+        .scope(route, async {
+            let output = request.await;
+            (output, ROUTE.with(|route| route.mutated.get()))
+        })
+        .await
+}
+
+/// Sends every remaining read of the current request to the primary.
+pub fn mark_mutation() {
+    let _ = ROUTE.try_with(|route| {
+        route.primary.set(true);
+        route.mutated.set(true);
+    });
+}
+
+/// Whether reads of the current request may go to a replica.
+pub fn replica_allowed() -> bool {
+    ROUTE
+        .try_with(|route| !route.primary.get())
+        .unwrap_or(false)
+}
+
+/// Reads `REPLICA_MAX_LAG_SECS`, falling back to five seconds.
+pub fn max_replica_lag() -> Duration {
+    Duration::from_secs(env_or("REPLICA_MAX_LAG_SECS", DEFAULT_REPLICA_MAX_LAG_SECS))
+}
+
+/// Optional read replica standing in for a primary pool on read-only queries.
+#[derive(Debug)]
+pub struct ReadReplica<DB: Database> {
+    pool: Option<Pool<DB>>,
+    healthy: AtomicBool,
+}
+
+impl<DB: Database> ReadReplica<DB> {
+    pub fn new(pool: Option<Pool<DB>>) -> Self {
+        Self {
+            pool,
+            healthy: AtomicBool::new(true),
+        }
+    }
+
+    pub fn pool(&self) -> Option<&Pool<DB>> {
+        self.pool.as_ref()
+    }
+
+    /// Marks the replica usable or not. Set by the lag monitor.
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::SeqCst);
+    }
+
+    /// Runs a read-only query on the replica when the current request allows
+    /// it and the replica is healthy, and on `primary` otherwise. A failed
+    /// replica query is retried on the primary and the replica is benched
+    /// until the monitor clears it.
+    pub async fn read<T, F, Fut>(&self, primary: &Pool<DB>, query: F) -> Result<T, sqlx::Error>
+    where
+        F: Fn(Pool<DB>) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if let Some(replica) = &self.pool {
+            if replica_allowed() && self.healthy.load(Ordering::SeqCst) {
+                match query(replica.clone()).await {
+                    Ok(rows) => return Ok(rows),
+                    Err(err) => {
+                        eprintln!("REPLICA_ERROR: Falling back to primary: {err}");
+                        self.set_healthy(false);
+                    }
+                }
+            }
+        }
+
+        query(primary.clone()).await
+    }
+
+    /// Updates the replica's health from a lag measurement in seconds. `None`
+    /// means the replica is not replicating at all.
+    pub fn record_lag(&self, lag: Result<Option<f64>, sqlx::Error>, max_lag: Duration) {
+        let healthy = match lag {
+            Ok(Some(secs)) => secs <= max_lag.as_secs_f64(),
+            Ok(None) => false,
+            Err(err) => {
+                eprintln!("REPLICA_ERROR: Error measuring replica lag: {err}");
+                false
+            }
+        };
+
+        if self.healthy.swap(healthy, Ordering::SeqCst) != healthy {
+            if healthy {
+                println!("REPLICA: Replica caught up, reads resume on it");
+            } else {
+                eprintln!("REPLICA: Replica is behind or down, reading from primary");
+            }
+        }
+    }
+
+    pub async fn close(&self) {
+        if let Some(replica) = &self.pool {
+            replica.close().await;
+        }
+    }
+}
+
+/// Viewers that recently mutated something, and until when their reads must
+/// come from the primary.
+#[derive(Debug, Default)]
+pub struct RecentWriters {
+    pinned: Mutex<HashMap<String, Instant>>,
+}
+
+impl RecentWriters {
+    pub fn pin(&self, viewer: &str, window: Duration) {
+        let Ok(mut pinned) = self.pinned.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if pinned.len() > PRUNE_THRESHOLD {
+            pinned.retain(|_, until| *until > now);
+        }
+        pinned.insert(viewer.to_string(), now + window);
+    }
+
+    pub fn is_pinned(&self, viewer: &str) -> bool {
+        let Ok(pinned) = self.pinned.lock() else {
+            return true;
+        };
+        pinned
+            .get(viewer)
+            .is_some_and(|until| *until > Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_expire_after_the_lag_window() {
+        let writers = RecentWriters::default();
+        writers.pin("ada", Duration::from_millis(50));
+        assert!(writers.is_pinned("ada"));
+        assert!(!writers.is_pinned("bo"));
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(!writers.is_pinned("ada"));
+
+        // Writing again starts a new window
+        writers.pin("ada", Duration::from_secs(60));
+        assert!(writers.is_pinned("ada"));
+    }
+
+    #[test]
+    fn expired_pins_are_pruned_once_there_are_many() {
+        let writers = RecentWriters::default();
+        for viewer in 0..=PRUNE_THRESHOLD {
+            writers.pin(&viewer.to_string(), Duration::ZERO);
+        }
+        writers.pin("ada", Duration::from_secs(60));
+
+        let pinned = writers.pinned.lock().unwrap();
+        assert_eq!(pinned.keys().collect::<Vec<_>>(), ["ada"]);
+    }
+
+    #[tokio::test]
+    async fn mutations_move_the_rest_of_a_request_to_the_primary() {
+        assert!(!replica_allowed());
+
+        let (allowed, mutated) = scope(false, async {
+            let before = replica_allowed();
+            mark_mutation();
+            (before, replica_allowed())
+        })
+        .await;
+        assert_eq!(allowed, (true, false));
+        assert!(mutated);
+
+        let (allowed, mutated) = scope(true, async { replica_allowed() }).await;
+        assert!(!allowed && !mutated);
+    }
+}
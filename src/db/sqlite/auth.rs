@@ -0,0 +1,249 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row};
+
+use crate::db::{
+    AccountOperation, AccountOperationKind, AccountOperationStatus, AuthRecord, AuthRepo, DbResult,
+};
+
+use super::SqliteStore;
+
+fn auth_from_row(row: SqliteRow) -> AuthRecord {
+    AuthRecord {
+        id: row.get("id"),
+        email: row.get("email"),
+        hash: row.get("hash"),
+        community_id: row.get("community_id"),
+        refresh_token: row.get("refresh_token"),
+    }
+}
+
+fn operation_from_row(row: SqliteRow) -> DbResult<AccountOperation> {
+    Ok(AccountOperation {
+        id: row.get("id"),
+        community_id: row.get("community_id"),
+        kind: AccountOperationKind::parse(row.get("kind"))?,
+        username: row.get("username"),
+        status: AccountOperationStatus::parse(row.get("status"))?,
+        attempts: row.get("attempts"),
+        created_at: row.get("created_at"),
+    })
+}
+
+const INSERT_OPERATION: &str = r#"
+    INSERT INTO account_operations
+        (
+            id,
+            community_id,
+            kind,
+            username,
+            status,
+            attempts,
+            created_at
+        )
+    VALUES (?, ?, ?, ?, ?, ?, ?)
+"#;
+
+#[async_trait]
+impl AuthRepo for SqliteStore {
+    async fn email_exists(&self, email: &str) -> DbResult<bool> {
+        Ok(sqlx::query("SELECT id FROM auths WHERE email = ?")
+            .bind(email)
+            .fetch_optional(&self.auth_pool)
+            .await?
+            .is_some())
+    }
+
+    async fn insert(&self, auth: &AuthRecord, operation: &AccountOperation) -> DbResult<()> {
+        let mut tx = self.auth_pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO auths (id, email, hash, community_id, refresh_token) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&auth.id)
+        .bind(&auth.email)
+        .bind(&auth.hash)
+        .bind(&auth.community_id)
+        .bind(&auth.refresh_token)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(INSERT_OPERATION)
+            .bind(&operation.id)
+            .bind(&operation.community_id)
+            .bind(operation.kind.as_str())
+            .bind(&operation.username)
+            .bind(operation.status.as_str())
+            .bind(operation.attempts)
+            .bind(operation.created_at)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_by_email(&self, email: &str) -> DbResult<Option<AuthRecord>> {
+        Ok(sqlx::query("SELECT * FROM auths WHERE email = ?")
+            .bind(email)
+            .map(auth_from_row)
+            .fetch_optional(&self.auth_pool)
+            .await?)
+    }
+
+    async fn find_by_id(&self, id: &str) -> DbResult<Option<AuthRecord>> {
+        Ok(sqlx::query("SELECT * FROM auths WHERE id = ?")
+            .bind(id)
+            .map(auth_from_row)
+            .fetch_optional(&self.auth_pool)
+            .await?)
+    }
+
+    async fn set_refresh_token(&self, community_id: &str, token: &str) -> DbResult<()> {
+        sqlx::query("UPDATE auths SET refresh_token = ? WHERE community_id = ?")
+            .bind(token)
+            .bind(community_id)
+            .execute(&self.auth_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_email(&self, community_id: &str, email: &str) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE 
+                auths 
+            SET email = ? 
+            WHERE community_id = ?
+        "#,
+        )
+        .bind(email)
+        .bind(community_id)
+        .execute(&self.auth_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_hash(&self, community_id: &str, hash: &str) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE 
+                auths 
+            SET hash = ? 
+            WHERE community_id = ?
+        "#,
+        )
+        .bind(hash)
+        .bind(community_id)
+        .execute(&self.auth_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        community_id: &str,
+        operation_id: &str,
+        status: AccountOperationStatus,
+    ) -> DbResult<()> {
+        let mut tx = self.auth_pool.begin().await?;
+
+        sqlx::query("DELETE FROM auths WHERE community_id = ?")
+            .bind(community_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE account_operations SET status = ?, last_modified = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(status.as_str())
+            .bind(operation_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Auths take their `created_at` from SQLite's clock, whose format differs
+    /// from bound timestamps, so both sides are normalised before comparing.
+    async fn community_ids(&self, created_before: DateTime<Utc>) -> DbResult<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT community_id FROM auths WHERE datetime(created_at) < datetime(?)",
+        )
+        .bind(created_before)
+        .fetch_all(&self.auth_pool)
+        .await?)
+    }
+
+    async fn insert_operation(&self, operation: &AccountOperation) -> DbResult<()> {
+        sqlx::query(INSERT_OPERATION)
+            .bind(&operation.id)
+            .bind(&operation.community_id)
+            .bind(operation.kind.as_str())
+            .bind(&operation.username)
+            .bind(operation.status.as_str())
+            .bind(operation.attempts)
+            .bind(operation.created_at)
+            .execute(&self.auth_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_operation_status(&self, id: &str, status: AccountOperationStatus) -> DbResult<()> {
+        sqlx::query("UPDATE account_operations SET status = ?, last_modified = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(status.as_str())
+            .bind(id)
+            .execute(&self.auth_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn fail_operation(
+        &self,
+        id: &str,
+        status: AccountOperationStatus,
+        error: &str,
+    ) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE
+                account_operations
+            SET status = ?, attempts = attempts + 1, last_error = ?, last_modified = CURRENT_TIMESTAMP
+            WHERE id = ?
+        "#,
+        )
+        .bind(status.as_str())
+        .bind(error)
+        .bind(id)
+        .execute(&self.auth_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn open_operations(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> DbResult<Vec<AccountOperation>> {
+        sqlx::query(
+            r#"
+            SELECT
+                id,
+                community_id,
+                kind,
+                username,
+                status,
+                attempts,
+                created_at
+            FROM account_operations
+            WHERE status IN ('pending', 'compensating')
+            AND created_at < ?
+            ORDER BY created_at
+        "#,
+        )
+        .bind(created_before)
+        .fetch_all(&self.auth_pool)
+        .await?
+        .into_iter()
+        .map(operation_from_row)
+        .collect()
+    }
+}
@@ -0,0 +1,289 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::{
+    migrate::{MigrateError, Migrator},
+    query::Query,
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
+    Pool, Row, Sqlite, SqliteConnection,
+};
+
+use super::{
+    migrations::{self, MigrationStatus},
+    unit::{Conn, UnitTransaction},
+    Backend, CommunityTransaction, Cursor, DbResult, DeletionRecord, SortKey,
+};
+
+mod auth;
+mod bookmarks;
+mod circles;
+mod drafts;
+mod posts;
+mod profiles;
+mod replies;
+mod topics;
+
+/// SQLite migrations for the auth database, embedded at compile time.
+pub static SQLITE_AUTH_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite/auth");
+/// SQLite migrations for the community database, embedded at compile time.
+pub static SQLITE_COMMUNITY_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite/community");
+
+/// Repository implementation backed by SQLite, for running the API locally
+/// without MySQL. Both URLs may point at files or at `sqlite::memory:`.
+#[derive(Debug)]
+pub struct SqliteStore {
+    pub auth_pool: Pool<Sqlite>,
+    pub community_pool: Pool<Sqlite>,
+    /// Open transaction when the store is bound to a unit of work.
+    pub community_unit: Option<UnitTransaction<Sqlite>>,
+}
+
+impl SqliteStore {
+    /// Opens both databases, creating missing files. In-memory databases
+    /// start out empty every time, so they are migrated right away.
+    pub async fn connect(auth_db_url: &str, community_db_url: &str) -> Result<Self, MigrateError> {
+        let store = Self {
+            auth_pool: open(auth_db_url).await?,
+            community_pool: open(community_db_url).await?,
+            community_unit: None,
+        };
+
+        if is_in_memory(auth_db_url) {
+            migrations::run(&SQLITE_AUTH_MIGRATOR, &store.auth_pool).await?;
+        }
+        if is_in_memory(community_db_url) {
+            migrations::run(&SQLITE_COMMUNITY_MIGRATOR, &store.community_pool).await?;
+        }
+
+        Ok(store)
+    }
+
+    /// Copy of the store bound to a new community transaction.
+    async fn begin_unit(&self) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            auth_pool: self.auth_pool.clone(),
+            community_pool: self.community_pool.clone(),
+            community_unit: Some(UnitTransaction::begin(&self.community_pool).await?),
+        })
+    }
+
+    /// Connection for community queries, inside the unit of work when the
+    /// store is bound to one.
+    async fn community(&self) -> Result<Conn<'_, Sqlite>, sqlx::Error> {
+        Conn::acquire(&self.community_pool, self.community_unit.as_ref()).await
+    }
+}
+
+fn is_in_memory(url: &str) -> bool {
+    url.contains(":memory:") || url.contains("mode=memory")
+}
+
+async fn open(url: &str) -> Result<Pool<Sqlite>, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+
+    // An in-memory database lives only as long as a connection to it, so the
+    // pool never lets its connections expire.
+    let pool = if is_in_memory(url) {
+        SqlitePoolOptions::new()
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        SqlitePoolOptions::new()
+    };
+
+    pool.connect_with(options).await
+}
+
+fn deletion_from_row(row: SqliteRow) -> DeletionRecord {
+    DeletionRecord {
+        author: row.get("author"),
+        deleted_by: row.get("deleted_by"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Binds the sort key and id of a page cursor, in the order [`Page::sql`]
+/// hands out their placeholders.
+fn bind_cursor<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    cursor: &'q Cursor,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match &cursor.key {
+        SortKey::Time(at) => query.bind(*at),
+        SortKey::Score(score) => query.bind(*score),
+    }
+    .bind(&cursor.id)
+}
+
+/// Shifts the counters of a post inside the caller's transaction. Ids that
+/// are not posts match nothing.
+async fn adjust_counts(
+    conn: &mut SqliteConnection,
+    post_id: &str,
+    likes: i32,
+    replies: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE expression_posts
+        SET like_count = like_count + ?, reply_count = reply_count + ?
+        WHERE id = ?
+    "#,
+    )
+    .bind(likes)
+    .bind(replies)
+    .bind(post_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn ping(pool: &Pool<Sqlite>) -> Result<(), String> {
+    if let Err(err) = sqlx::query("SELECT 1").execute(pool).await {
+        return Err(err.to_string());
+    }
+
+    Ok(())
+}
+
+async fn status(
+    migrator: &Migrator,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let applied = if count > 0 {
+        migrations::applied(pool).await?
+    } else {
+        vec![]
+    };
+    Ok(migrations::status(migrator, &applied))
+}
+
+#[async_trait]
+impl Backend for SqliteStore {
+    async fn close(&self) {
+        self.auth_pool.close().await;
+        self.community_pool.close().await;
+    }
+
+    async fn ping_auth(&self) -> Result<(), String> {
+        ping(&self.auth_pool).await
+    }
+
+    async fn ping_community(&self) -> Result<(), String> {
+        ping(&self.community_pool).await
+    }
+
+    async fn migrate_up(&self) -> Result<(), MigrateError> {
+        migrations::run(&SQLITE_AUTH_MIGRATOR, &self.auth_pool).await?;
+        migrations::run(&SQLITE_COMMUNITY_MIGRATOR, &self.community_pool).await
+    }
+
+    async fn migrate_down(&self) -> Result<(Option<i64>, Option<i64>), MigrateError> {
+        Ok((
+            migrations::revert_latest(&SQLITE_AUTH_MIGRATOR, &self.auth_pool).await?,
+            migrations::revert_latest(&SQLITE_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    async fn migration_status(
+        &self,
+    ) -> Result<(Vec<MigrationStatus>, Vec<MigrationStatus>), MigrateError> {
+        Ok((
+            status(&SQLITE_AUTH_MIGRATOR, &self.auth_pool).await?,
+            status(&SQLITE_COMMUNITY_MIGRATOR, &self.community_pool).await?,
+        ))
+    }
+
+    fn has_replicas(&self) -> bool {
+        false
+    }
+
+    async fn check_replicas(&self, _max_lag: Duration) {}
+
+    async fn begin_community(&self) -> DbResult<Arc<dyn CommunityTransaction>> {
+        Ok(Arc::new(self.begin_unit().await?))
+    }
+}
+
+/// Stores that are not bound to a unit of work commit every call on its own,
+/// so there is nothing left to commit or roll back.
+#[async_trait]
+impl CommunityTransaction for SqliteStore {
+    async fn commit(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.commit().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback(&self) -> DbResult<()> {
+        match &self.community_unit {
+            Some(unit) => unit.rollback().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tables of the database other than the migrations table and SQLite's own.
+    async fn tables(pool: &Pool<Sqlite>) -> Vec<String> {
+        sqlx::query_scalar(
+            r#"
+            SELECT name FROM sqlite_master
+            WHERE type = 'table' AND name NOT LIKE '\_sqlx%' ESCAPE '\' AND name NOT LIKE 'sqlite\_%' ESCAPE '\'
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    /// Applies every migration, reverts them one at a time down to an empty
+    /// database, then applies them again.
+    async fn round_trip(migrator: &Migrator) {
+        let pool = open("sqlite::memory:").await.unwrap();
+
+        migrations::run(migrator, &pool).await.unwrap();
+        let migrated = tables(&pool).await;
+        assert!(!migrated.is_empty());
+
+        let mut reverted = vec![];
+        while let Some(version) = migrations::revert_latest(migrator, &pool).await.unwrap() {
+            reverted.push(version);
+        }
+        let mut expected: Vec<i64> = migrator
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| migration.version)
+            .collect();
+        expected.reverse();
+        assert_eq!(reverted, expected);
+        assert!(tables(&pool).await.is_empty());
+
+        migrations::run(migrator, &pool).await.unwrap();
+        assert_eq!(tables(&pool).await, migrated);
+        let applied = migrations::applied(&pool).await.unwrap();
+        migrations::ensure_current(&migrations::status(migrator, &applied)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn auth_migrations_round_trip() {
+        round_trip(&SQLITE_AUTH_MIGRATOR).await;
+    }
+
+    #[tokio::test]
+    async fn community_migrations_round_trip() {
+        round_trip(&SQLITE_COMMUNITY_MIGRATOR).await;
+    }
+}
@@ -0,0 +1,552 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    query::Query,
+    sqlite::{SqliteArguments, SqliteRow},
+    Connection, Row, Sqlite,
+};
+
+use crate::{
+    community::{ExpressionPost, UserProfile},
+    db::{
+        self, DbResult, DeletionRecord, NewPostRecord, Page, PostCounts, PostFeed, PostRepo,
+        PostSearch, SearchHit,
+    },
+};
+
+use super::{adjust_counts, bind_cursor, deletion_from_row, SqliteStore};
+
+/// Columns shared by every query that loads full posts. Expects the post and
+/// its author to be aliased `post` and `profile`. Tags are read back as a
+/// comma separated list.
+const POST_COLUMNS: &str = r#"
+    post.id AS id, 
+    post.title AS title, 
+    post.subtitle AS subtitle, 
+    post.cover_image AS cover_image,
+    profile.id AS author_id, 
+    profile.username AS author_username, 
+    profile.avatar AS author_avatar, 
+    post.content_type AS content_type, 
+    post.content_value AS content_value,
+    post.reply_count AS reply_count, 
+    post.like_count AS like_count, 
+    post.created_at AS created_at, 
+    post.last_modified AS last_modified,
+    post.hot_score AS hot_score,
+    post.circle_id AS circle_id,
+    (
+        SELECT group_concat(topic, ',')
+        FROM (SELECT topic FROM post_tags WHERE post_id = post.id ORDER BY topic)
+    ) AS tags
+"#;
+
+fn post_from_row(row: SqliteRow) -> ExpressionPost {
+    ExpressionPost::new(
+        row.get("id"),
+        row.get("title"),
+        row.get("subtitle"),
+        row.get("cover_image"),
+        UserProfile::new(
+            row.get("author_id"),
+            row.get("author_username"),
+            row.get("author_avatar"),
+            vec![],
+        ),
+        row.get("content_type"),
+        row.get("content_value"),
+        vec![],
+        row.get("reply_count"),
+        row.get("like_count"),
+        row.get("created_at"),
+        row.get("last_modified"),
+    )
+    .with_hot_score(row.get("hot_score"))
+    .with_circle(row.get("circle_id"))
+    .with_tags(db::split_tags(row.get("tags")))
+}
+
+/// Feed filter for posts since the time bound first, by authors the viewer
+/// bound second follows or tagged with topics the viewer bound third follows.
+const HOME_FILTER: &str = r#"
+                AND post.circle_id IS NULL
+                AND post.created_at > ?
+                AND (
+                    post.author IN (SELECT followee_id FROM user_follows WHERE follower_id = ?)
+                    OR EXISTS (
+                        SELECT 1 FROM post_tags AS tag
+                        JOIN topic_follows AS follow ON follow.topic = tag.topic
+                        WHERE tag.post_id = post.id AND follow.user_id = ?
+                    )
+                )"#;
+
+/// Query for a page of a feed. Its parameters are bound by `feed_query`.
+fn feed_sql(feed: &PostFeed, page: &Page) -> String {
+    let (filter, key) = match feed {
+        PostFeed::Recent => (" AND post.circle_id IS NULL", "post.created_at"),
+        PostFeed::Trending { .. } => (
+            " AND post.circle_id IS NULL AND post.created_at > ?",
+            "post.hot_score",
+        ),
+        PostFeed::ByAuthor(_) => (
+            " AND post.circle_id IS NULL AND post.author = ?",
+            "post.created_at",
+        ),
+        PostFeed::ByTag(_) => (
+            " AND post.circle_id IS NULL AND EXISTS (SELECT 1 FROM post_tags AS tag WHERE tag.post_id = post.id AND tag.topic = ?)",
+            "post.created_at",
+        ),
+        PostFeed::CircleRecent(_) => (" AND post.circle_id = ?", "post.created_at"),
+        PostFeed::CircleTrending { .. } => (
+            " AND post.circle_id = ? AND post.created_at > ?",
+            "post.hot_score",
+        ),
+        PostFeed::Home { .. } => (HOME_FILTER, "post.hot_score"),
+    };
+    let (keyset, order) = page.sql(key, "post.id", true, || "?".to_string());
+    format!(
+        r#"
+            SELECT {POST_COLUMNS}
+            FROM expression_posts AS post
+            LEFT JOIN user_profiles AS profile ON profile.id = post.author
+            WHERE post.deleted_at IS NULL{filter}{keyset}
+            ORDER BY {order}
+            LIMIT ?
+        "#
+    )
+}
+
+fn feed_query<'q>(
+    sql: &'q str,
+    feed: &'q PostFeed,
+    page: &'q Page,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let mut query = sqlx::query(sql);
+    match feed {
+        PostFeed::Recent => {}
+        PostFeed::Trending { since } => query = query.bind(*since),
+        PostFeed::ByAuthor(author) => query = query.bind(author),
+        PostFeed::ByTag(topic) => query = query.bind(topic),
+        PostFeed::CircleRecent(circle) => query = query.bind(circle),
+        PostFeed::CircleTrending { circle, since } => query = query.bind(circle).bind(*since),
+        PostFeed::Home { viewer, since } => query = query.bind(*since).bind(viewer).bind(viewer),
+    }
+    for cursor in page.cursors() {
+        query = bind_cursor(query, cursor);
+    }
+    query.bind(i64::from(page.limit))
+}
+
+/// Query for a page of search results. Replies that match are walked up
+/// their thread to the post it belongs to. Title matches weigh the most and
+/// content matches the least. Its parameters are bound by `search_query`.
+fn search_sql(search: &PostSearch) -> String {
+    let filters = search_filters(search, "post.content_type", || "?".to_string());
+    let (limit, offset) = ("?", "?");
+    format!(
+        r#"
+            WITH RECURSIVE
+            reply_hits (content, score, parent) AS (
+                SELECT reply.content, -bm25(reply_search), reply.parent
+                FROM reply_search
+                JOIN replies AS reply ON reply.rowid = reply_search.rowid
+                WHERE reply_search MATCH ? AND reply.deleted_at IS NULL
+                UNION ALL
+                SELECT hit.content, hit.score, reply.parent
+                FROM reply_hits AS hit
+                JOIN replies AS reply ON reply.id = hit.parent
+            ),
+            hits (post_id, score, reply) AS (
+                SELECT post.id, -bm25(post_search, 10.0, 5.0, 1.0), NULL
+                FROM post_search
+                JOIN expression_posts AS post ON post.rowid = post_search.rowid
+                WHERE post_search MATCH ?
+                UNION ALL
+                SELECT parent, score * 0.5, content FROM reply_hits
+            ),
+            ranked AS (
+                SELECT
+                    post_id,
+                    score,
+                    reply,
+                    ROW_NUMBER() OVER (PARTITION BY post_id ORDER BY score DESC) AS position
+                FROM hits
+            )
+            SELECT {POST_COLUMNS}, ranked.score AS score, ranked.reply AS matched_reply
+            FROM ranked
+            JOIN expression_posts AS post ON post.id = ranked.post_id
+            LEFT JOIN user_profiles AS profile ON profile.id = post.author
+            WHERE ranked.position = 1
+                AND post.deleted_at IS NULL
+                AND post.circle_id IS NULL{filters}
+            ORDER BY ranked.score DESC, post.id DESC
+            LIMIT {limit} OFFSET {offset}
+        "#
+    )
+}
+
+/// Conditions, each starting with `AND`, for the filters a search sets.
+fn search_filters(
+    search: &PostSearch,
+    content_type: &str,
+    mut placeholder: impl FnMut() -> String,
+) -> String {
+    let mut filters = String::new();
+    if search.author.is_some() {
+        filters.push_str(&format!(" AND post.author = {}", placeholder()));
+    }
+    if search.created_after.is_some() {
+        filters.push_str(&format!(" AND post.created_at >= {}", placeholder()));
+    }
+    if search.created_before.is_some() {
+        filters.push_str(&format!(" AND post.created_at < {}", placeholder()));
+    }
+    if search.content_type.is_some() {
+        filters.push_str(&format!(" AND {content_type} = {}", placeholder()));
+    }
+    filters
+}
+
+fn search_query<'q>(
+    sql: &'q str,
+    text: &'q str,
+    search: &'q PostSearch,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let mut query = sqlx::query(sql).bind(text).bind(text);
+    if let Some(author) = &search.author {
+        query = query.bind(author);
+    }
+    if let Some(after) = search.created_after {
+        query = query.bind(after);
+    }
+    if let Some(before) = search.created_before {
+        query = query.bind(before);
+    }
+    if let Some(content_type) = &search.content_type {
+        query = query.bind(content_type);
+    }
+    query
+        .bind(i64::from(search.limit))
+        .bind(i64::from(search.offset))
+}
+
+fn hit_from_row(row: SqliteRow) -> SearchHit {
+    SearchHit {
+        score: row.get("score"),
+        reply: row.get("matched_reply"),
+        post: post_from_row(row),
+    }
+}
+
+#[async_trait]
+impl PostRepo for SqliteStore {
+    async fn get_by_id(&self, id: &str) -> DbResult<Option<ExpressionPost>> {
+        Ok(sqlx::query(&format!(
+            r#"
+            SELECT {POST_COLUMNS}
+            FROM expression_posts AS post
+            LEFT JOIN user_profiles AS profile ON profile.id = post.author
+            WHERE post.id = ? AND post.deleted_at IS NULL
+        "#
+        ))
+        .bind(id)
+        .map(post_from_row)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn author_of(&self, id: &str) -> DbResult<Option<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT author FROM expression_posts WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn insert(&self, post: &NewPostRecord) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO expression_posts
+                (
+                    id, 
+                    title, 
+                    subtitle,
+                    cover_image,
+                    author, 
+                    content_type, 
+                    content_value,
+                    created_at,
+                    last_modified,
+                    circle_id
+                ) 
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(&post.id)
+        .bind(&post.title)
+        .bind(&post.subtitle)
+        .bind(&post.cover_image)
+        .bind(&post.author)
+        .bind(&post.content_type)
+        .bind(&post.content_value)
+        .bind(post.created_at)
+        .bind(post.created_at)
+        .bind(&post.circle_id)
+        .execute(&mut *tx)
+        .await?;
+        for tag in &post.tags {
+            sqlx::query("INSERT INTO post_tags (post_id, topic) VALUES (?, ?)")
+                .bind(&post.id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update_content(
+        &self,
+        id: &str,
+        content_type: &str,
+        content_value: &str,
+    ) -> DbResult<()> {
+        // SQLite cannot refresh last_modified on its own
+        sqlx::query(
+            r#"
+            UPDATE expression_posts
+            SET content_type = ?, content_value = ?, last_modified = ?
+            WHERE id = ?
+        "#,
+        )
+        .bind(content_type)
+        .bind(content_value)
+        .bind(db::now())
+        .bind(id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn soft_delete(&self, id: &str, deleted_by: &str, at: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE expression_posts 
+            SET deleted_at = ?, deleted_by = ?, last_modified = ? 
+            WHERE id = ? AND deleted_at IS NULL
+        "#,
+        )
+        .bind(at)
+        .bind(deleted_by)
+        .bind(db::now())
+        .bind(id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn restore(&self, id: &str) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE expression_posts SET deleted_at = NULL, deleted_by = NULL, last_modified = ? WHERE id = ?
+        "#,
+        )
+        .bind(db::now())
+        .bind(id)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn deletion(&self, id: &str) -> DbResult<Option<DeletionRecord>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT author, deleted_by, deleted_at 
+            FROM expression_posts 
+            WHERE id = ? AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .map(deletion_from_row)
+        .fetch_optional(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM likes 
+            WHERE parent_id IN (SELECT id FROM expression_posts WHERE deleted_at < ?)
+        "#,
+        )
+        .bind(before)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE thread (id) AS (
+                SELECT id FROM replies
+                WHERE parent IN (SELECT id FROM expression_posts WHERE deleted_at < ?)
+                UNION ALL
+                SELECT child.id FROM replies AS child JOIN thread ON child.parent = thread.id
+            )
+            DELETE FROM replies WHERE id IN (SELECT id FROM thread)
+        "#,
+        )
+        .bind(before)
+        .execute(&mut *tx)
+        .await?;
+
+        let purged = sqlx::query("DELETE FROM expression_posts WHERE deleted_at < ?")
+            .bind(before)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(purged)
+    }
+
+    async fn add_like(&self, post_id: &str, author: &str) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        let added = sqlx::query(
+            r#"
+                INSERT OR IGNORE INTO likes
+                    (
+                        parent_id, 
+                        author
+                    ) VALUES (?, ?)
+            "#,
+        )
+        .bind(post_id)
+        .bind(author)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if added > 0 {
+            adjust_counts(&mut tx, post_id, added as i32, 0).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_like(&self, post_id: &str, author: &str) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+
+        let removed = sqlx::query(
+            r#"
+                DELETE FROM likes 
+                WHERE parent_id = ? 
+                AND author = ?
+            "#,
+        )
+        .bind(post_id)
+        .bind(author)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if removed > 0 {
+            adjust_counts(&mut tx, post_id, -(removed as i32), 0).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn repair_counts(&self) -> DbResult<u64> {
+        Ok(sqlx::query(
+            r#"
+            UPDATE expression_posts AS post
+            SET like_count = actual.like_count, reply_count = actual.reply_count
+            FROM (
+                SELECT 
+                    counted.id AS id,
+                    (
+                        SELECT COUNT(*) FROM likes WHERE parent_id = counted.id
+                    ) AS like_count,
+                    (
+                        SELECT COUNT(*) FROM replies 
+                        WHERE parent = counted.id AND deleted_at IS NULL
+                    ) AS reply_count
+                FROM expression_posts AS counted
+            ) AS actual
+            WHERE actual.id = post.id 
+            AND (post.like_count <> actual.like_count OR post.reply_count <> actual.reply_count)
+        "#,
+        )
+        .execute(&mut *self.community().await?)
+        .await?
+        .rows_affected())
+    }
+
+    async fn feed(&self, feed: &PostFeed, page: &Page) -> DbResult<Vec<ExpressionPost>> {
+        let sql = feed_sql(feed, page);
+        let posts = feed_query(&sql, feed, page)
+            .map(post_from_row)
+            .fetch_all(&mut *self.community().await?)
+            .await?;
+        Ok(page.finish(posts))
+    }
+
+    async fn counts_since(&self, since: DateTime<Utc>) -> DbResult<Vec<PostCounts>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT id, like_count, reply_count, created_at, hot_score
+            FROM expression_posts
+            WHERE created_at > ? AND deleted_at IS NULL
+        "#,
+        )
+        .bind(since)
+        .map(|row: SqliteRow| PostCounts {
+            id: row.get("id"),
+            likes: row.get("like_count"),
+            replies: row.get("reply_count"),
+            created_at: row.get("created_at"),
+            hot_score: row.get("hot_score"),
+        })
+        .fetch_all(&mut *self.community().await?)
+        .await?)
+    }
+
+    async fn set_hot_scores(&self, scores: &[(String, f64)]) -> DbResult<()> {
+        let mut conn = self.community().await?;
+        let mut tx = conn.begin().await?;
+        for (id, score) in scores {
+            sqlx::query("UPDATE expression_posts SET hot_score = ? WHERE id = ?")
+                .bind(score)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn search(&self, search: &PostSearch) -> DbResult<Vec<SearchHit>> {
+        // Quoted words are matched as they are and all of them are required
+        let text = search
+            .terms
+            .iter()
+            .map(|term| format!("\"{term}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let sql = search_sql(search);
+        Ok(search_query(&sql, &text, search)
+            .map(hit_from_row)
+            .fetch_all(&mut *self.community().await?)
+            .await?)
+    }
+}
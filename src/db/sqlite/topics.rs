@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row};
+
+use crate::{
+    community::Topic,
+    db::{DbResult, TopicRepo},
+};
+
+use super::SqliteStore;
+
+/// Topics matching `condition`, with the number of live posts tagged with
+/// each. `join` may bring in another table for the condition.
+fn topics_sql(join: &str, condition: &str, order: &str) -> String {
+    format!(
+        r#"
+            SELECT topic.name AS name, COUNT(post.id) AS post_count
+            FROM topics AS topic
+            {join}
+            LEFT JOIN post_tags AS tag ON tag.topic = topic.name
+            LEFT JOIN expression_posts AS post
+                ON post.id = tag.post_id
+                AND post.deleted_at IS NULL
+                AND post.circle_id IS NULL
+            WHERE {condition}
+            GROUP BY topic.name
+            ORDER BY {order}
+        "#
+    )
+}
+
+fn topic_from_row(row: SqliteRow) -> Topic {
+    Topic {
+        name: row.get("name"),
+        post_count: row.get("post_count"),
+    }
+}
+
+#[async_trait]
+impl TopicRepo for SqliteStore {
+    async fn list(&self, prefix: &str, limit: u16) -> DbResult<Vec<Topic>> {
+        let query = format!(
+            "{} LIMIT ?",
+            topics_sql("", "topic.name LIKE ?", "post_count DESC, topic.name ASC")
+        );
+        Ok(sqlx::query(&query)
+            .bind(format!("{prefix}%"))
+            .bind(i64::from(limit))
+            .map(topic_from_row)
+            .fetch_all(&mut *self.community().await?)
+            .await?)
+    }
+
+    async fn existing(&self, names: &[String]) -> DbResult<Vec<String>> {
+        let query = format!(
+            "SELECT name FROM topics WHERE name IN ({})",
+            vec!["?"; names.len()].join(", ")
+        );
+        let mut query = sqlx::query_scalar(&query);
+        for name in names {
+            query = query.bind(name);
+        }
+        Ok(query.fetch_all(&mut *self.community().await?).await?)
+    }
+
+    async fn insert(&self, name: &str, created_by: &str, at: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query("INSERT INTO topics (name, created_by, created_at) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(created_by)
+            .bind(at)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn follow(&self, user_id: &str, topic: &str, at: DateTime<Utc>) -> DbResult<()> {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO topic_follows (user_id, topic, created_at)
+            VALUES (?, ?, ?)
+        "#,
+        )
+        .bind(user_id)
+        .bind(topic)
+        .bind(at)
+        .execute(&mut *self.community().await?)
+        .await?;
+        Ok(())
+    }
+
+    async fn unfollow(&self, user_id: &str, topic: &str) -> DbResult<()> {
+        sqlx::query("DELETE FROM topic_follows WHERE user_id = ? AND topic = ?")
+            .bind(user_id)
+            .bind(topic)
+            .execute(&mut *self.community().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn followed(&self, user_id: &str) -> DbResult<Vec<Topic>> {
+        let query = topics_sql(
+            "JOIN topic_follows AS follow ON follow.topic = topic.name",
+            "follow.user_id = ?",
+            "topic.name ASC",
+        );
+        Ok(sqlx::query(&query)
+            .bind(user_id)
+            .map(topic_from_row)
+            .fetch_all(&mut *self.community().await?)
+            .await?)
+    }
+}
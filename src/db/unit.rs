@@ -0,0 +1,121 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    ops::{Deref, DerefMut},
+};
+
+use sqlx::{pool::PoolConnection, Database, Pool, Transaction};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+use super::{DbError, DbResult};
+
+tokio::task_local! {
+    static CONFLICT: Cell<bool>;
+}
+
+/// Runs one attempt of a unit of work. Returns whether any query in it
+/// failed on a deadlock or lock timeout, in which case it is worth retrying.
+pub async fn attempt<F: Future>(work: F) -> (F::Output, bool) {
+    CONFLICT
+        .scope(Cell::new(false), async {
+            let output = work.await;
+            (output, CONFLICT.with(Cell::get))
+        })
+        .await
+}
+
+/// Flags the current unit of work for a retry.
+pub fn note_conflict() {
+    let _ = CONFLICT.try_with(|conflict| conflict.set(true));
+}
+
+/// Whether the error is a deadlock or lock timeout that a retry of the whole
+/// transaction can get past.
+pub fn is_conflict(err: &sqlx::Error) -> bool {
+    let Some(err) = err.as_database_error() else {
+        return false;
+    };
+    // MySQL reports lock wait timeouts with a generic SQLSTATE
+    if let Some(err) = err.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+        return matches!(err.number(), 1205 | 1213);
+    }
+    // Serialization failure and deadlock on Postgres, busy and locked on SQLite
+    matches!(
+        err.code().as_deref(),
+        Some("40001" | "40P01" | "5" | "6" | "517")
+    )
+}
+
+/// Community transaction shared by every repository call made through one
+/// unit of work.
+#[derive(Debug)]
+pub struct UnitTransaction<DB: Database> {
+    tx: Mutex<Option<Transaction<'static, DB>>>,
+}
+
+impl<DB: Database> UnitTransaction<DB> {
+    pub async fn begin(pool: &Pool<DB>) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            tx: Mutex::new(Some(pool.begin().await?)),
+        })
+    }
+
+    pub async fn commit(&self) -> DbResult<()> {
+        match self.tx.lock().await.take() {
+            Some(tx) => Ok(tx.commit().await?),
+            None => Err(finished()),
+        }
+    }
+
+    pub async fn rollback(&self) -> DbResult<()> {
+        match self.tx.lock().await.take() {
+            Some(tx) => Ok(tx.rollback().await?),
+            None => Err(finished()),
+        }
+    }
+}
+
+fn finished() -> DbError {
+    DbError::Backend("The unit of work has already finished".to_string())
+}
+
+/// Connection a community query runs on: the open transaction of a unit of
+/// work, or one taken from the primary pool.
+pub enum Conn<'a, DB: Database> {
+    Pooled(PoolConnection<DB>),
+    Unit(MappedMutexGuard<'a, DB::Connection>),
+}
+
+impl<'a, DB: Database> Conn<'a, DB> {
+    pub async fn acquire(
+        pool: &Pool<DB>,
+        unit: Option<&'a UnitTransaction<DB>>,
+    ) -> Result<Self, sqlx::Error> {
+        let Some(unit) = unit else {
+            return Ok(Conn::Pooled(pool.acquire().await?));
+        };
+        MutexGuard::try_map(unit.tx.lock().await, |tx| tx.as_deref_mut())
+            .map(Conn::Unit)
+            .map_err(|_| sqlx::Error::Protocol("The unit of work has already finished".into()))
+    }
+}
+
+impl<DB: Database> Deref for Conn<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Unit(conn) => conn,
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for Conn<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Unit(conn) => conn,
+        }
+    }
+}
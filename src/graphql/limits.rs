@@ -0,0 +1,223 @@
+use std::{collections::HashSet, sync::Arc};
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery},
+    parser::types::{ExecutableDocument, Selection, SelectionSet},
+    ServerError, ServerResult, Variables,
+};
+
+use crate::config::env_or;
+
+const DEFAULT_MAX_DEPTH: usize = 10;
+const DEFAULT_MAX_COMPLEXITY: usize = 1000;
+const DEFAULT_MAX_ALIASES: usize = 15;
+const DEFAULT_MAX_FIELDS: usize = 200;
+
+/// Limits applied to every incoming operation. Depth and complexity are
+/// enforced by async-graphql itself, alias and field counts by the extension
+/// implemented below.
+#[derive(Debug, Clone)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+    pub max_aliases: usize,
+    pub max_fields: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_complexity: DEFAULT_MAX_COMPLEXITY,
+            max_aliases: DEFAULT_MAX_ALIASES,
+            max_fields: DEFAULT_MAX_FIELDS,
+        }
+    }
+}
+
+impl QueryLimits {
+    /// Reads `GRAPHQL_MAX_DEPTH`, `GRAPHQL_MAX_COMPLEXITY`, `GRAPHQL_MAX_ALIASES`
+    /// and `GRAPHQL_MAX_FIELDS`, keeping the defaults for unset values.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_depth: env_or("GRAPHQL_MAX_DEPTH", defaults.max_depth),
+            max_complexity: env_or("GRAPHQL_MAX_COMPLEXITY", defaults.max_complexity),
+            max_aliases: env_or("GRAPHQL_MAX_ALIASES", defaults.max_aliases),
+            max_fields: env_or("GRAPHQL_MAX_FIELDS", defaults.max_fields),
+        }
+    }
+}
+
+impl ExtensionFactory for QueryLimits {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryLimitsExtension {
+            limits: self.clone(),
+        })
+    }
+}
+
+struct QueryLimitsExtension {
+    limits: QueryLimits,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for QueryLimitsExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let mut counter = SelectionCounter::new(&document, self.limits.max_fields);
+        for (_, operation) in document.operations.iter() {
+            counter.visit(&operation.node.selection_set.node);
+        }
+
+        if counter.aliases > self.limits.max_aliases {
+            return Err(ServerError::new("Query uses too many aliases.", None));
+        }
+        if counter.fields > self.limits.max_fields {
+            return Err(ServerError::new("Query selects too many fields.", None));
+        }
+
+        Ok(document)
+    }
+}
+
+/// Counts fields and aliases across every operation, expanding fragment
+/// spreads each time they are used so fan-out through fragments is counted.
+struct SelectionCounter<'a> {
+    document: &'a ExecutableDocument,
+    active_fragments: HashSet<&'a str>,
+    max_fields: usize,
+    aliases: usize,
+    fields: usize,
+}
+
+impl<'a> SelectionCounter<'a> {
+    fn new(document: &'a ExecutableDocument, max_fields: usize) -> Self {
+        Self {
+            document,
+            active_fragments: HashSet::new(),
+            max_fields,
+            aliases: 0,
+            fields: 0,
+        }
+    }
+
+    fn visit(&mut self, selection_set: &'a SelectionSet) {
+        for selection in &selection_set.items {
+            // Stop expanding once the operation is already over the limit
+            if self.fields > self.max_fields {
+                return;
+            }
+
+            match &selection.node {
+                Selection::Field(field) => {
+                    self.fields += 1;
+                    if field.node.alias.is_some() {
+                        self.aliases += 1;
+                    }
+                    self.visit(&field.node.selection_set.node);
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.visit(&fragment.node.selection_set.node);
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    // Cyclic fragments are rejected during validation; skip them here
+                    if !self.active_fragments.insert(name) {
+                        continue;
+                    }
+                    if let Some(fragment) = self.document.fragments.get(name) {
+                        self.visit(&fragment.node.selection_set.node);
+                    }
+                    self.active_fragments.remove(name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{parser::parse_query, EmptyMutation, EmptySubscription, Object, Schema};
+
+    use super::*;
+
+    fn count(query: &str, max_fields: usize) -> (usize, usize) {
+        let document = parse_query(query).unwrap();
+        let mut counter = SelectionCounter::new(&document, max_fields);
+        for (_, operation) in document.operations.iter() {
+            counter.visit(&operation.node.selection_set.node);
+        }
+        (counter.fields, counter.aliases)
+    }
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn value(&self) -> i32 {
+            1
+        }
+    }
+
+    #[test]
+    fn counts_fields_and_aliases_of_every_operation() {
+        let query = r#"
+            query Posts { first: post { id title } second: post { id } }
+            query Profile { profile { id ... on Profile { username } } }
+        "#;
+        assert_eq!(count(query, 200), (8, 2));
+    }
+
+    #[test]
+    fn fragment_spreads_count_every_time_they_are_used() {
+        let query = r#"
+            query { a: post { ...Card } b: post { ...Card } }
+            fragment Card on Post { id title author { ...Name } }
+            fragment Name on Profile { username }
+        "#;
+        assert_eq!(count(query, 200), (10, 2));
+    }
+
+    #[test]
+    fn counting_stops_once_over_the_field_limit() {
+        let query = "{ a b c d e f g h }";
+        assert_eq!(count(query, 3), (4, 0));
+    }
+
+    #[tokio::test]
+    async fn operations_over_a_limit_are_rejected() {
+        let limits = QueryLimits {
+            max_aliases: 2,
+            max_fields: 4,
+            ..QueryLimits::default()
+        };
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .extension(limits)
+            .finish();
+        let error = |query: &'static str| {
+            let schema = &schema;
+            async move {
+                let response = schema.execute(query).await;
+                response.errors.first().map(|err| err.message.clone())
+            }
+        };
+
+        assert_eq!(error("{ a: value b: value value }").await, None);
+        assert_eq!(
+            error("{ a: value b: value c: value }").await.as_deref(),
+            Some("Query uses too many aliases.")
+        );
+        assert_eq!(
+            error("{ value value value value value }").await.as_deref(),
+            Some("Query selects too many fields.")
+        );
+    }
+}
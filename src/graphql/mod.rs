@@ -0,0 +1,129 @@
+use std::error::Error;
+
+use async_graphql::{
+    extensions::apollo_persisted_queries::{ApolloPersistedQueries, LruCacheStorage},
+    EmptySubscription, MergedObject, Schema, SchemaBuilder,
+};
+
+use crate::{auth, community, config::env_or};
+
+mod limits;
+mod persisted;
+mod routing;
+
+pub use limits::QueryLimits;
+pub use persisted::{Allowlist, PersistedQueries};
+pub use routing::ReadRouting;
+
+/// Query root of `/auth`. Wraps the auth queries so the endpoint keeps
+/// exposing them as `Query`, whatever name they merge under.
+#[derive(MergedObject, Default)]
+#[graphql(name = "Query")]
+pub struct AuthQuery(auth::Query);
+
+/// Mutation root of `/auth`.
+#[derive(MergedObject, Default)]
+#[graphql(name = "Mutation")]
+pub struct AuthMutation(auth::Mutation);
+
+/// Query root of `/community`.
+#[derive(MergedObject, Default)]
+#[graphql(name = "Query")]
+pub struct CommunityQuery(community::Query);
+
+/// Mutation root of `/community`.
+#[derive(MergedObject, Default)]
+#[graphql(name = "Mutation")]
+pub struct CommunityMutation(community::Mutation);
+
+/// Query root of the single `/graphql` endpoint, combining the auth and
+/// community queries.
+#[derive(MergedObject, Default)]
+pub struct Query(auth::Query, community::Query);
+
+/// Mutation root of the single `/graphql` endpoint, combining the auth and
+/// community mutations.
+#[derive(MergedObject, Default)]
+pub struct Mutation(auth::Mutation, community::Mutation);
+
+pub type AuthSchema = Schema<AuthQuery, AuthMutation, EmptySubscription>;
+pub type CommunitySchema = Schema<CommunityQuery, CommunityMutation, EmptySubscription>;
+pub type UnifiedSchema = Schema<Query, Mutation, EmptySubscription>;
+
+/// Server-wide GraphQL settings shared by every schema.
+#[derive(Debug, Clone, Default)]
+pub struct GraphQLConfig {
+    pub limits: QueryLimits,
+    pub persisted_queries: PersistedQueries,
+    /// Serves the merged schema on `/graphql` alongside `/auth` and `/community`.
+    pub unified_endpoint: bool,
+}
+
+impl GraphQLConfig {
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            limits: QueryLimits::from_env(),
+            persisted_queries: PersistedQueries::from_env()?,
+            unified_endpoint: env_or("UNIFIED_GRAPHQL_ENDPOINT", false),
+        })
+    }
+}
+
+/// Applies the shared settings to a schema. Each schema gets its own APQ
+/// cache so a hash registered on one endpoint never resolves on another.
+pub fn configure<Query, Mutation, Subscription>(
+    mut builder: SchemaBuilder<Query, Mutation, Subscription>,
+    config: &GraphQLConfig,
+) -> SchemaBuilder<Query, Mutation, Subscription> {
+    let persisted = &config.persisted_queries;
+    if let Some(queries) = &persisted.allowlist {
+        builder = builder.extension(Allowlist::new(queries.clone(), persisted.strict));
+    }
+
+    builder
+        .extension(ReadRouting)
+        .extension(ApolloPersistedQueries::new(LruCacheStorage::new(
+            persisted.cache_size,
+        )))
+        .limit_depth(config.limits.max_depth)
+        .limit_complexity(config.limits.max_complexity)
+        .extension(config.limits.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_endpoint_keeps_its_root_type_names() {
+        let auth = AuthSchema::build(
+            AuthQuery::default(),
+            AuthMutation::default(),
+            EmptySubscription,
+        )
+        .finish()
+        .sdl();
+        let community = CommunitySchema::build(
+            CommunityQuery::default(),
+            CommunityMutation::default(),
+            EmptySubscription,
+        )
+        .finish()
+        .sdl();
+        let unified =
+            UnifiedSchema::build(Query::default(), Mutation::default(), EmptySubscription)
+                .finish()
+                .sdl();
+
+        for sdl in [&auth, &community, &unified] {
+            assert!(sdl.contains("type Query {"));
+            assert!(sdl.contains("type Mutation {"));
+            assert!(!sdl.contains("type AuthQuery"));
+            assert!(!sdl.contains("type CommunityQuery"));
+            assert_eq!(sdl.matches("type UserProfileResponse {").count(), 1);
+        }
+        assert!(auth.contains("\tlogin("));
+        assert!(community.contains("\tgetRecentFeed("));
+        assert!(unified.contains("\tlogin(") && unified.contains("\tgetRecentFeed("));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest},
+    Request, ServerError, ServerResult, Value,
+};
+use sha2::{Digest, Sha256};
+
+use crate::config::env_or;
+
+const DEFAULT_APQ_CACHE_SIZE: usize = 1000;
+
+/// Settings for Automatic Persisted Queries and the operation allowlist.
+#[derive(Debug, Clone)]
+pub struct PersistedQueries {
+    /// Maximum number of queries kept in each schema's APQ cache.
+    pub cache_size: usize,
+    /// Operations registered in the allowlist manifest, keyed by sha256 hash.
+    pub allowlist: Option<Arc<HashMap<String, String>>>,
+    /// When set, any operation missing from the allowlist is rejected.
+    pub strict: bool,
+}
+
+impl Default for PersistedQueries {
+    fn default() -> Self {
+        Self {
+            cache_size: DEFAULT_APQ_CACHE_SIZE,
+            allowlist: None,
+            strict: false,
+        }
+    }
+}
+
+impl PersistedQueries {
+    /// Reads `GRAPHQL_APQ_CACHE_SIZE`, `GRAPHQL_ALLOWLIST_PATH` and
+    /// `GRAPHQL_STRICT_ALLOWLIST`.
+    ///
+    /// The manifest is a JSON object mapping sha256 hashes to operation text,
+    /// the format produced by most persisted query tooling.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let cache_size = env_or("GRAPHQL_APQ_CACHE_SIZE", DEFAULT_APQ_CACHE_SIZE).max(1);
+        let strict = env_or("GRAPHQL_STRICT_ALLOWLIST", false);
+
+        let allowlist = match dotenv::var("GRAPHQL_ALLOWLIST_PATH") {
+            Ok(path) => Some(Arc::new(load_manifest(&path)?)),
+            Err(_) if strict => {
+                return Err("GRAPHQL_STRICT_ALLOWLIST requires GRAPHQL_ALLOWLIST_PATH".into())
+            }
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            cache_size,
+            allowlist,
+            strict,
+        })
+    }
+}
+
+fn load_manifest(path: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let manifest: HashMap<String, String> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    let mut queries = HashMap::with_capacity(manifest.len());
+    for (hash, query) in manifest {
+        let computed = sha256(&query);
+        if hash != computed {
+            return Err(format!("Allowlist entry {hash} does not match its query").into());
+        }
+        queries.insert(computed, query);
+    }
+
+    Ok(queries)
+}
+
+fn sha256(query: &str) -> String {
+    format!("{:x}", Sha256::digest(query.as_bytes()))
+}
+
+/// Hash sent by APQ clients in `extensions.persistedQuery.sha256Hash`.
+fn persisted_hash(request: &Request) -> Option<String> {
+    let Some(Value::Object(persisted)) = request.extensions.get("persistedQuery") else {
+        return None;
+    };
+    match persisted.get("sha256Hash") {
+        Some(Value::String(hash)) => Some(hash.clone()),
+        _ => None,
+    }
+}
+
+/// Resolves hash-only requests against the allowlist manifest and, in strict
+/// mode, rejects every operation that is not registered in it. Runs before
+/// the APQ extension so allowlisted hashes never miss the cache.
+pub struct Allowlist {
+    queries: Arc<HashMap<String, String>>,
+    strict: bool,
+}
+
+impl Allowlist {
+    pub fn new(queries: Arc<HashMap<String, String>>, strict: bool) -> Self {
+        Self { queries, strict }
+    }
+}
+
+impl ExtensionFactory for Allowlist {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(AllowlistExtension {
+            queries: Arc::clone(&self.queries),
+            strict: self.strict,
+        })
+    }
+}
+
+struct AllowlistExtension {
+    queries: Arc<HashMap<String, String>>,
+    strict: bool,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for AllowlistExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        mut request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let hash = if request.query.is_empty() {
+            persisted_hash(&request)
+        } else {
+            Some(sha256(&request.query))
+        };
+
+        match hash.and_then(|hash| self.queries.get(&hash)) {
+            Some(query) if request.query.is_empty() => request.query = query.clone(),
+            Some(_) => {}
+            None if self.strict => {
+                return Err(ServerError::new("Operation is not allowlisted.", None));
+            }
+            None => {}
+        }
+
+        next.run(ctx, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{EmptyMutation, EmptySubscription, Name, Object, Schema};
+
+    use super::*;
+
+    const QUERY: &str = "{ value }";
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn value(&self) -> i32 {
+            1
+        }
+    }
+
+    /// Writes `manifest` to a file of its own and loads it back.
+    fn load(name: &str, manifest: &HashMap<&str, &str>) -> Result<HashMap<String, String>, String> {
+        let path =
+            std::env::temp_dir().join(format!("allowlist-{name}-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(manifest).unwrap()).unwrap();
+        let loaded = load_manifest(path.to_str().unwrap()).map_err(|err| err.to_string());
+        std::fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    async fn error(
+        schema: &Schema<Query, EmptyMutation, EmptySubscription>,
+        request: Request,
+    ) -> Option<String> {
+        let response = schema.execute(request).await;
+        response.errors.first().map(|err| err.message.clone())
+    }
+
+    fn hash_only(hash: &str) -> Request {
+        let mut request = Request::new("");
+        request.extensions.insert(
+            "persistedQuery".to_string(),
+            Value::Object(
+                [(Name::new("sha256Hash"), Value::String(hash.to_string()))]
+                    .into_iter()
+                    .collect(),
+            ),
+        );
+        request
+    }
+
+    fn schema(strict: bool) -> Schema<Query, EmptyMutation, EmptySubscription> {
+        let queries = HashMap::from([(sha256(QUERY), QUERY.to_string())]);
+        Schema::build(Query, EmptyMutation, EmptySubscription)
+            .extension(Allowlist::new(Arc::new(queries), strict))
+            .finish()
+    }
+
+    #[test]
+    fn manifest_entries_must_match_their_hash() {
+        let hash = sha256(QUERY);
+        let loaded = load("valid", &HashMap::from([(hash.as_str(), QUERY)])).unwrap();
+        assert_eq!(loaded.get(&hash).map(String::as_str), Some(QUERY));
+
+        let err = load("mismatched", &HashMap::from([(hash.as_str(), "{ other }")])).unwrap_err();
+        assert!(err.contains("does not match its query"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn strict_allowlists_reject_unknown_operations() {
+        let schema = schema(true);
+        assert_eq!(error(&schema, Request::new(QUERY)).await, None);
+        assert_eq!(error(&schema, hash_only(&sha256(QUERY))).await, None);
+        assert_eq!(
+            error(&schema, Request::new("{ value value }"))
+                .await
+                .as_deref(),
+            Some("Operation is not allowlisted.")
+        );
+        assert_eq!(
+            error(&schema, hash_only(&sha256("{ value value }")))
+                .await
+                .as_deref(),
+            Some("Operation is not allowlisted.")
+        );
+    }
+
+    #[tokio::test]
+    async fn lenient_allowlists_only_resolve_known_hashes() {
+        let schema = schema(false);
+        assert_eq!(error(&schema, hash_only(&sha256(QUERY))).await, None);
+        assert_eq!(error(&schema, Request::new("{ value value }")).await, None);
+    }
+}
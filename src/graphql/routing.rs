@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery},
+    parser::types::{ExecutableDocument, OperationType},
+    ServerResult, Variables,
+};
+
+use crate::db::routing;
+
+/// Sends every read of a mutation request to the primary database and flags
+/// the request so the gateway can keep the viewer on the primary afterwards.
+pub struct ReadRouting;
+
+impl ExtensionFactory for ReadRouting {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ReadRoutingExtension)
+    }
+}
+
+struct ReadRoutingExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for ReadRoutingExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        // Documents with several operations are treated as a mutation if any
+        // of them is one
+        if document
+            .operations
+            .iter()
+            .any(|(_, operation)| operation.node.ty == OperationType::Mutation)
+        {
+            routing::mark_mutation();
+        }
+
+        Ok(document)
+    }
+}
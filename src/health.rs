@@ -0,0 +1,90 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::{auth, community::CacheStats, ApplicationState};
+
+#[derive(Serialize)]
+pub struct Liveness {
+    pub status: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct DependencyStatus {
+    pub ready: bool,
+    pub error: Option<String>,
+}
+
+impl From<Result<(), String>> for DependencyStatus {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self {
+                ready: true,
+                error: None,
+            },
+            Err(err) => Self {
+                ready: false,
+                error: Some(err),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub shutting_down: bool,
+    pub auth_db: DependencyStatus,
+    pub community_db: DependencyStatus,
+    pub migrations: DependencyStatus,
+    pub signing_keys: DependencyStatus,
+}
+
+/// Liveness probe. Answers as long as the process can serve requests at all.
+pub async fn healthz() -> impl IntoResponse {
+    Json(Liveness { status: "ok" })
+}
+
+/// Readiness probe. Reports the status of every dependency and answers 503
+/// when any of them is unavailable or the server is shutting down.
+pub async fn readyz(State(state): State<Arc<ApplicationState>>) -> impl IntoResponse {
+    let shutting_down = state.shutting_down.load(Ordering::SeqCst);
+
+    let (auth_db, community_db, migrations) = tokio::join!(
+        state.db.ping_auth(),
+        state.db.ping_community(),
+        state.db.check_migrations()
+    );
+    let signing_keys = if auth::signing_keys_loaded() {
+        Ok(())
+    } else {
+        Err("Token signing secrets are not configured".to_string())
+    };
+
+    let report = Readiness {
+        ready: !shutting_down
+            && auth_db.is_ok()
+            && community_db.is_ok()
+            && migrations.is_ok()
+            && signing_keys.is_ok(),
+        shutting_down,
+        auth_db: auth_db.into(),
+        community_db: community_db.into(),
+        migrations: migrations.into(),
+        signing_keys: signing_keys.into(),
+    };
+
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(report))
+}
+
+/// Hit and miss counts of the community read cache.
+pub async fn cachez(State(state): State<Arc<ApplicationState>>) -> Json<CacheStats> {
+    Json(state.db.cache.stats())
+}
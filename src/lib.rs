@@ -1,22 +1,69 @@
-use std::sync::Arc;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use async_graphql::{http::GraphiQLSource, EmptySubscription, OutputType, Schema, SimpleObject};
+use async_graphql::{
+    http::GraphiQLSource, EmptySubscription, OutputType, Schema, ServerError, SimpleObject,
+};
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{
     extract::State,
     response::{Html, IntoResponse},
 };
-use community::{ExpressionPost, ExpressionPostAggregate, Reply, UserProfile};
+use community::{
+    Bookmark, BookmarkCollectionList, BookmarkConnection, Circle, CircleList, CircleMember,
+    CircleMemberList, CommunityLoader, ExpressionPost, ExpressionPostAggregate,
+    ExpressionPostConnection, FollowConnection, PostDraft, PostDraftConnection, PostSearchResults,
+    Reply, ReplyConnection, ReplyEditHistory, Topic, TopicList, UserProfile,
+};
+use config::env_or;
 use db::DbController;
+use graphql::{AuthSchema, CommunitySchema, UnifiedSchema};
 use tower_cookies::Cookies;
 
 mod auth;
 mod community;
+mod config;
 mod db;
+mod graphql;
+mod health;
+mod tasks;
+
+pub use health::{cachez, healthz, readyz};
+pub use tasks::{BackgroundTasks, ShutdownListener};
+
+/// Default time allowed for in-flight requests and background tasks to finish.
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+/// Default time readiness reports 503 before the listener stops accepting
+/// connections, so load balancers stop routing to the server first.
+const DEFAULT_SHUTDOWN_DRAIN_DELAY_SECS: u64 = 5;
+/// Default time between account reconciliation runs.
+const DEFAULT_RECONCILE_INTERVAL_SECS: u64 = 300;
+/// Default number of orphaned accounts one reconciliation run may remove.
+const DEFAULT_MAX_ORPHAN_REMOVALS: usize = 10;
+/// Time between replica lag checks.
+const REPLICA_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Default time deleted posts and replies are kept before being purged.
+const DEFAULT_DELETION_RETENTION_DAYS: i64 = 30;
+/// Default time between purges of deleted posts and replies.
+const DEFAULT_PURGE_INTERVAL_SECS: u64 = 3600;
+/// Default time between full rescores of trending posts.
+const DEFAULT_TRENDING_REFRESH_INTERVAL_SECS: u64 = 3600;
+/// Default time between runs publishing the drafts that are due.
+const DEFAULT_DRAFT_PUBLISH_INTERVAL_SECS: u64 = 60;
 
 pub struct ApplicationState {
-    pub auth_schema: Schema<auth::Query, auth::Mutation, EmptySubscription>,
-    pub community_schema: Schema<community::Query, community::Mutation, EmptySubscription>,
+    pub auth_schema: AuthSchema,
+    pub community_schema: CommunitySchema,
+    pub unified_schema: Option<UnifiedSchema>,
+    pub tasks: BackgroundTasks,
+    db: Arc<DbController>,
+    shutting_down: AtomicBool,
 }
 
 impl ApplicationState {
@@ -27,19 +74,255 @@ impl ApplicationState {
                 .expect("Error initializing database"),
         );
 
-        let auth_schema = Schema::build(auth::Query, auth::Mutation, EmptySubscription)
+        let config =
+            graphql::GraphQLConfig::from_env().expect("Error loading GraphQL configuration");
+
+        let auth_schema = graphql::configure(
+            Schema::build(
+                graphql::AuthQuery::default(),
+                graphql::AuthMutation::default(),
+                EmptySubscription,
+            ),
+            &config,
+        )
+        .data(Arc::clone(&db))
+        .data(CommunityLoader::new(Arc::clone(&db)))
+        .finish();
+        let community_schema = graphql::configure(
+            Schema::build(
+                graphql::CommunityQuery::default(),
+                graphql::CommunityMutation::default(),
+                EmptySubscription,
+            ),
+            &config,
+        )
+        .data(Arc::clone(&db))
+        .data(CommunityLoader::new(Arc::clone(&db)))
+        .finish();
+        let unified_schema = config.unified_endpoint.then(|| {
+            graphql::configure(
+                Schema::build(
+                    graphql::Query::default(),
+                    graphql::Mutation::default(),
+                    EmptySubscription,
+                ),
+                &config,
+            )
             .data(Arc::clone(&db))
-            .finish();
-        let community_schema =
-            Schema::build(community::Query, community::Mutation, EmptySubscription)
-                .data(Arc::clone(&db))
-                .finish();
+            .data(CommunityLoader::new(Arc::clone(&db)))
+            .finish()
+        });
+
+        let tasks = BackgroundTasks::new();
+        if let Some(period) = interval_from_env(
+            "ACCOUNT_RECONCILE_INTERVAL_SECS",
+            DEFAULT_RECONCILE_INTERVAL_SECS,
+        ) {
+            let db = Arc::clone(&db);
+            tasks
+                .spawn_periodic("account-reconciler", period, move || {
+                    let db = Arc::clone(&db);
+                    async move {
+                        match auth::AccountSaga::reconcile(&db, orphan_policy(false)).await {
+                            Ok(report) => println!("RECONCILE: {report}"),
+                            Err(err) => eprintln!("RECONCILE_ERROR: {err}"),
+                        }
+                    }
+                })
+                .await;
+        }
+        if let Some(period) =
+            interval_from_env("DELETION_PURGE_INTERVAL_SECS", DEFAULT_PURGE_INTERVAL_SECS)
+        {
+            let db = Arc::clone(&db);
+            tasks
+                .spawn_periodic("deletion-purge", period, move || {
+                    let db = Arc::clone(&db);
+                    async move {
+                        match ExpressionPost::purge_deleted(&db, deletion_retention()).await {
+                            Ok((posts, replies)) => {
+                                println!("PURGE: Removed {posts} posts and {replies} replies")
+                            }
+                            Err(err) => eprintln!("PURGE_ERROR: {err}"),
+                        }
+                    }
+                })
+                .await;
+        }
+        if let Some(period) = interval_from_env(
+            "TRENDING_REFRESH_INTERVAL_SECS",
+            DEFAULT_TRENDING_REFRESH_INTERVAL_SECS,
+        ) {
+            let db = Arc::clone(&db);
+            tasks
+                .spawn_periodic("trending-scores", period, move || {
+                    let db = Arc::clone(&db);
+                    async move {
+                        match ExpressionPost::refresh_hot_scores(&db).await {
+                            Ok(posts) => println!("TRENDING: Rescored {posts} posts"),
+                            Err(err) => eprintln!("TRENDING_ERROR: {err}"),
+                        }
+                    }
+                })
+                .await;
+        }
+        if let Some(period) = interval_from_env(
+            "DRAFT_PUBLISH_INTERVAL_SECS",
+            DEFAULT_DRAFT_PUBLISH_INTERVAL_SECS,
+        ) {
+            let db = Arc::clone(&db);
+            tasks
+                .spawn_periodic("draft-publisher", period, move || {
+                    let db = Arc::clone(&db);
+                    async move {
+                        match PostDraft::publish_due(&db).await {
+                            Ok((0, 0)) => {}
+                            Ok((published, failed)) => {
+                                println!("PUBLISH: Published {published} drafts, {failed} failed")
+                            }
+                            Err(err) => eprintln!("PUBLISH_ERROR: {err}"),
+                        }
+                    }
+                })
+                .await;
+        }
+        if db.has_replicas() {
+            let db = Arc::clone(&db);
+            tasks
+                .spawn_periodic("replica-monitor", REPLICA_CHECK_INTERVAL, move || {
+                    let db = Arc::clone(&db);
+                    async move { db.check_replicas().await }
+                })
+                .await;
+        }
 
         Arc::new(ApplicationState {
             auth_schema,
             community_schema,
+            unified_schema,
+            tasks,
+            db,
+            shutting_down: AtomicBool::new(false),
         })
     }
+
+    /// Runs a GraphQL request with reads routed to replicas where allowed.
+    /// Viewers who just mutated something keep reading from the primaries
+    /// until the replicas have caught up with them.
+    async fn execute(
+        &self,
+        cookies: &Cookies,
+        request: impl Future<Output = async_graphql::Response>,
+    ) -> async_graphql::Response {
+        let pinned = viewer_id(cookies).is_some_and(|viewer| self.db.reads_from_primary(&viewer));
+        let (response, mutated) = db::routing::scope(pinned, request).await;
+
+        // Looked up again, since logging in or registering sets the cookie
+        if mutated {
+            if let Some(viewer) = viewer_id(cookies) {
+                self.db.note_write(&viewer);
+            }
+        }
+
+        response
+    }
+
+    /// Marks the server as shutting down so readiness checks start failing.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops background tasks and closes both database pools. Call once the
+    /// HTTP server has finished draining in-flight requests.
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.begin_shutdown();
+        self.tasks.shutdown(timeout).await;
+        self.db.close().await;
+    }
+}
+
+/// Community id of the logged in viewer, if any.
+fn viewer_id(cookies: &Cookies) -> Option<String> {
+    let cookie = cookies.get("sat")?;
+    auth::AccessToken::decode(cookie.value())
+        .ok()
+        .map(|claims| claims.sub)
+}
+
+/// Reads `SHUTDOWN_TIMEOUT_SECS`, falling back to 30 seconds.
+pub fn shutdown_timeout() -> Duration {
+    Duration::from_secs(env_or(
+        "SHUTDOWN_TIMEOUT_SECS",
+        DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+    ))
+}
+
+/// Reads an interval in seconds from `key`, falling back to `default_secs`.
+/// Zero turns the background task it paces off.
+fn interval_from_env(key: &str, default_secs: u64) -> Option<Duration> {
+    let secs = env_or(key, default_secs);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Orphaned accounts are only reported unless `remove` is set or
+/// `ACCOUNT_RECONCILE_REMOVE_ORPHANS` is true. Removals are capped by
+/// `ACCOUNT_RECONCILE_MAX_REMOVALS` per run.
+fn orphan_policy(remove: bool) -> auth::OrphanPolicy {
+    if remove || env_or("ACCOUNT_RECONCILE_REMOVE_ORPHANS", false) {
+        auth::OrphanPolicy::Remove(env_or(
+            "ACCOUNT_RECONCILE_MAX_REMOVALS",
+            DEFAULT_MAX_ORPHAN_REMOVALS,
+        ))
+    } else {
+        auth::OrphanPolicy::Report
+    }
+}
+
+/// Reads `SHUTDOWN_DRAIN_DELAY_SECS`, falling back to five seconds.
+pub fn shutdown_drain_delay() -> Duration {
+    Duration::from_secs(env_or(
+        "SHUTDOWN_DRAIN_DELAY_SECS",
+        DEFAULT_SHUTDOWN_DRAIN_DELAY_SECS,
+    ))
+}
+
+/// Reads `DELETION_RETENTION_DAYS`, falling back to 30 days.
+fn deletion_retention() -> chrono::Duration {
+    chrono::Duration::days(env_or(
+        "DELETION_RETENTION_DAYS",
+        DEFAULT_DELETION_RETENTION_DAYS,
+    ))
+}
+
+/// Resolves when the process receives SIGINT (Ctrl+C) or SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            eprintln!("SHUTDOWN: Unable to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => {
+                eprintln!("SHUTDOWN: Unable to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
 
 pub async fn auth_gateway(
@@ -48,8 +331,11 @@ pub async fn auth_gateway(
     req: GraphQLRequest,
 ) -> GraphQLResponse {
     let mut req = req.into_inner();
-    req = req.data(cookies);
-    state.auth_schema.execute(req).await.into()
+    req = req.data(cookies.clone());
+    state
+        .execute(&cookies, state.auth_schema.execute(req))
+        .await
+        .into()
 }
 
 pub async fn auth_playground() -> impl IntoResponse {
@@ -62,14 +348,110 @@ pub async fn community_gateway(
     req: GraphQLRequest,
 ) -> GraphQLResponse {
     let mut req = req.into_inner();
-    req = req.data(cookies);
-    state.community_schema.execute(req).await.into()
+    req = req.data(cookies.clone());
+    state
+        .execute(&cookies, state.community_schema.execute(req))
+        .await
+        .into()
 }
 
 pub async fn community_playground() -> impl IntoResponse {
     Html(GraphiQLSource::build().endpoint("/community").finish())
 }
 
+/// Serves the merged auth and community schema. Only routed when
+/// `UNIFIED_GRAPHQL_ENDPOINT` is enabled.
+pub async fn graphql_gateway(
+    cookies: Cookies,
+    State(state): State<Arc<ApplicationState>>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let Some(schema) = &state.unified_schema else {
+        return async_graphql::Response::from_errors(vec![ServerError::new(
+            "The unified GraphQL endpoint is disabled.",
+            None,
+        )])
+        .into();
+    };
+    let mut req = req.into_inner();
+    req = req.data(cookies.clone());
+    state.execute(&cookies, schema.execute(req)).await.into()
+}
+
+pub async fn graphql_playground() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+/// Runs `migrate up`, `migrate down` or `migrate status` against both
+/// databases without starting the server.
+pub async fn run_migrate_command(command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DbController::connect().await?;
+
+    match command {
+        "up" => {
+            db.migrate_up().await?;
+            println!("All migrations applied.");
+        }
+        "down" => {
+            let (auth, community) = db.migrate_down().await?;
+            for (name, reverted) in [("auth", auth), ("community", community)] {
+                match reverted {
+                    Some(version) => println!("{name}: reverted migration {version}"),
+                    None => println!("{name}: nothing to revert"),
+                }
+            }
+        }
+        "status" => {
+            let (auth, community) = db.migration_status().await?;
+            for (name, statuses) in [("auth", auth), ("community", community)] {
+                println!("{name}:");
+                for status in statuses {
+                    println!("  {status}");
+                }
+            }
+        }
+        _ => {
+            return Err(
+                format!("Unknown migrate command `{command}`. Use up, down or status.").into(),
+            )
+        }
+    }
+
+    db.close().await;
+    Ok(())
+}
+
+/// Runs one account reconciliation pass against both databases without
+/// starting the server. Orphaned accounts are only removed when
+/// `remove_orphans` is set or the environment opts in.
+pub async fn run_reconcile_command(remove_orphans: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DbController::connect().await?;
+    let report = auth::AccountSaga::reconcile(&db, orphan_policy(remove_orphans)).await?;
+    println!("{report}");
+    db.close().await;
+    Ok(())
+}
+
+/// Permanently removes posts and replies deleted longer ago than the
+/// retention period, without starting the server.
+pub async fn run_purge_command() -> Result<(), Box<dyn std::error::Error>> {
+    let db = DbController::connect().await?;
+    let (posts, replies) = ExpressionPost::purge_deleted(&db, deletion_retention()).await?;
+    println!("Removed {posts} posts and {replies} replies.");
+    db.close().await;
+    Ok(())
+}
+
+/// Recomputes the like and reply counters of every post, without starting
+/// the server.
+pub async fn run_repair_counts_command() -> Result<(), Box<dyn std::error::Error>> {
+    let db = DbController::connect().await?;
+    let repaired = ExpressionPost::repair_counts(&db).await?;
+    println!("Repaired the counters of {repaired} posts.");
+    db.close().await;
+    Ok(())
+}
+
 pub fn welcome() {
     println!("SPADE Mental Health API!");
 }
@@ -81,7 +463,29 @@ pub fn welcome() {
     name = "ExpressionPostAggregateResponse",
     params(ExpressionPostAggregate)
 ))]
+#[graphql(concrete(
+    name = "ExpressionPostConnectionResponse",
+    params(ExpressionPostConnection)
+))]
 #[graphql(concrete(name = "ReplyResponse", params(Reply)))]
+#[graphql(concrete(name = "ReplyConnectionResponse", params(ReplyConnection)))]
+#[graphql(concrete(name = "FollowConnectionResponse", params(FollowConnection)))]
+#[graphql(concrete(name = "ReplyEditHistoryResponse", params(ReplyEditHistory)))]
+#[graphql(concrete(name = "PostSearchResultsResponse", params(PostSearchResults)))]
+#[graphql(concrete(name = "TopicResponse", params(Topic)))]
+#[graphql(concrete(name = "TopicListResponse", params(TopicList)))]
+#[graphql(concrete(name = "CircleResponse", params(Circle)))]
+#[graphql(concrete(name = "CircleListResponse", params(CircleList)))]
+#[graphql(concrete(name = "CircleMemberResponse", params(CircleMember)))]
+#[graphql(concrete(name = "CircleMemberListResponse", params(CircleMemberList)))]
+#[graphql(concrete(name = "BookmarkResponse", params(Bookmark)))]
+#[graphql(concrete(name = "BookmarkConnectionResponse", params(BookmarkConnection)))]
+#[graphql(concrete(
+    name = "BookmarkCollectionListResponse",
+    params(BookmarkCollectionList)
+))]
+#[graphql(concrete(name = "PostDraftResponse", params(PostDraft)))]
+#[graphql(concrete(name = "PostDraftConnectionResponse", params(PostDraftConnection)))]
 pub struct GatewayResponse<T: OutputType> {
     pub success: bool,
     pub message: Option<String>,
@@ -4,23 +4,49 @@ use axum::{
     Router,
 };
 use spade_api::ApplicationState;
-use std::error::Error;
-use tokio::net::TcpListener;
+use std::{error::Error, future::IntoFuture, sync::Arc};
+use tokio::{net::TcpListener, sync::Notify};
 use tower_cookies::CookieManagerLayer;
 use tower_http::cors::CorsLayer;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // `spade_api migrate <up|down|status>` manages the schema and exits
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("migrate") {
+        let command = args.get(2).map(String::as_str).unwrap_or("status");
+        return spade_api::run_migrate_command(command).await;
+    }
+    // `spade_api reconcile [--remove-orphans]` repairs half finished
+    // registrations and deletions, and reports or removes orphaned accounts
+    if args.get(1).map(String::as_str) == Some("reconcile") {
+        let remove_orphans = args.get(2).map(String::as_str) == Some("--remove-orphans");
+        return spade_api::run_reconcile_command(remove_orphans).await;
+    }
+    // `spade_api purge` removes deleted posts and replies past their retention
+    if args.get(1).map(String::as_str) == Some("purge") {
+        return spade_api::run_purge_command().await;
+    }
+    // `spade_api repair-counts` recomputes the like and reply counters
+    if args.get(1).map(String::as_str) == Some("repair-counts") {
+        return spade_api::run_repair_counts_command().await;
+    }
+
     spade_api::welcome();
 
     let app_state = ApplicationState::init().await;
+    let shutdown_timeout = spade_api::shutdown_timeout();
+    let drain_delay = spade_api::shutdown_drain_delay();
 
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST])
         .allow_credentials(true)
         .allow_origin("http://localhost:5173".parse::<HeaderValue>().unwrap());
 
-    let app = Router::new()
+    let mut app = Router::new()
+        .route("/healthz", get(spade_api::healthz))
+        .route("/readyz", get(spade_api::readyz))
+        .route("/cachez", get(spade_api::cachez))
         .route(
             "/auth",
             get(spade_api::auth_playground).post(spade_api::auth_gateway),
@@ -28,14 +54,49 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .route(
             "/community",
             get(spade_api::community_playground).post(spade_api::community_gateway),
-        )
-        .with_state(app_state)
+        );
+
+    if app_state.unified_schema.is_some() {
+        app = app.route(
+            "/graphql",
+            get(spade_api::graphql_playground).post(spade_api::graphql_gateway),
+        );
+    }
+
+    let app = app
+        .with_state(Arc::clone(&app_state))
         .layer(cors)
         .layer(CookieManagerLayer::new());
 
     let listener = TcpListener::bind("127.0.0.1:8000").await?;
 
-    axum::serve(listener, app).await?;
+    // On SIGINT/SIGTERM, fail readiness first and keep serving for the drain
+    // delay so load balancers take the server out of rotation. Then stop
+    // accepting connections and give in-flight requests up to the shutdown
+    // timeout to finish.
+    let draining = Arc::new(Notify::new());
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let app_state = Arc::clone(&app_state);
+        let draining = Arc::clone(&draining);
+        async move {
+            spade_api::shutdown_signal().await;
+            println!("Shutting down. Reporting not ready for {drain_delay:?}...");
+            app_state.begin_shutdown();
+            tokio::time::sleep(drain_delay).await;
+            println!("Draining in-flight requests...");
+            draining.notify_one();
+        }
+    });
+
+    tokio::select! {
+        result = server.into_future() => result?,
+        _ = async {
+            draining.notified().await;
+            tokio::time::sleep(shutdown_timeout).await;
+        } => eprintln!("SHUTDOWN: In-flight requests did not finish in time."),
+    }
+
+    app_state.shutdown(shutdown_timeout).await;
 
     Ok(())
 }
@@ -0,0 +1,80 @@
+use std::{future::Future, time::Duration};
+
+use tokio::{
+    sync::{watch, Mutex},
+    task::JoinHandle,
+    time::Instant,
+};
+
+/// Receiver side of the shutdown signal handed to every background task.
+/// `changed()` resolves once the server starts shutting down.
+pub type ShutdownListener = watch::Receiver<bool>;
+
+/// Tracks long running background tasks so they can be stopped together when
+/// the server shuts down.
+pub struct BackgroundTasks {
+    shutdown: watch::Sender<bool>,
+    handles: Mutex<Vec<(&'static str, JoinHandle<()>)>>,
+}
+
+impl Default for BackgroundTasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundTasks {
+    pub fn new() -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            shutdown,
+            handles: Mutex::new(vec![]),
+        }
+    }
+
+    /// Spawns a task that receives a listener it must watch to exit cleanly.
+    pub async fn spawn<F, Fut>(&self, name: &'static str, task: F)
+    where
+        F: FnOnce(ShutdownListener) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task(self.shutdown.subscribe()));
+        self.handles.lock().await.push((name, handle));
+    }
+
+    /// Spawns a task that runs `job` every `period` until shutdown.
+    pub async fn spawn_periodic<F, Fut>(&self, name: &'static str, period: Duration, mut job: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn(name, move |mut shutdown| async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => job().await,
+                    _ = shutdown.changed() => break,
+                }
+            }
+        })
+        .await;
+    }
+
+    /// Signals every task to stop and waits up to `timeout` for all of them,
+    /// aborting the ones that do not finish in time.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let _ = self.shutdown.send(true);
+
+        let deadline = Instant::now() + timeout;
+        let handles = std::mem::take(&mut *self.handles.lock().await);
+        for (name, mut handle) in handles {
+            if tokio::time::timeout_at(deadline, &mut handle)
+                .await
+                .is_err()
+            {
+                eprintln!("SHUTDOWN: Background task {name} did not stop in time. Aborting.");
+                handle.abort();
+            }
+        }
+    }
+}